@@ -1,5 +1,15 @@
 //#[struct_layout::explicit(size = 1, align = 1)]
+use std::io::{self, Read, Write};
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
 use crate::bus::BusDevice;
+use crate::state::SaveState;
+use crate::system::Region;
+
+pub const SAMPLE_RATE: u32 = 44_100;
+// A second of samples, past that nobody is draining them and the oldest half goes
+pub const MAX_SAMPLES: usize = SAMPLE_RATE as usize;
 
 pub struct Alu2A03 {
     // #[field(offset = 0)]
@@ -54,10 +64,55 @@ pub struct Alu2A03 {
     // pub joy2: u8,
 
     pub fake_status: u8,
+
+    // Picks the CPU clock the samples are timed against
+    pub region: Region,
+
+    // Mixed output at SAMPLE_RATE, drained by whoever is playing or recording it, never more than MAX_SAMPLES
+    pub samples: Vec<f32>,
+    sample_clock: f64,
+}
+
+impl Alu2A03 {
+    pub fn new() -> Self {
+        Self {
+            fake_status: 0x00,
+            region: Region::Ntsc,
+            samples: Vec::new(),
+            sample_clock: 0.0,
+        }
+    }
+
+    pub fn with_region(region: Region) -> Self {
+        Self { region, ..Self::new() }
+    }
+
+    pub fn cycle(&mut self) {
+        let cpu_clock = self.region.profile().cpu_clock();
+        self.sample_clock += SAMPLE_RATE as f64;
+        if self.sample_clock >= cpu_clock {
+            self.sample_clock -= cpu_clock;
+            if self.samples.len() >= MAX_SAMPLES {
+                self.samples.drain(..MAX_SAMPLES / 2);
+            }
+            self.samples.push(self.output());
+        }
+    }
+
+    fn output(&self) -> f32 {
+        // None of the channels are emulated yet so the mixer is silent
+        0.0
+    }
+}
+
+impl Default for Alu2A03 {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl BusDevice for Alu2A03{
-    fn read(&self, address: u16) -> u8 {
+    fn read(&mut self, address: u16) -> u8 {
         //println!("APU READ!! ${:04X} {}", address, self.fake_status);
         if address == 0x4015 {
             return self.fake_status;
@@ -71,4 +126,30 @@ impl BusDevice for Alu2A03{
             self.fake_status = data;
         }
     }
-}
\ No newline at end of file
+
+    fn peek(&self, address: u16) -> u8 {
+        match address {
+            0x4015 => self.fake_status,
+            _ => 0,
+        }
+    }
+
+    fn poke(&mut self, address: u16, data: u8) {
+        if address == 0x4015 {
+            self.fake_status = data;
+        }
+    }
+}
+
+impl SaveState for Alu2A03 {
+    fn save_state(&self, writer: &mut dyn Write) -> io::Result<()> {
+        writer.write_u8(self.fake_status)?;
+        writer.write_f64::<LittleEndian>(self.sample_clock)
+    }
+
+    fn load_state(&mut self, reader: &mut dyn Read) -> io::Result<()> {
+        self.fake_status = reader.read_u8()?;
+        self.sample_clock = reader.read_f64::<LittleEndian>()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,59 @@
+#[cfg(feature = "audio")]
+mod device;
+mod rate_control;
+mod sinks;
+
+use std::any::Any;
+
+use crate::apu::SAMPLE_RATE;
+
+#[cfg(feature = "audio")]
+pub use self::device::DeviceSink;
+pub use self::{
+    rate_control::{DynamicRateControl, MAX_RATE_ADJUSTMENT},
+    sinks::{NullSink, WavSink},
+};
+
+// Somewhere the APU's samples end up, after they've been resampled to the sink's rate
+pub trait AudioSink: Any {
+    fn sample_rate(&self) -> u32;
+    // Samples written and not played yet
+    fn queued(&self) -> usize;
+    // How many samples it holds before it starts dropping them. Sinks that aren't played back against a clock have no
+    // capacity and get samples at exactly their rate.
+    fn capacity(&self) -> usize;
+    fn write(&mut self, samples: &[f32]);
+}
+
+// Takes the APU's output to a sink, nudging the resampling ratio to keep the sink's buffer half full. The emulator runs
+// at the display's pace rather than the sound card's, so without that the buffer slowly runs dry or overflows.
+pub struct AudioOutput {
+    sink: Box<dyn AudioSink>,
+    pub rate_control: DynamicRateControl,
+}
+
+impl AudioOutput {
+    pub fn new(sink: Box<dyn AudioSink>) -> Self {
+        let rate_control = DynamicRateControl::new(SAMPLE_RATE, sink.sample_rate());
+        Self { sink, rate_control }
+    }
+
+    pub fn push(&mut self, samples: &[f32]) {
+        let fill = self.fill();
+        let resampled = self.rate_control.resample(samples, fill);
+        self.sink.write(&resampled);
+    }
+
+    // How full the sink's buffer is, 0 to 1, or None if it doesn't have one
+    pub fn fill(&self) -> Option<f64> {
+        match self.sink.capacity() {
+            0 => None,
+            capacity => Some(self.sink.queued() as f64 / capacity as f64),
+        }
+    }
+
+    pub fn sink_mut<T: AudioSink>(&mut self) -> Option<&mut T> {
+        let sink: &mut dyn Any = self.sink.as_mut();
+        sink.downcast_mut::<T>()
+    }
+}
@@ -0,0 +1,86 @@
+use std::{
+    collections::VecDeque,
+    io,
+    sync::{Arc, Mutex},
+};
+
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+
+use super::AudioSink;
+
+// Output buffer length, rate control aims to keep it half full
+const BUFFER_MILLISECONDS: usize = 80;
+
+// The default output device. The stream's callback plays from a shared queue and fills in silence when it runs dry.
+pub struct DeviceSink {
+    _stream: cpal::Stream,
+    queue: Arc<Mutex<VecDeque<f32>>>,
+    sample_rate: u32,
+    capacity: usize,
+}
+
+impl DeviceSink {
+    pub fn open() -> io::Result<Self> {
+        let device = cpal::default_host()
+            .default_output_device()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no audio output device"))?;
+        let supported = device.default_output_config().map_err(io::Error::other)?;
+        let format = supported.sample_format();
+        let config: cpal::StreamConfig = supported.into();
+
+        let sample_rate = config.sample_rate.0;
+        let capacity = sample_rate as usize * BUFFER_MILLISECONDS / 1000;
+        let queue = Arc::new(Mutex::new(VecDeque::with_capacity(capacity)));
+        let stream = match format {
+            cpal::SampleFormat::I16 => build_stream::<i16>(&device, &config, queue.clone()),
+            cpal::SampleFormat::U16 => build_stream::<u16>(&device, &config, queue.clone()),
+            cpal::SampleFormat::F32 => build_stream::<f32>(&device, &config, queue.clone()),
+        }?;
+        stream.play().map_err(io::Error::other)?;
+
+        Ok(Self {
+            _stream: stream,
+            queue,
+            sample_rate,
+            capacity,
+        })
+    }
+}
+
+fn build_stream<T: cpal::Sample>(device: &cpal::Device, config: &cpal::StreamConfig, queue: Arc<Mutex<VecDeque<f32>>>) -> io::Result<cpal::Stream> {
+    let channels = config.channels as usize;
+    device
+        .build_output_stream(
+            config,
+            move |data: &mut [T], _| {
+                let mut queue = queue.lock().unwrap();
+                // Mono, so every channel of a frame gets the same sample
+                for frame in data.chunks_mut(channels) {
+                    let sample = queue.pop_front().unwrap_or(0.0);
+                    frame.fill(T::from(&sample));
+                }
+            },
+            |e| log::warn!("Audio stream error: {}", e),
+        )
+        .map_err(io::Error::other)
+}
+
+impl AudioSink for DeviceSink {
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn queued(&self) -> usize {
+        self.queue.lock().unwrap().len()
+    }
+
+    fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    fn write(&mut self, samples: &[f32]) {
+        let mut queue = self.queue.lock().unwrap();
+        let fits = samples.len().min(self.capacity - queue.len().min(self.capacity));
+        queue.extend(&samples[..fits]);
+    }
+}
@@ -0,0 +1,53 @@
+// Furthest the ratio gets pushed either way, small enough that the pitch change can't be heard
+pub const MAX_RATE_ADJUSTMENT: f64 = 0.005;
+
+// Linear interpolation resampler whose ratio follows how full the output buffer is: a little faster when it's emptying,
+// a little slower when it's filling up
+pub struct DynamicRateControl {
+    input_rate: f64,
+    output_rate: f64,
+    pub max_adjustment: f64,
+    // Output samples per input sample used on the last resample
+    pub ratio: f64,
+    // Where the next output sample falls between `previous` and the next input sample
+    position: f64,
+    previous: f32,
+}
+
+impl DynamicRateControl {
+    pub fn new(input_rate: u32, output_rate: u32) -> Self {
+        Self {
+            input_rate: input_rate as f64,
+            output_rate: output_rate as f64,
+            max_adjustment: MAX_RATE_ADJUSTMENT,
+            ratio: output_rate as f64 / input_rate as f64,
+            position: 0.0,
+            previous: 0.0,
+        }
+    }
+
+    pub fn nominal_ratio(&self) -> f64 {
+        self.output_rate / self.input_rate
+    }
+
+    // `fill` is how full the output buffer is from 0 to 1, None leaves the ratio alone
+    pub fn resample(&mut self, input: &[f32], fill: Option<f64>) -> Vec<f32> {
+        let adjustment = match fill {
+            Some(fill) => (1.0 - 2.0 * fill.clamp(0.0, 1.0)) * self.max_adjustment,
+            None => 0.0,
+        };
+        self.ratio = self.nominal_ratio() * (1.0 + adjustment);
+        let step = 1.0 / self.ratio;
+
+        let mut output = Vec::with_capacity((input.len() as f64 * self.ratio) as usize + 1);
+        for sample in input {
+            while self.position < 1.0 {
+                output.push(self.previous + (sample - self.previous) * self.position as f32);
+                self.position += step;
+            }
+            self.position -= 1.0;
+            self.previous = *sample;
+        }
+        output
+    }
+}
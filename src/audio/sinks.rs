@@ -0,0 +1,120 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use byteorder::{LittleEndian, WriteBytesExt};
+
+use super::AudioSink;
+
+// Throws samples away, but keeps count and can pretend to be a sound card being played at a steady rate
+pub struct NullSink {
+    sample_rate: u32,
+    capacity: usize,
+    queued: usize,
+    pub written: usize,
+    // Samples that didn't fit in the buffer
+    pub dropped: usize,
+}
+
+impl NullSink {
+    pub fn new(sample_rate: u32) -> Self {
+        Self::with_capacity(sample_rate, 0)
+    }
+
+    pub fn with_capacity(sample_rate: u32, capacity: usize) -> Self {
+        Self {
+            sample_rate,
+            capacity,
+            queued: 0,
+            written: 0,
+            dropped: 0,
+        }
+    }
+
+    // Takes `count` samples off the buffer as if they'd been played, returning how many there were to play
+    pub fn play(&mut self, count: usize) -> usize {
+        let played = count.min(self.queued);
+        self.queued -= played;
+        played
+    }
+}
+
+impl AudioSink for NullSink {
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn queued(&self) -> usize {
+        self.queued
+    }
+
+    fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    fn write(&mut self, samples: &[f32]) {
+        self.written += samples.len();
+        if self.capacity > 0 {
+            let fits = samples.len().min(self.capacity - self.queued);
+            self.queued += fits;
+            self.dropped += samples.len() - fits;
+        }
+    }
+}
+
+// Keeps everything to be saved as a WAV file afterwards
+pub struct WavSink {
+    sample_rate: u32,
+    pub samples: Vec<f32>,
+}
+
+impl WavSink {
+    pub fn new(sample_rate: u32) -> Self {
+        Self { sample_rate, samples: Vec::new() }
+    }
+
+    // 16-bit mono PCM
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        let data_size = self.samples.len() as u32 * 2;
+
+        out.write_all(b"RIFF")?;
+        out.write_u32::<LittleEndian>(36 + data_size)?;
+        out.write_all(b"WAVEfmt ")?;
+        out.write_u32::<LittleEndian>(16)?;
+        out.write_u16::<LittleEndian>(1)?;
+        out.write_u16::<LittleEndian>(1)?;
+        out.write_u32::<LittleEndian>(self.sample_rate)?;
+        out.write_u32::<LittleEndian>(self.sample_rate * 2)?;
+        out.write_u16::<LittleEndian>(2)?;
+        out.write_u16::<LittleEndian>(16)?;
+        out.write_all(b"data")?;
+        out.write_u32::<LittleEndian>(data_size)?;
+
+        for sample in self.samples.iter() {
+            out.write_i16::<LittleEndian>((sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16)?;
+        }
+
+        out.flush()
+    }
+}
+
+impl AudioSink for WavSink {
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn queued(&self) -> usize {
+        0
+    }
+
+    fn capacity(&self) -> usize {
+        0
+    }
+
+    fn write(&mut self, samples: &[f32]) {
+        self.samples.extend_from_slice(samples);
+    }
+}
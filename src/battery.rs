@@ -0,0 +1,72 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::system::ConsoleSystem;
+
+pub const DEFAULT_FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+
+// Keeps a cartridge's battery-backed memory in a .sav file next to the ROM
+pub struct BatterySave {
+    pub path: PathBuf,
+    pub flush_interval: Duration,
+    saved: Option<Vec<u8>>,
+    last_flush: Instant,
+}
+
+impl BatterySave {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            flush_interval: DEFAULT_FLUSH_INTERVAL,
+            saved: None,
+            last_flush: Instant::now(),
+        }
+    }
+
+    pub fn for_rom(rom_path: &Path) -> Self {
+        Self::new(rom_path.with_extension("sav"))
+    }
+
+    // Returns false when there's nothing to load, either no file yet or no battery on the cartridge
+    pub fn load(&mut self, system: &mut ConsoleSystem) -> io::Result<bool> {
+        if system.battery_ram().is_none() {
+            return Ok(false);
+        }
+
+        let data = match fs::read(&self.path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(e),
+        };
+
+        system.load_battery_ram(&data)?;
+        self.saved = Some(data);
+        Ok(true)
+    }
+
+    // Writes the file if the RAM changed since the last flush
+    pub fn flush(&mut self, system: &ConsoleSystem) -> io::Result<bool> {
+        self.last_flush = Instant::now();
+        let data = match system.battery_ram() {
+            Some(data) if self.saved.as_ref() != Some(&data) => data,
+            _ => return Ok(false),
+        };
+
+        // Write beside the real file and swap it in so a crash mid-write can't eat the save
+        let temp = self.path.with_extension("sav.tmp");
+        fs::write(&temp, &data)?;
+        fs::rename(&temp, &self.path)?;
+        self.saved = Some(data);
+        Ok(true)
+    }
+
+    pub fn flush_if_due(&mut self, system: &ConsoleSystem) -> io::Result<bool> {
+        if self.last_flush.elapsed() < self.flush_interval {
+            return Ok(false);
+        }
+
+        self.flush(system)
+    }
+}
@@ -0,0 +1,83 @@
+use std::{
+    env,
+    fs::File,
+    io::{self, BufRead, Write},
+    path::Path,
+    process,
+};
+
+use nes::{
+    debugger::{Debugger, GdbServer},
+    disasm::Labels,
+    roms::RomImage,
+    system::ConsoleSystem,
+};
+
+// debugger <rom> [--gdb port] [symbol files...]
+fn main() {
+    let mut args = env::args().skip(1);
+    let rom_path = args.next().unwrap_or_else(|| {
+        eprintln!("usage: debugger <rom> [--gdb port] [labels.dbg|.nl|.mlb ...]");
+        process::exit(2);
+    });
+
+    let mut rom_file = File::open(&rom_path).expect("Failed to open ROM");
+    let image = RomImage::from(&mut rom_file).expect("Failed to load ROM");
+    let mut system = ConsoleSystem::new(image);
+    system.reset();
+
+    let mut debugger = Debugger::new(system);
+    let mut gdb_port = None;
+    while let Some(path) = args.next() {
+        if path == "--gdb" {
+            gdb_port = args.next().and_then(|port| port.parse::<u16>().ok());
+            continue;
+        }
+        match Labels::load(Path::new(&path)) {
+            Ok(labels) => debugger.labels.merge(labels),
+            Err(e) => eprintln!("Couldn't load {}: {}", path, e),
+        }
+    }
+
+    // Get through the reset sequence so there's an instruction to look at
+    debugger.step_instruction();
+
+    if let Some(port) = gdb_port {
+        println!("Waiting for gdb on 127.0.0.1:{}", port);
+        let mut server = GdbServer::new(debugger);
+        if let Err(e) = server.listen(("127.0.0.1", port)) {
+            eprintln!("gdb connection failed: {}", e);
+            process::exit(1);
+        }
+        return;
+    }
+
+    println!("{}", debugger.execute("r").unwrap_or_default());
+
+    let stdin = io::stdin();
+    let mut last = String::new();
+    loop {
+        print!("> ");
+        let _ = io::stdout().flush();
+
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line).unwrap_or(0) == 0 {
+            break;
+        }
+
+        // An empty line repeats the last command, like gdb
+        let line = match line.trim() {
+            "" => last.clone(),
+            line => line.to_string(),
+        };
+        if line == "q" || line == "quit" {
+            break;
+        }
+
+        match debugger.execute(&line) {
+            Ok(output) => println!("{}", output.trim_end()),
+            Err(e) => println!("{}", e),
+        }
+        last = line;
+    }
+}
@@ -1,20 +1,260 @@
+use std::{env, fs::{self, File}, io::{self, BufWriter}, num::NonZeroU32, path::{Path, PathBuf}, time::{Duration, Instant}};
+
+use nes::{
+    audio::AudioOutput,
+    battery::BatterySave,
+    bindings::{Binding, Hotkey, InputConfig, InputMapper},
+    cheats::Cheats,
+    palette::{Palette, PALETTE_ENTRIES},
+    ppu::{PPU, SCREEN_HEIGHT, SCREEN_WIDTH},
+    roms::RomImage,
+    system::{ConsoleSystem, RewindSettings},
+};
+use wgpu::util::DeviceExt;
 use winit::{
     event::*,
     event_loop::{ControlFlow, EventLoop},
     window::{WindowBuilder, Window},
 };
 
+// Past this many frames behind it gives up catching up instead of fast forwarding
+const MAX_FRAMES_PER_REFRESH: u32 = 4;
+const STATE_SLOTS: u8 = 10;
+const INPUT_CONFIG: &str = "input.toml";
+
+struct Game {
+    system: ConsoleSystem,
+    battery: BatterySave,
+    rom_path: PathBuf,
+    slot: u8,
+    paused: bool,
+    // Run one frame while paused
+    advance: bool,
+}
+
 // Using https://github.com/jack1232/wgpu-step-by-step as example
 fn main() {
 
     let event_loop = EventLoop::new();
-    let window = winit::window::Window::new(&event_loop).unwrap(); 
+    let window = winit::window::Window::new(&event_loop).unwrap();
     window.set_title("Some Shit Rust NES Emulator");
     env_logger::init();
-    pollster::block_on( run(event_loop, window));    
+    let game = env::args().nth(1).map(|path| load_game(&PathBuf::from(path)));
+    pollster::block_on( run(event_loop, window, game, load_input_config()));
+}
+
+fn load_input_config() -> InputConfig {
+    match InputConfig::load(Path::new(INPUT_CONFIG)) {
+        Ok(config) => config,
+        Err(e) => {
+            if e.kind() != io::ErrorKind::NotFound {
+                log::warn!("Couldn't load {}, using the default bindings: {}", INPUT_CONFIG, e);
+            }
+            InputConfig::new()
+        }
+    }
+}
+
+fn load_game(rom_path: &Path) -> Game {
+    let mut rom_file = File::open(rom_path).expect("Failed to open ROM");
+    let image = RomImage::from(&mut rom_file).expect("Failed to load ROM");
+    let mut system = ConsoleSystem::new(image);
+    system.reset();
+    system.enable_rewind(RewindSettings::new());
+
+    let mut battery = BatterySave::for_rom(rom_path);
+    if let Err(e) = battery.load(&mut system) {
+        log::warn!("Couldn't load {}: {}", battery.path.display(), e);
+    }
+
+    // Cheats go in a .cht next to the ROM, like FCEUX keeps them
+    let cheats_path = rom_path.with_extension("cht");
+    match Cheats::load(&cheats_path) {
+        Ok(cheats) => system.cpu.cheats = Some(cheats),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => log::warn!("Couldn't load {}: {}", cheats_path.display(), e),
+    }
+
+    Game {
+        system,
+        battery,
+        rom_path: rom_path.to_path_buf(),
+        slot: 0,
+        paused: false,
+        advance: false,
+    }
+}
+
+#[cfg(feature = "audio")]
+fn open_audio() -> Option<AudioOutput> {
+    match nes::audio::DeviceSink::open() {
+        Ok(sink) => Some(AudioOutput::new(Box::new(sink))),
+        Err(e) => {
+            log::warn!("Running without sound: {}", e);
+            None
+        }
+    }
+}
+
+// Built without the audio feature
+#[cfg(not(feature = "audio"))]
+fn open_audio() -> Option<AudioOutput> {
+    None
+}
+
+fn flush_battery(game: &mut Option<Game>, force: bool) {
+    if let Some(game) = game {
+        let result = match force {
+            true => game.battery.flush(&game.system),
+            false => game.battery.flush_if_due(&game.system),
+        };
+
+        if let Err(e) = result {
+            log::warn!("Couldn't save {}: {}", game.battery.path.display(), e);
+        }
+    }
+}
+
+impl Game {
+    fn state_path(&self) -> PathBuf {
+        self.rom_path.with_extension(format!("state{}", self.slot))
+    }
+
+    fn hotkey(&mut self, hotkey: Hotkey) {
+        match hotkey {
+            Hotkey::Pause => self.paused = !self.paused,
+            Hotkey::Reset => self.system.reset(),
+            Hotkey::SaveState => {
+                let path = self.state_path();
+                match fs::write(&path, self.system.save_state()) {
+                    Ok(()) => log::info!("Saved {}", path.display()),
+                    Err(e) => log::warn!("Couldn't save {}: {}", path.display(), e),
+                }
+            }
+            Hotkey::LoadState => {
+                let path = self.state_path();
+                match fs::read(&path).and_then(|data| self.system.load_state(&data)) {
+                    Ok(()) => log::info!("Loaded {}", path.display()),
+                    Err(e) => log::warn!("Couldn't load {}: {}", path.display(), e),
+                }
+            }
+            Hotkey::NextSlot | Hotkey::PreviousSlot => {
+                let step = if hotkey == Hotkey::NextSlot { 1 } else { STATE_SLOTS - 1 };
+                self.slot = (self.slot + step) % STATE_SLOTS;
+                log::info!("State slot {}", self.slot);
+            }
+            Hotkey::FastForward | Hotkey::Rewind => {}
+            Hotkey::FrameAdvance => {
+                self.paused = true;
+                self.advance = true;
+            }
+            Hotkey::Screenshot => {
+                let frame = self.system.cpu.mapper.get_ppu().frame;
+                let path = self.rom_path.with_extension(format!("{}.png", frame));
+                match self.screenshot(&path) {
+                    Ok(()) => log::info!("Saved {}", path.display()),
+                    Err(e) => log::warn!("Couldn't save {}: {}", path.display(), e),
+                }
+            }
+        }
+    }
+
+    fn screenshot(&mut self, path: &Path) -> io::Result<()> {
+        let palette = Palette::new();
+        let ppu = self.system.cpu.mapper.get_ppu();
+        let pixels: Vec<u8> = ppu.framebuffer.iter().flat_map(|pixel| palette.rgb(*pixel)).collect();
+
+        let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?), SCREEN_WIDTH as u32, SCREEN_HEIGHT as u32);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().map_err(io::Error::other)?;
+        writer.write_image_data(&pixels).map_err(io::Error::other)
+    }
+
+    fn run_frame(&mut self, input: &InputMapper, audio: Option<&mut AudioOutput>) {
+        input.apply(&mut self.system);
+        let result = self.system.run_frame();
+        if let Some(audio) = audio {
+            audio.push(&result.samples);
+        }
+    }
 }
 
-pub async fn run(event_loop: EventLoop<()>, window: Window) {
+#[cfg(feature = "gamepad")]
+fn poll_gamepads(gilrs: &mut Option<gilrs::Gilrs>, input: &mut InputMapper, game: &mut Option<Game>) {
+    let Some(gilrs) = gilrs else { return };
+    while let Some(gilrs::Event { id, event, .. }) = gilrs.next_event() {
+        match event {
+            gilrs::EventType::ButtonPressed(button, _) => {
+                let hotkeys = input.press(Binding::Gamepad(id.into(), format!("{:?}", button)));
+                if let Some(game) = game.as_mut() {
+                    hotkeys.into_iter().for_each(|hotkey| game.hotkey(hotkey));
+                }
+            }
+            gilrs::EventType::ButtonReleased(button, _) => input.release(&Binding::Gamepad(id.into(), format!("{:?}", button))),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(feature = "gamepad")]
+fn open_gamepads() -> Option<gilrs::Gilrs> {
+    gilrs::Gilrs::new().map_err(|e| log::warn!("Running without gamepads: {}", e)).ok()
+}
+
+fn create_texture(device: &wgpu::Device, width: u32, height: u32, format: wgpu::TextureFormat) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: None,
+        size: wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+    })
+}
+
+fn write_texture(queue: &wgpu::Queue, texture: &wgpu::Texture, data: &[u8], width: u32, height: u32, bytes_per_texel: u32) {
+    queue.write_texture(
+        wgpu::ImageCopyTexture {
+            texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        data,
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: NonZeroU32::new(width * bytes_per_texel),
+            rows_per_image: NonZeroU32::new(height),
+        },
+        wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+    );
+}
+
+fn upload_framebuffer(queue: &wgpu::Queue, texture: &wgpu::Texture, ppu: &PPU) {
+    let data: Vec<u8> = ppu.framebuffer.iter().flat_map(|pixel| pixel.to_le_bytes()).collect();
+    write_texture(queue, texture, &data, SCREEN_WIDTH as u32, SCREEN_HEIGHT as u32, 2);
+}
+
+fn texture_entry(binding: u32, sample_type: wgpu::TextureSampleType) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Texture {
+            sample_type,
+            view_dimension: wgpu::TextureViewDimension::D2,
+            multisampled: false,
+        },
+        count: None,
+    }
+}
+
+fn screen_size(width: u32, height: u32) -> Vec<u8> {
+    [width as f32, height as f32].iter().flat_map(|size| size.to_le_bytes()).collect()
+}
+
+async fn run(event_loop: EventLoop<()>, window: Window, mut game: Option<Game>, input_config: InputConfig) {
     let size = window.inner_size();
     let instance = wgpu::Instance::new(wgpu::Backends::PRIMARY);
     let surface = unsafe { instance.create_surface(&window) };
@@ -45,7 +285,8 @@ pub async fn run(event_loop: EventLoop<()>, window: Window) {
         format: format,
         width: size.width,
         height: size.height,
-        present_mode: wgpu::PresentMode::Mailbox,
+        // Waits for vsync so each redraw lines up with a display refresh
+        present_mode: wgpu::PresentMode::Fifo,
     };
     surface.configure(&device, &config);
 
@@ -54,10 +295,52 @@ pub async fn run(event_loop: EventLoop<()>, window: Window) {
         label: None,
         source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/shader.wgsl").into()),
     });
-    
+
+    let frame_texture = create_texture(&device, SCREEN_WIDTH as u32, SCREEN_HEIGHT as u32, wgpu::TextureFormat::R16Uint);
+    let palette_rows = (PALETTE_ENTRIES / 64) as u32;
+    let palette_texture = create_texture(&device, 64, palette_rows, wgpu::TextureFormat::Rgba8UnormSrgb);
+    let colours: Vec<u8> = Palette::new().colours().iter().flat_map(|[r, g, b]| [*r, *g, *b, 0xff]).collect();
+    write_texture(&queue, &palette_texture, &colours, 64, palette_rows, 4);
+
+    let screen_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: None,
+        contents: &screen_size(size.width, size.height),
+        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+    });
+
+    let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: None,
+        entries: &[
+            texture_entry(0, wgpu::TextureSampleType::Uint),
+            texture_entry(1, wgpu::TextureSampleType::Float { filterable: false }),
+            wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+        ],
+    });
+
+    let frame_view = frame_texture.create_view(&wgpu::TextureViewDescriptor::default());
+    let palette_view = palette_texture.create_view(&wgpu::TextureViewDescriptor::default());
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: &bind_group_layout,
+        entries: &[
+            wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(&frame_view) },
+            wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::TextureView(&palette_view) },
+            wgpu::BindGroupEntry { binding: 2, resource: screen_buffer.as_entire_binding() },
+        ],
+    });
+
     let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
         label: None,
-        bind_group_layouts: &[],
+        bind_group_layouts: &[&bind_group_layout],
         push_constant_ranges: &[],
     });
 
@@ -91,22 +374,110 @@ pub async fn run(event_loop: EventLoop<()>, window: Window) {
         multiview: None,
     });
 
-    event_loop.run(move |event, _, control_flow| {       
+    // Emulated time owed, paid off a whole frame at a time so the game runs at its own rate whatever the display's is
+    let frame_time = game.as_ref()
+        .map(|game| Duration::from_secs_f64(1.0 / game.system.region.profile().frame_rate()))
+        .unwrap_or(Duration::MAX);
+    let mut owed = Duration::ZERO;
+    let mut last_redraw = Instant::now();
+    let mut audio = game.as_ref().and_then(|_| open_audio());
+    let mut input = InputMapper::new(input_config);
+    #[cfg(feature = "gamepad")]
+    let mut gilrs = open_gamepads();
+
+    event_loop.run(move |event, _, control_flow| {
         let _ = (&instance, &adapter, &shader, &pipeline_layout);
-        *control_flow = ControlFlow::Wait;
+        *control_flow = ControlFlow::Poll;
 
         match event {
             Event::WindowEvent {
                 event: WindowEvent::Resized(size),
                 ..
             } => {
-                // Recreate the surface with the new size
-                config.width = size.width;
-                config.height = size.height;
-                surface.configure(&device, &config);
+                // Recreate the surface with the new size, minimised windows have nothing to draw to
+                if size.width > 0 && size.height > 0 {
+                    config.width = size.width;
+                    config.height = size.height;
+                    surface.configure(&device, &config);
+                    queue.write_buffer(&screen_buffer, 0, &screen_size(size.width, size.height));
+                }
+            }
+            Event::WindowEvent {
+                event: WindowEvent::KeyboardInput {
+                    input: KeyboardInput { state, virtual_keycode: Some(key), .. },
+                    ..
+                },
+                ..
+            } => {
+                let binding = Binding::Key(format!("{:?}", key));
+                match state {
+                    ElementState::Pressed => {
+                        let hotkeys = input.press(binding);
+                        if let Some(game) = game.as_mut() {
+                            hotkeys.into_iter().for_each(|hotkey| game.hotkey(hotkey));
+                        }
+                    }
+                    ElementState::Released => input.release(&binding),
+                }
+            }
+            // Releases that happen while the window isn't focused never arrive
+            Event::WindowEvent {
+                event: WindowEvent::Focused(false),
+                ..
+            } => input.release_all(),
+            Event::MainEventsCleared => {
+                #[cfg(feature = "gamepad")]
+                poll_gamepads(&mut gilrs, &mut input, &mut game);
+                window.request_redraw();
             }
             Event::RedrawRequested(_) => {
-                let frame = surface.get_current_texture().unwrap();                
+                let now = Instant::now();
+                owed += now - last_redraw;
+                last_redraw = now;
+
+                if let Some(game) = game.as_mut() {
+                    if game.paused {
+                        owed = Duration::ZERO;
+                        if game.advance {
+                            game.advance = false;
+                            game.run_frame(&input, audio.as_mut());
+                        }
+                    } else if input.is_held(Hotkey::Rewind) {
+                        // Back through the frames at the rate they were played
+                        let mut frames = 0;
+                        while owed >= frame_time && frames < MAX_FRAMES_PER_REFRESH && game.system.rewind_frame() {
+                            owed -= frame_time;
+                            frames += 1;
+                        }
+                        owed = owed.min(frame_time);
+                    } else if input.is_held(Hotkey::FastForward) {
+                        // As many frames as a refresh allows, without the sound, which can't keep up
+                        owed = Duration::ZERO;
+                        for _ in 0..MAX_FRAMES_PER_REFRESH {
+                            game.run_frame(&input, None);
+                        }
+                    } else {
+                        let mut frames = 0;
+                        while owed >= frame_time && frames < MAX_FRAMES_PER_REFRESH {
+                            game.run_frame(&input, audio.as_mut());
+                            owed -= frame_time;
+                            frames += 1;
+                        }
+                        if frames == MAX_FRAMES_PER_REFRESH {
+                            owed = Duration::ZERO;
+                        }
+                    }
+                    upload_framebuffer(&queue, &frame_texture, game.system.cpu.mapper.get_ppu());
+                }
+
+                let frame = match surface.get_current_texture() {
+                    Ok(frame) => frame,
+                    Err(e) => {
+                        log::warn!("Skipping a redraw: {}", e);
+                        surface.configure(&device, &config);
+                        return;
+                    }
+                };
                 let view = frame.texture.create_view(&wgpu::TextureViewDescriptor::default());
                 let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
                 {
@@ -123,16 +494,19 @@ pub async fn run(event_loop: EventLoop<()>, window: Window) {
                         depth_stencil_attachment: None,
                     });
                     rpass.set_pipeline(&render_pipeline);
+                    rpass.set_bind_group(0, &bind_group, &[]);
                     rpass.draw(0..4, 0..1);
                 }
 
                 queue.submit(Some(encoder.finish()));
                 frame.present();
+                flush_battery(&mut game, false);
             }
             Event::WindowEvent {
                 event: WindowEvent::CloseRequested,
                 ..
             } => *control_flow = ControlFlow::Exit,
+            Event::LoopDestroyed => flush_battery(&mut game, true),
             _ => {}
         }
     });
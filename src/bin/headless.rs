@@ -0,0 +1,404 @@
+use std::{
+    collections::BTreeMap,
+    env,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::Path,
+    process,
+};
+
+use nes::{
+    apu::SAMPLE_RATE,
+    audio::{AudioSink, WavSink},
+    cdl::CodeDataLogger,
+    cheats::{Cheat, Cheats},
+    cpu::trace::{TraceFormat, Tracer, WriterSink},
+    input::{Buttons, Port, StandardController},
+    movie::Movie,
+    ntsc::{NtscFilter, NTSC_WIDTH},
+    palette::Palette,
+    ppu::{SCREEN_HEIGHT, SCREEN_WIDTH},
+    roms::{Mappers, RomImage},
+    system::{ConsoleSystem, Region},
+};
+
+const USAGE: &str = "usage: headless <rom> [--frames N] [--cycles N] [--until-pc ADDR] [--until-ram ADDR=VALUE]
+                [--input FILE] [--png FILE] [--wav FILE] [--ram FILE] [--json FILE]
+                [--trace FILE] [--trace-format nestest|mesen|fceux] [--cdl FILE]
+                [--region ntsc|pal|dendy] [--palette FILE] [--ntsc] [--movie FILE] [--record FILE]
+                [--cheats FILE] [--cheat CODE]...";
+
+const DEFAULT_FRAMES: u64 = 60;
+
+#[derive(Default)]
+struct Options {
+    rom: String,
+    frames: Option<u64>,
+    cycles: Option<u64>,
+    until_pc: Option<u16>,
+    until_ram: Option<(u16, u8)>,
+    input: Option<String>,
+    png: Option<String>,
+    wav: Option<String>,
+    ram: Option<String>,
+    json: Option<String>,
+    trace: Option<String>,
+    trace_format: Option<TraceFormat>,
+    cdl: Option<String>,
+    // Overrides what the header says
+    region: Option<Region>,
+    palette: Option<String>,
+    // Runs the PNG through the composite filter instead of the palette
+    ntsc: bool,
+    // .fm2 or .bk2 to play, which runs to its end unless told otherwise
+    movie: Option<String>,
+    // .fm2 to record the run's input to, from power on
+    record: Option<String>,
+    // A .cht file, then any Game Genie or RAM codes on top
+    cheats: Option<String>,
+    cheat_codes: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StopReason {
+    Frames,
+    Cycles,
+    Pc,
+    Ram,
+}
+
+impl StopReason {
+    fn name(&self) -> &'static str {
+        match self {
+            StopReason::Frames => "frames",
+            StopReason::Cycles => "cycles",
+            StopReason::Pc => "pc",
+            StopReason::Ram => "ram",
+        }
+    }
+}
+
+fn main() {
+    let options = parse_args(env::args().skip(1)).unwrap_or_else(|e| {
+        eprintln!("{}\n{}", e, USAGE);
+        process::exit(2);
+    });
+
+    match run(&options) {
+        Ok(true) => {}
+        // The movie desynced, the outputs are still written
+        Ok(false) => process::exit(3),
+        Err(e) => {
+            eprintln!("headless: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+// False when a movie played back out of sync
+fn run(options: &Options) -> io::Result<bool> {
+    let mut rom_file = File::open(&options.rom)?;
+    let image = RomImage::from(&mut rom_file)?;
+    Mappers::check(&image.header).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+    // An existing .cdl gets added to rather than started over, like FCEUX does
+    let logger = match &options.cdl {
+        Some(path) if Path::new(path).exists() => Some(CodeDataLogger::load(Path::new(path), &image.header)?),
+        Some(_) => Some(CodeDataLogger::new(&image.header)),
+        None => None,
+    };
+    let mut system = match options.region {
+        Some(region) => ConsoleSystem::with_region(image, region),
+        None => ConsoleSystem::new(image),
+    };
+    system.cpu.code_data_logger = logger;
+    if options.cheats.is_some() || !options.cheat_codes.is_empty() {
+        let mut cheats = match &options.cheats {
+            Some(path) => Cheats::load(Path::new(path))?,
+            None => Cheats::new(),
+        };
+        for code in &options.cheat_codes {
+            cheats.add(Cheat::parse(code)?);
+        }
+        system.cpu.cheats = Some(cheats);
+    }
+    system.reset();
+
+    if let Some(path) = &options.trace {
+        let format = options.trace_format.unwrap_or(TraceFormat::Nestest);
+        let sink = WriterSink::new(BufWriter::new(File::create(path)?), format);
+        system.cpu.tracer = Some(Tracer::new(Box::new(sink)));
+    }
+
+    let mut frame_limit = options.frames;
+    if let Some(path) = &options.movie {
+        let movie = Movie::load(Path::new(path))?;
+        frame_limit = frame_limit.or(Some(movie.frames.len() as u64));
+        system.play_movie(movie)?;
+    }
+    if options.record.is_some() {
+        system.record_movie(false);
+    }
+
+    let script = match &options.input {
+        Some(path) => parse_script(&fs::read_to_string(path)?)?,
+        None => BTreeMap::new(),
+    };
+
+    let mut samples = Vec::new();
+    let start_frame = system.cpu.mapper.get_ppu().frame;
+    let mut applied_frame = None;
+    let mut cycles = 0u64;
+
+    let reason = loop {
+        let frame = system.cpu.mapper.get_ppu().frame - start_frame;
+        if applied_frame != Some(frame) {
+            apply_input(&mut system, &script, frame);
+            applied_frame = Some(frame);
+        }
+
+        if frame_limit.is_some_and(|frames| frame >= frames) {
+            break StopReason::Frames;
+        }
+        if options.cycles.is_some_and(|limit| cycles >= limit) {
+            break StopReason::Cycles;
+        }
+
+        system.cycle();
+        cycles += 1;
+        samples.append(&mut system.cpu.mapper.get_alu().samples);
+
+        let boundary = system.cpu.at_instruction_boundary();
+        if options.until_pc.is_some_and(|pc| boundary && system.cpu.pc == pc) {
+            break StopReason::Pc;
+        }
+        if let Some((address, value)) = options.until_ram {
+            if system.cpu.mapper.peek(address) == value {
+                break StopReason::Ram;
+            }
+        }
+    };
+
+    // Drop the tracer so its buffered writer flushes
+    system.cpu.tracer = None;
+
+    let session = system.stop_movie();
+    let desyncs = session.as_ref().map_or(0, |session| session.desyncs.len());
+    for desync in session.iter().flat_map(|session| &session.desyncs) {
+        eprintln!("headless: movie desynced at frame {}, RAM hash {:016x} instead of {:016x}", desync.frame, desync.actual, desync.expected);
+    }
+    if let (Some(path), Some(mut session)) = (&options.record, session) {
+        session.movie.rom_filename = Path::new(&options.rom).file_stem().unwrap_or_default().to_string_lossy().into_owned();
+        session.movie.save(Path::new(path))?;
+    }
+
+    if let Some(path) = &options.png {
+        match options.ntsc {
+            true => write_ntsc_png(&mut system, path)?,
+            false => {
+                let palette = match &options.palette {
+                    Some(palette) => Palette::load(Path::new(palette))?,
+                    None => Palette::new(),
+                };
+                write_png(&mut system, &palette, path)?;
+            }
+        }
+    }
+    if let Some(path) = &options.wav {
+        let mut wav = WavSink::new(SAMPLE_RATE);
+        wav.write(&samples);
+        wav.save(Path::new(path))?;
+    }
+    if let Some(path) = &options.ram {
+        write_ram(&mut system, path)?;
+    }
+    if let Some(path) = &options.json {
+        write_summary(&mut system, path, reason, cycles, start_frame, desyncs)?;
+    }
+    if let (Some(path), Some(logger)) = (&options.cdl, &system.cpu.code_data_logger) {
+        logger.save(Path::new(path))?;
+    }
+
+    Ok(desyncs == 0)
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut rom = None;
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--frames" => options.frames = Some(parse_number(&value()?)?),
+            "--cycles" => options.cycles = Some(parse_number(&value()?)?),
+            "--until-pc" => options.until_pc = Some(parse_narrow(&value()?)?),
+            "--until-ram" => {
+                let value = value()?;
+                let (address, data) = value.split_once('=').ok_or("--until-ram expects ADDR=VALUE")?;
+                options.until_ram = Some((parse_narrow(address)?, parse_narrow(data)?));
+            }
+            "--input" => options.input = Some(value()?),
+            "--png" => options.png = Some(value()?),
+            "--wav" => options.wav = Some(value()?),
+            "--ram" => options.ram = Some(value()?),
+            "--json" => options.json = Some(value()?),
+            "--trace" => options.trace = Some(value()?),
+            "--trace-format" => {
+                options.trace_format = Some(match value()?.as_str() {
+                    "nestest" => TraceFormat::Nestest,
+                    "mesen" => TraceFormat::Mesen,
+                    "fceux" => TraceFormat::Fceux,
+                    other => return Err(format!("unknown trace format {}", other)),
+                })
+            }
+            "--cdl" => options.cdl = Some(value()?),
+            "--palette" => options.palette = Some(value()?),
+            "--ntsc" => options.ntsc = true,
+            "--movie" => options.movie = Some(value()?),
+            "--record" => options.record = Some(value()?),
+            "--cheats" => options.cheats = Some(value()?),
+            "--cheat" => options.cheat_codes.push(value()?),
+            "--region" => {
+                options.region = Some(match value()?.as_str() {
+                    "ntsc" => Region::Ntsc,
+                    "pal" => Region::Pal,
+                    "dendy" => Region::Dendy,
+                    other => return Err(format!("unknown region {}", other)),
+                })
+            }
+            _ if arg.starts_with("--") => return Err(format!("unknown option {}", arg)),
+            _ => rom = Some(arg),
+        }
+    }
+
+    options.rom = rom.ok_or("no ROM given")?;
+    let stops = options.cycles.is_some() || options.until_pc.is_some() || options.until_ram.is_some() || options.movie.is_some();
+    if options.frames.is_none() && !stops {
+        options.frames = Some(DEFAULT_FRAMES);
+    }
+
+    Ok(options)
+}
+
+// Decimal, or hex with a $ or 0x prefix
+fn parse_number(text: &str) -> Result<u64, String> {
+    let parsed = if let Some(hex) = text.strip_prefix('$').or_else(|| text.strip_prefix("0x")) {
+        u64::from_str_radix(hex, 16)
+    } else {
+        text.parse()
+    };
+
+    parsed.map_err(|_| format!("bad number {}", text))
+}
+
+// A number that has to fit an address or a byte
+fn parse_narrow<T: TryFrom<u64>>(text: &str) -> Result<T, String> {
+    T::try_from(parse_number(text)?).map_err(|_| format!("{} is out of range", text))
+}
+
+// One line per change: "<frame> <port> <BUTTON+BUTTON...>", held until the next line for that port.
+// An empty button list (or "-") releases everything.
+fn parse_script(text: &str) -> io::Result<BTreeMap<u64, Vec<(Port, Buttons)>>> {
+    let mut script: BTreeMap<u64, Vec<(Port, Buttons)>> = BTreeMap::new();
+
+    for (number, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, format!("input line {}: {}", number + 1, message));
+        let mut fields = line.split_whitespace();
+        let frame = fields.next().and_then(|f| f.parse().ok()).ok_or_else(|| invalid("bad frame"))?;
+        let port = match fields.next() {
+            Some("1") => Port::One,
+            Some("2") => Port::Two,
+            _ => return Err(invalid("port must be 1 or 2")),
+        };
+
+        let mut buttons = Buttons::empty();
+        for name in fields.next().unwrap_or("-").split('+').filter(|name| *name != "-") {
+            buttons |= parse_button(name).ok_or_else(|| invalid("unknown button"))?;
+        }
+
+        script.entry(frame).or_default().push((port, buttons));
+    }
+
+    Ok(script)
+}
+
+fn parse_button(name: &str) -> Option<Buttons> {
+    Some(match name.to_ascii_uppercase().as_str() {
+        "A" => Buttons::A,
+        "B" => Buttons::B,
+        "SELECT" => Buttons::SELECT,
+        "START" => Buttons::START,
+        "UP" => Buttons::UP,
+        "DOWN" => Buttons::DOWN,
+        "LEFT" => Buttons::LEFT,
+        "RIGHT" => Buttons::RIGHT,
+        _ => return None,
+    })
+}
+
+fn apply_input(system: &mut ConsoleSystem, script: &BTreeMap<u64, Vec<(Port, Buttons)>>, frame: u64) {
+    for (port, buttons) in script.get(&frame).into_iter().flatten() {
+        match system.input().device_mut::<StandardController>(*port) {
+            Some(controller) => controller.buttons = *buttons,
+            None => eprintln!("headless: no standard controller in port {:?}, ignoring input for frame {}", port, frame),
+        }
+    }
+}
+
+fn write_png(system: &mut ConsoleSystem, palette: &Palette, path: &str) -> io::Result<()> {
+    let ppu = system.cpu.mapper.get_ppu();
+    let pixels: Vec<u8> = ppu.framebuffer.iter().flat_map(|pixel| palette.rgb(*pixel)).collect();
+    encode_png(&pixels, SCREEN_WIDTH, path)
+}
+
+// Twice as wide as the PPU's picture so the colour fringes survive
+fn write_ntsc_png(system: &mut ConsoleSystem, path: &str) -> io::Result<()> {
+    let ppu = system.cpu.mapper.get_ppu();
+    let pixels: Vec<u8> = NtscFilter::default().apply(&ppu.framebuffer[..], ppu.frame).concat();
+    encode_png(&pixels, NTSC_WIDTH, path)
+}
+
+fn encode_png(pixels: &[u8], width: usize, path: &str) -> io::Result<()> {
+    let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?), width as u32, SCREEN_HEIGHT as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(io::Error::other)?;
+    writer.write_image_data(pixels).map_err(io::Error::other)
+}
+
+fn write_ram(system: &mut ConsoleSystem, path: &str) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+
+    for row in (0..0x800u16).step_by(16) {
+        let bytes: Vec<String> = (row..row + 16).map(|address| format!("{:02X}", system.cpu.mapper.peek(address))).collect();
+        writeln!(out, "{:04X}: {}", row, bytes.join(" "))?;
+    }
+
+    out.flush()
+}
+
+fn write_summary(system: &mut ConsoleSystem, path: &str, reason: StopReason, cycles: u64, start_frame: u64, desyncs: usize) -> io::Result<()> {
+    let cpu = &system.cpu;
+    let (a, x, y, p, s, pc) = (cpu.a, cpu.x, cpu.y, cpu.p.bits(), cpu.s, cpu.pc);
+    let ppu = system.cpu.mapper.get_ppu();
+
+    let mut out = File::create(path)?;
+    writeln!(out, "{{")?;
+    writeln!(out, "  \"stop_reason\": \"{}\",", reason.name())?;
+    writeln!(out, "  \"frames\": {},", ppu.frame - start_frame)?;
+    writeln!(out, "  \"cycles\": {},", cycles)?;
+    writeln!(out, "  \"scanline\": {},", ppu.scanline)?;
+    writeln!(out, "  \"dot\": {},", ppu.dot)?;
+    writeln!(
+        out,
+        "  \"cpu\": {{ \"pc\": {}, \"a\": {}, \"x\": {}, \"y\": {}, \"p\": {}, \"s\": {} }},",
+        pc, a, x, y, p, s
+    )?;
+    writeln!(out, "  \"movie_desyncs\": {},", desyncs)?;
+    writeln!(out, "  \"framebuffer_hash\": \"{:016x}\"", ppu.framebuffer_hash())?;
+    writeln!(out, "}}")
+}
@@ -0,0 +1,29 @@
+use std::{env, fs, path::Path, process};
+
+use nes::testing::Manifest;
+
+// Runs every entry of a test ROM manifest and prints (or writes) the pass/fail matrix
+fn main() {
+    let mut args = env::args().skip(1);
+    let manifest_path = args.next().unwrap_or_else(|| "tests/roms.toml".to_string());
+    let report_path = args.next();
+
+    let manifest = Manifest::load(Path::new(&manifest_path)).unwrap_or_else(|e| {
+        eprintln!("rom_suite: couldn't load {}: {}", manifest_path, e);
+        process::exit(2);
+    });
+
+    let report = manifest.run();
+    let matrix = report.matrix();
+    match report_path {
+        Some(path) => fs::write(&path, &matrix).unwrap_or_else(|e| {
+            eprintln!("rom_suite: couldn't write {}: {}", path, e);
+            process::exit(2);
+        }),
+        None => print!("{}", matrix),
+    }
+
+    if report.unexpected().next().is_some() {
+        process::exit(1);
+    }
+}
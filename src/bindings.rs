@@ -0,0 +1,278 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+use crate::input::{Buttons, Port, StandardController};
+use crate::system::ConsoleSystem;
+
+// Something a player can press, named the way the frontend names it: winit's key names for the keyboard, and
+// "Pad<N>:<button>" with gilrs' button names for gamepad N
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize)]
+#[serde(try_from = "String")]
+pub enum Binding {
+    Key(String),
+    Gamepad(usize, String),
+}
+
+impl FromStr for Binding {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, String> {
+        match text.strip_prefix("Pad") {
+            Some(pad) => {
+                let (id, button) = pad.split_once(':').ok_or_else(|| format!("{} should be Pad<N>:<button>", text))?;
+                let id = id.parse().map_err(|_| format!("bad gamepad number in {}", text))?;
+                Ok(Binding::Gamepad(id, button.to_string()))
+            }
+            None => Ok(Binding::Key(text.to_string())),
+        }
+    }
+}
+
+impl TryFrom<String> for Binding {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Self, String> {
+        text.parse()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hotkey {
+    Pause,
+    Reset,
+    SaveState,
+    LoadState,
+    NextSlot,
+    PreviousSlot,
+    // Held rather than pressed
+    FastForward,
+    Rewind,
+    // Pauses if it isn't already, then runs one frame
+    FrameAdvance,
+    Screenshot,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Deserialize)]
+#[serde(default)]
+pub struct PortBindings {
+    pub a: Vec<Binding>,
+    pub b: Vec<Binding>,
+    pub select: Vec<Binding>,
+    pub start: Vec<Binding>,
+    pub up: Vec<Binding>,
+    pub down: Vec<Binding>,
+    pub left: Vec<Binding>,
+    pub right: Vec<Binding>,
+    // Press and release A or B by themselves for as long as they're held
+    pub turbo_a: Vec<Binding>,
+    pub turbo_b: Vec<Binding>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Deserialize)]
+#[serde(default)]
+pub struct HotkeyBindings {
+    pub pause: Vec<Binding>,
+    pub reset: Vec<Binding>,
+    pub save_state: Vec<Binding>,
+    pub load_state: Vec<Binding>,
+    pub next_slot: Vec<Binding>,
+    pub previous_slot: Vec<Binding>,
+    pub fast_forward: Vec<Binding>,
+    pub rewind: Vec<Binding>,
+    pub frame_advance: Vec<Binding>,
+    pub screenshot: Vec<Binding>,
+}
+
+// The frontend's input.toml. Anything left out keeps its default, but a section that's there replaces the default
+// section rather than adding to it.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct InputConfig {
+    pub port1: PortBindings,
+    pub port2: PortBindings,
+    pub hotkeys: HotkeyBindings,
+    // Frames a turbo button spends pressed, then the same again released
+    pub turbo_period: u64,
+}
+
+fn bindings(names: &[&str]) -> Vec<Binding> {
+    names.iter().map(|name| name.parse().unwrap()).collect()
+}
+
+fn pad_bindings(pad: usize) -> [Vec<Binding>; 10] {
+    let pad = |button: &str| Binding::Gamepad(pad, button.to_string());
+    [
+        vec![pad("East")],
+        vec![pad("South")],
+        vec![pad("Select")],
+        vec![pad("Start")],
+        vec![pad("DPadUp")],
+        vec![pad("DPadDown")],
+        vec![pad("DPadLeft")],
+        vec![pad("DPadRight")],
+        vec![pad("North")],
+        vec![pad("West")],
+    ]
+}
+
+impl PortBindings {
+    fn from_lists([a, b, select, start, up, down, left, right, turbo_a, turbo_b]: [Vec<Binding>; 10]) -> Self {
+        Self { a, b, select, start, up, down, left, right, turbo_a, turbo_b }
+    }
+
+    fn buttons(&self) -> [(Buttons, &Vec<Binding>); 8] {
+        [
+            (Buttons::A, &self.a),
+            (Buttons::B, &self.b),
+            (Buttons::SELECT, &self.select),
+            (Buttons::START, &self.start),
+            (Buttons::UP, &self.up),
+            (Buttons::DOWN, &self.down),
+            (Buttons::LEFT, &self.left),
+            (Buttons::RIGHT, &self.right),
+        ]
+    }
+}
+
+impl HotkeyBindings {
+    fn hotkeys(&self) -> [(Hotkey, &Vec<Binding>); 10] {
+        [
+            (Hotkey::Pause, &self.pause),
+            (Hotkey::Reset, &self.reset),
+            (Hotkey::SaveState, &self.save_state),
+            (Hotkey::LoadState, &self.load_state),
+            (Hotkey::NextSlot, &self.next_slot),
+            (Hotkey::PreviousSlot, &self.previous_slot),
+            (Hotkey::FastForward, &self.fast_forward),
+            (Hotkey::Rewind, &self.rewind),
+            (Hotkey::FrameAdvance, &self.frame_advance),
+            (Hotkey::Screenshot, &self.screenshot),
+        ]
+    }
+}
+
+impl InputConfig {
+    pub fn new() -> Self {
+        // Keyboard and the first gamepad on port 1, the second gamepad on port 2
+        let mut port1 = pad_bindings(0);
+        let keys = ["X", "Z", "RShift", "Return", "Up", "Down", "Left", "Right", "S", "A"];
+        for (bindings, key) in port1.iter_mut().zip(keys) {
+            bindings.insert(0, Binding::Key(key.to_string()));
+        }
+
+        Self {
+            port1: PortBindings::from_lists(port1),
+            port2: PortBindings::from_lists(pad_bindings(1)),
+            hotkeys: HotkeyBindings {
+                pause: bindings(&["P"]),
+                reset: bindings(&["F2"]),
+                save_state: bindings(&["F5"]),
+                load_state: bindings(&["F7"]),
+                next_slot: bindings(&["F6"]),
+                previous_slot: bindings(&["F4"]),
+                fast_forward: bindings(&["Tab"]),
+                rewind: bindings(&["Back"]),
+                frame_advance: bindings(&["Backslash"]),
+                screenshot: bindings(&["F12"]),
+            },
+            turbo_period: 2,
+        }
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn parse(text: &str) -> io::Result<Self> {
+        toml::from_str(text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    pub fn port(&self, port: Port) -> &PortBindings {
+        match port {
+            Port::One => &self.port1,
+            Port::Two => &self.port2,
+        }
+    }
+}
+
+impl Default for InputConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Tracks what's held and turns it into controller buttons and hotkeys
+pub struct InputMapper {
+    pub config: InputConfig,
+    held: HashSet<Binding>,
+}
+
+impl InputMapper {
+    pub fn new(config: InputConfig) -> Self {
+        Self { config, held: HashSet::new() }
+    }
+
+    // The hotkeys bound to it, if this is a fresh press rather than a key repeat
+    pub fn press(&mut self, binding: Binding) -> Vec<Hotkey> {
+        if !self.held.insert(binding.clone()) {
+            return Vec::new();
+        }
+
+        self.config.hotkeys.hotkeys().iter()
+            .filter(|(_, bindings)| bindings.contains(&binding))
+            .map(|(hotkey, _)| *hotkey)
+            .collect()
+    }
+
+    pub fn release(&mut self, binding: &Binding) {
+        self.held.remove(binding);
+    }
+
+    // Lets go of everything, for when the window loses focus and the releases won't arrive
+    pub fn release_all(&mut self) {
+        self.held.clear();
+    }
+
+    pub fn is_held(&self, hotkey: Hotkey) -> bool {
+        let hotkeys = self.config.hotkeys.hotkeys();
+        hotkeys.iter().any(|(bound, bindings)| *bound == hotkey && self.any_held(bindings))
+    }
+
+    fn any_held(&self, bindings: &[Binding]) -> bool {
+        bindings.iter().any(|binding| self.held.contains(binding))
+    }
+
+    // Turbo buttons are pressed for the first half of every 2 * turbo_period frames
+    pub fn buttons(&self, port: Port, frame: u64) -> Buttons {
+        let bindings = self.config.port(port);
+        let mut buttons = Buttons::empty();
+        for (button, bound) in bindings.buttons() {
+            buttons.set(button, self.any_held(bound));
+        }
+
+        let turbo_on = (frame / self.config.turbo_period.max(1)).is_multiple_of(2);
+        if turbo_on && self.any_held(&bindings.turbo_a) {
+            buttons |= Buttons::A;
+        }
+        if turbo_on && self.any_held(&bindings.turbo_b) {
+            buttons |= Buttons::B;
+        }
+        buttons
+    }
+
+    // Sets both standard controllers for the frame that's about to run, other devices are left alone
+    pub fn apply(&self, system: &mut ConsoleSystem) {
+        let frame = system.cpu.mapper.get_ppu().frame;
+        for port in [Port::One, Port::Two] {
+            let buttons = self.buttons(port, frame);
+            if let Some(controller) = system.input().device_mut::<StandardController>(port) {
+                controller.buttons = buttons;
+            }
+        }
+    }
+}
@@ -1,8 +1,26 @@
 use crate::system::ConsoleDevices;
 
 pub trait BusDevice {
-    fn read(self: &Self, address: u16) -> u8;
+    fn read(self: &mut Self, address: u16) -> u8;
     fn write(self: &mut Self, address: u16, data: u8);
+    // Debugger access: what `read` would return, without any of its side effects
+    fn peek(&self, address: u16) -> u8;
+    // Debugger access: changes what's behind `address` without anything reacting to it, ROM included
+    fn poke(&mut self, address: u16, data: u8);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessKind {
+    Read,
+    Write,
+}
+
+// One access on a bus, kept around for a cycle so debuggers can watch memory without hooking every device
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BusAccess {
+    pub kind: AccessKind,
+    pub address: u16,
+    pub data: u8,
 }
 
 pub type MemoryMapper = fn(u16, &mut ConsoleDevices) -> &mut dyn BusDevice;
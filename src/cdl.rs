@@ -0,0 +1,148 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use bitflags::bitflags;
+
+use crate::roms::{Mapper, RomImageHeader};
+
+bitflags! {
+    // One byte per PRG-ROM byte, in FCEUX's .cdl layout
+    pub struct ProgramFlags: u8 {
+        const CODE = 0x01;
+        const DATA = 0x02;
+        // Which 8K window of $8000-$FFFF the byte was last seen through
+        const WINDOW = 0x0c;
+        // The target of a JMP ($nnnn)
+        const INDIRECT_CODE = 0x10;
+        // Read through a ($nn,X) or ($nn),Y pointer
+        const INDIRECT_DATA = 0x20;
+        const PCM = 0x40;
+        // Unused by FCEUX, kept here to tell opcodes from operands and dropped when saving
+        const OPCODE = 0x80;
+    }
+}
+
+bitflags! {
+    // One byte per CHR-ROM byte after the PRG part
+    pub struct CharacterFlags: u8 {
+        const RENDERED = 0x01;
+        const READ = 0x02;
+    }
+}
+
+// Code/data logger, marks how every ROM byte gets used by its offset in the ROM so banked code is told apart
+pub struct CodeDataLogger {
+    program: Vec<ProgramFlags>,
+    character: Vec<CharacterFlags>,
+}
+
+impl CodeDataLogger {
+    pub fn new(header: &RomImageHeader) -> Self {
+        Self {
+            program: vec![ProgramFlags::empty(); header.program_rom_size as usize * 0x4000],
+            character: vec![CharacterFlags::empty(); header.character_rom_size as usize * 0x2000],
+        }
+    }
+
+    // Carries on from an earlier session's .cdl
+    pub fn load(path: &Path, header: &RomImageHeader) -> io::Result<Self> {
+        let mut logger = Self::new(header);
+        let data = fs::read(path)?;
+        if data.len() != logger.program.len() + logger.character.len() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "CDL file doesn't match the ROM size"));
+        }
+
+        let (program, character) = data.split_at(logger.program.len());
+        for (flags, byte) in logger.program.iter_mut().zip(program) {
+            *flags = ProgramFlags::from_bits_truncate(*byte);
+        }
+        for (flags, byte) in logger.character.iter_mut().zip(character) {
+            *flags = CharacterFlags::from_bits_truncate(*byte);
+        }
+        Ok(logger)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut file = fs::File::create(path)?;
+        self.write(&mut file)
+    }
+
+    pub fn write(&self, writer: &mut dyn Write) -> io::Result<()> {
+        let program: Vec<u8> = self.program.iter().map(|flags| (*flags - ProgramFlags::OPCODE).bits()).collect();
+        let character: Vec<u8> = self.character.iter().map(|flags| flags.bits()).collect();
+        writer.write_all(&program)?;
+        writer.write_all(&character)
+    }
+
+    pub fn program_flags(&self, offset: usize) -> ProgramFlags {
+        self.program.get(offset).copied().unwrap_or(ProgramFlags::empty())
+    }
+
+    pub fn character_flags(&self, offset: usize) -> CharacterFlags {
+        self.character.get(offset).copied().unwrap_or(CharacterFlags::empty())
+    }
+
+    // PRG bytes seen as code and as data, like FCEUX's logger window shows
+    pub fn coverage(&self) -> (usize, usize) {
+        let code = self.program.iter().filter(|flags| flags.contains(ProgramFlags::CODE)).count();
+        let data = self.program.iter().filter(|flags| flags.contains(ProgramFlags::DATA)).count();
+        (code, data)
+    }
+
+    // An instruction at `address` just got decoded, `length` bytes of it are opcode and operands
+    pub fn log_instruction(&mut self, mapper: &dyn Mapper, address: u16, length: u8, indirect: bool) {
+        for i in 0..length as u16 {
+            let mut flags = ProgramFlags::CODE;
+            if i == 0 {
+                flags |= ProgramFlags::OPCODE;
+                if indirect {
+                    flags |= ProgramFlags::INDIRECT_CODE;
+                }
+            }
+            self.mark_program(mapper, address.wrapping_add(i), flags);
+        }
+    }
+
+    pub fn log_read(&mut self, mapper: &dyn Mapper, address: u16, indirect: bool) {
+        let flags = match indirect {
+            true => ProgramFlags::DATA | ProgramFlags::INDIRECT_DATA,
+            false => ProgramFlags::DATA,
+        };
+        self.mark_program(mapper, address, flags);
+    }
+
+    // For DMC sample fetches, nothing makes those yet
+    pub fn log_sample(&mut self, mapper: &dyn Mapper, address: u16) {
+        self.mark_program(mapper, address, ProgramFlags::DATA | ProgramFlags::PCM);
+    }
+
+    // A pattern table byte read through $2007
+    pub fn log_character_read(&mut self, mapper: &dyn Mapper, address: u16) {
+        self.mark_character(mapper, address, CharacterFlags::READ);
+    }
+
+    // A pattern table byte fetched for drawing, for when the PPU renders
+    pub fn log_character_rendered(&mut self, mapper: &dyn Mapper, address: u16) {
+        self.mark_character(mapper, address, CharacterFlags::RENDERED);
+    }
+
+    fn mark_program(&mut self, mapper: &dyn Mapper, address: u16, flags: ProgramFlags) {
+        let Some(offset) = mapper.program_rom_offset(address) else {
+            return;
+        };
+        if let Some(byte) = self.program.get_mut(offset) {
+            let window = ProgramFlags::from_bits_truncate(((address >> 13) & 3) as u8 * 4);
+            *byte |= flags | window;
+        }
+    }
+
+    fn mark_character(&mut self, mapper: &dyn Mapper, address: u16, flags: CharacterFlags) {
+        let Some(offset) = mapper.character_rom_offset(address) else {
+            return;
+        };
+        if let Some(byte) = self.character.get_mut(offset) {
+            *byte |= flags;
+        }
+    }
+}
@@ -0,0 +1,212 @@
+mod game_genie;
+
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+pub use self::game_genie::{decode as decode_game_genie, encode as encode_game_genie};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheatKind {
+    // Replaces what reads of the address see, like a Game Genie does with PRG-ROM
+    Substitute,
+    // Keeps a RAM byte at the value: reads see it and writes store it instead of what was written
+    Freeze,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cheat {
+    pub name: String,
+    pub kind: CheatKind,
+    pub address: u16,
+    pub value: u8,
+    // Only substitute when the real byte is this, so 8 letter codes survive bank switching
+    pub compare: Option<u8>,
+    pub enabled: bool,
+}
+
+impl Cheat {
+    pub fn new(name: &str, kind: CheatKind, address: u16, value: u8, compare: Option<u8>) -> Self {
+        Self {
+            name: name.to_string(),
+            kind,
+            address,
+            value,
+            compare,
+            enabled: true,
+        }
+    }
+
+    pub fn game_genie(code: &str) -> io::Result<Self> {
+        let (address, value, compare) = decode_game_genie(code)?;
+        Ok(Self::new(&code.to_ascii_uppercase(), CheatKind::Substitute, address, value, compare))
+    }
+
+    // Pro Action Replay style RAM codes, "AAAAVV" or "AAAA:VV" in hex
+    pub fn pro_action_replay(code: &str) -> io::Result<Self> {
+        let digits = code.replace(':', "");
+        let bad_code = || invalid_cheat(format!("{} isn't an AAAAVV RAM code", code));
+        if digits.len() != 6 {
+            return Err(bad_code());
+        }
+        let address = u16::from_str_radix(&digits[..4], 16).map_err(|_| bad_code())?;
+        let value = u8::from_str_radix(&digits[4..], 16).map_err(|_| bad_code())?;
+        if address >= 0x8000 {
+            return Err(invalid_cheat(format!("{} isn't a RAM address", code)));
+        }
+        Ok(Self::new(&digits.to_ascii_uppercase(), CheatKind::Freeze, address, value, None))
+    }
+
+    // Either kind of code, Game Genie codes are all letters and RAM codes are hex
+    pub fn parse(code: &str) -> io::Result<Self> {
+        let code = code.trim();
+        match code.contains(':') || code.bytes().any(|c| c.is_ascii_digit()) {
+            true => Self::pro_action_replay(code),
+            false => Self::game_genie(code),
+        }
+    }
+
+    // The Game Genie code for it, if a Game Genie can do it
+    pub fn game_genie_code(&self) -> Option<String> {
+        (self.kind == CheatKind::Substitute && self.address >= 0x8000)
+            .then(|| encode_game_genie(self.address, self.value, self.compare))
+    }
+
+    // Internal RAM is mirrored four times, a cheat on any mirror catches all of them
+    fn hits(&self, address: u16) -> bool {
+        match (self.address, address) {
+            (0x0000..=0x1fff, 0x0000..=0x1fff) => self.address & 0x7ff == address & 0x7ff,
+            _ => self.address == address,
+        }
+    }
+}
+
+// FCEUX's .cht layout, one cheat a line: [S][C][:]AAAA:VV[:CC]:name, S for a substitute rather than a freeze, C when
+// there's a compare and the colon when it's disabled
+fn parse_cht_line(line: &str) -> io::Result<Cheat> {
+    let bad_line = || invalid_cheat(format!("bad cheat line {}", line));
+    let mut rest = line;
+    let substitute = rest.starts_with('S');
+    rest = rest.strip_prefix('S').unwrap_or(rest);
+    let compared = rest.starts_with('C');
+    rest = rest.strip_prefix('C').unwrap_or(rest);
+    let enabled = !rest.starts_with(':');
+    rest = rest.strip_prefix(':').unwrap_or(rest);
+
+    let fields = if compared { 4 } else { 3 };
+    let parts: Vec<&str> = rest.splitn(fields, ':').collect();
+    if parts.len() != fields {
+        return Err(bad_line());
+    }
+    let address = u16::from_str_radix(parts[0], 16).map_err(|_| bad_line())?;
+    let value = u8::from_str_radix(parts[1], 16).map_err(|_| bad_line())?;
+    let compare = match compared {
+        true => Some(u8::from_str_radix(parts[2], 16).map_err(|_| bad_line())?),
+        false => None,
+    };
+    let kind = if substitute { CheatKind::Substitute } else { CheatKind::Freeze };
+
+    let mut cheat = Cheat::new(parts[fields - 1], kind, address, value, compare);
+    cheat.enabled = enabled;
+    Ok(cheat)
+}
+
+pub(crate) fn invalid_cheat(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+// The cheats in front of the CPU's reads and writes. They only change the data, never add or drop an access, so
+// timing stays the same, and nothing about them goes in save states.
+pub struct Cheats {
+    cheats: Vec<Cheat>,
+}
+
+impl Cheats {
+    pub fn new() -> Self {
+        Self { cheats: Vec::new() }
+    }
+
+    pub fn parse(text: &str) -> io::Result<Self> {
+        let mut cheats = Self::new();
+        for line in text.lines() {
+            let line = line.trim_end_matches('\r');
+            if !line.trim().is_empty() {
+                cheats.add(parse_cht_line(line)?);
+            }
+        }
+        Ok(cheats)
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn to_cht(&self) -> String {
+        let mut text = String::new();
+        for cheat in &self.cheats {
+            if cheat.kind == CheatKind::Substitute {
+                text.push('S');
+            }
+            if cheat.compare.is_some() {
+                text.push('C');
+            }
+            if !cheat.enabled {
+                text.push(':');
+            }
+            let _ = write!(text, "{:04x}:{:02x}:", cheat.address, cheat.value);
+            if let Some(compare) = cheat.compare {
+                let _ = write!(text, "{:02x}:", compare);
+            }
+            let _ = writeln!(text, "{}", cheat.name);
+        }
+        text
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.to_cht())
+    }
+
+    // Returns the cheat's index
+    pub fn add(&mut self, cheat: Cheat) -> usize {
+        self.cheats.push(cheat);
+        self.cheats.len() - 1
+    }
+
+    pub fn remove(&mut self, index: usize) -> Option<Cheat> {
+        (index < self.cheats.len()).then(|| self.cheats.remove(index))
+    }
+
+    pub fn set_enabled(&mut self, index: usize, enabled: bool) {
+        if let Some(cheat) = self.cheats.get_mut(index) {
+            cheat.enabled = enabled;
+        }
+    }
+
+    pub fn cheats(&self) -> &[Cheat] {
+        &self.cheats
+    }
+
+    // What a read of `address` sees instead of `data`
+    pub fn read(&self, address: u16, data: u8) -> u8 {
+        self.cheats
+            .iter()
+            .filter(|cheat| cheat.enabled && cheat.hits(address))
+            .find(|cheat| cheat.compare.is_none_or(|compare| compare == data))
+            .map_or(data, |cheat| cheat.value)
+    }
+
+    // What a write of `data` to `address` stores instead
+    pub fn write(&self, address: u16, data: u8) -> u8 {
+        self.cheats
+            .iter()
+            .find(|cheat| cheat.enabled && cheat.kind == CheatKind::Freeze && cheat.hits(address))
+            .map_or(data, |cheat| cheat.value)
+    }
+}
+
+impl Default for Cheats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
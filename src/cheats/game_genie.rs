@@ -0,0 +1,62 @@
+use std::io;
+
+use super::invalid_cheat;
+
+// Each letter is a nybble, in this order
+const LETTERS: &[u8; 16] = b"APZLGITYEOXUKSVN";
+
+// The Game Genie scrambles the address, value and compare bits across the letters. Bit 3 of the third letter says
+// whether there's a compare, which is also why 6 and 8 letter codes look different from the start.
+pub fn decode(code: &str) -> io::Result<(u16, u8, Option<u8>)> {
+    let n = code
+        .bytes()
+        .map(|letter| LETTERS.iter().position(|&l| l == letter.to_ascii_uppercase()).map(|n| n as u16))
+        .collect::<Option<Vec<u16>>>()
+        .ok_or_else(|| invalid_cheat(format!("{} isn't a Game Genie code", code)))?;
+    if n.len() != 6 && n.len() != 8 {
+        return Err(invalid_cheat(format!("{} should be 6 or 8 letters", code)));
+    }
+
+    let address = 0x8000
+        | ((n[3] & 7) << 12)
+        | ((n[5] & 7) << 8)
+        | ((n[4] & 8) << 8)
+        | ((n[2] & 7) << 4)
+        | ((n[1] & 8) << 4)
+        | (n[4] & 7)
+        | (n[3] & 8);
+    let value = ((n[1] & 7) << 4) | ((n[0] & 8) << 4) | (n[0] & 7);
+    match n.len() {
+        6 => Ok((address, (value | (n[5] & 8)) as u8, None)),
+        _ => {
+            let compare = ((n[7] & 7) << 4) | ((n[6] & 8) << 4) | (n[6] & 7) | (n[5] & 8);
+            Ok((address, (value | (n[7] & 8)) as u8, Some(compare as u8)))
+        }
+    }
+}
+
+pub fn encode(address: u16, value: u8, compare: Option<u8>) -> String {
+    let (address, value) = (address as usize, value as usize);
+    let mut n = [0usize; 8];
+    n[0] = (value & 7) | ((value >> 4) & 8);
+    n[1] = ((value >> 4) & 7) | ((address >> 4) & 8);
+    n[2] = (address >> 4) & 7;
+    n[3] = ((address >> 12) & 7) | (address & 8);
+    n[4] = (address & 7) | ((address >> 8) & 8);
+    n[5] = (address >> 8) & 7;
+    let length = match compare {
+        None => {
+            n[5] |= value & 8;
+            6
+        }
+        Some(compare) => {
+            let compare = compare as usize;
+            n[2] |= 8;
+            n[5] |= compare & 8;
+            n[6] = (compare & 7) | ((compare >> 4) & 8);
+            n[7] = ((compare >> 4) & 7) | (value & 8);
+            8
+        }
+    };
+    n[..length].iter().map(|&n| LETTERS[n] as char).collect()
+}
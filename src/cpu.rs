@@ -1,48 +1,140 @@
 mod addressing_modes;
 pub mod instructions;
+pub mod trace;
 
-use std::{collections::VecDeque};
+use std::{collections::VecDeque, io::{self, Read, Write}};
 
 use bitflags::bitflags;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use num_enum::TryFromPrimitive;
 
-use crate::{roms::Mapper, address::Address};
+use crate::{roms::Mapper, address::Address, bus::{AccessKind, BusAccess}, cdl::CodeDataLogger, cheats::Cheats, disasm::{self, AddressingMode}, state::{SaveState, invalid_state}};
 
-use self::{addressing_modes::*, instructions::{ReadOperation, WriteOperation, BranchOperation, ReadWriteOperation}};
+use self::{addressing_modes::*, instructions::{ReadOperation, WriteOperation, BranchOperation, ReadWriteOperation}, trace::{TraceEvent, Tracer}};
 pub use self::{addressing_modes::{AddressingModes}, instructions::{Operations, IllegalOperations}};
 
+// Everything queued is plain data so an instruction can be saved and restored part way through
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 enum MicrocodeTask {
-    Branch(BusRead, BranchOperation, Microcode<BusRead, BranchOperation>),
-    Read(BusRead, ReadOperation, Microcode<BusRead, ReadOperation>),
-    Write(BusWrite, WriteOperation, Microcode<BusWrite, WriteOperation>),
-    ReadWrite(BusWrite, ReadWriteOperation, Microcode<BusWrite, ReadWriteOperation>),
+    Branch(BusRead, BranchOperation, BranchMicrocode),
+    Read(BusRead, ReadOperation, ReadMicrocode),
+    Write(BusWrite, WriteOperation, WriteMicrocode),
+    ReadWrite(BusWrite, ReadWriteOperation, ReadWriteMicrocode),
+}
+
+impl MicrocodeTask {
+    fn save(&self, writer: &mut dyn Write) -> io::Result<()> {
+        let (kind, io, op, microcode) = match *self {
+            Self::Branch(io, op, microcode) => (0, io as u8, op as u8, microcode as u8),
+            Self::Read(io, op, microcode) => (1, io as u8, op as u8, microcode as u8),
+            Self::Write(io, op, microcode) => (2, io as u8, op as u8, microcode as u8),
+            Self::ReadWrite(io, op, microcode) => (3, io as u8, op as u8, microcode as u8),
+        };
+        writer.write_all(&[kind, io, op, microcode])
+    }
+
+    fn load(reader: &mut dyn Read) -> io::Result<Self> {
+        let mut task: [u8; 4] = [0; 4];
+        reader.read_exact(&mut task)?;
+        let [kind, io, op, microcode] = task;
+        let task = match kind {
+            0 => Self::Branch(decode(io)?, decode(op)?, decode(microcode)?),
+            1 => Self::Read(decode(io)?, decode(op)?, decode(microcode)?),
+            2 => Self::Write(decode(io)?, decode(op)?, decode(microcode)?),
+            3 => Self::ReadWrite(decode(io)?, decode(op)?, decode(microcode)?),
+            _ => return Err(invalid_state("Unknown microcode task")),
+        };
+        Ok(task)
+    }
+}
+
+fn decode<T: TryFromPrimitive<Primitive = u8>>(value: u8) -> io::Result<T> {
+    T::try_from_primitive(value).map_err(|_| invalid_state("Unknown microcode"))
+}
+
+// What polling the interrupt lines before an instruction's last cycle found, taken once the instruction is done
+#[derive(Clone, Copy, Debug, PartialEq, Eq, TryFromPrimitive)]
+#[repr(u8)]
+enum InterruptPoll {
+    None,
+    Nmi,
+    Irq,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, TryFromPrimitive)]
+#[repr(u8)]
+pub enum BusRead {
+    Address,
+    AddressPageWrapped,
+    Pc,
+    PcIncrement,
+    Pointer,
+    PointerIncrement,
+    Stack,
+    PopStack,
+    ResetVectorLow,
+    ResetVectorHigh,
+    IrqVectorLow,
+    IrqVectorHigh,
+    NmiVectorLow,
+    NmiVectorHigh,
+}
+
+impl BusRead {
+    fn read(self, cpu: &mut Mos6502) -> u8 {
+        let data = match self {
+            Self::Address => cpu.read_address(),
+            Self::AddressPageWrapped => cpu.read_address_page_wrapped(),
+            Self::Pc => cpu.read_pc(),
+            Self::PcIncrement => cpu.read_pc_increment(),
+            Self::Pointer => cpu.read_pointer(),
+            Self::PointerIncrement => cpu.read_pointer_increment(),
+            Self::Stack => cpu.read_stack(),
+            Self::PopStack => cpu.pop_stack(),
+            Self::ResetVectorLow => cpu.read_fixed::<0xfffc>(),
+            Self::ResetVectorHigh => cpu.read_fixed::<0xfffd>(),
+            Self::IrqVectorLow => cpu.read_fixed::<0xfffe>(),
+            Self::IrqVectorHigh => cpu.read_fixed::<0xffff>(),
+            Self::NmiVectorLow => cpu.read_fixed::<0xfffa>(),
+            Self::NmiVectorHigh => cpu.read_fixed::<0xfffb>(),
+        };
+
+        if self.is_data() {
+            if let Some(access) = cpu.last_access {
+                cpu.log_read(self, access.address);
+            }
+        }
+        data
+    }
+
+    // Everything but the instruction stream itself, which gets logged as code when it's decoded
+    fn is_data(self) -> bool {
+        !matches!(self, Self::Pc | Self::PcIncrement)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, TryFromPrimitive)]
+#[repr(u8)]
+pub enum BusWrite {
+    Address,
+    PushStack,
+    // Read-modify-write on the accumulator still puts a dummy read of PC on the bus
+    DummyReadPc,
+}
+
+impl BusWrite {
+    fn write(self, cpu: &mut Mos6502, data: u8) {
+        match self {
+            Self::Address => cpu.write_address(data),
+            Self::PushStack => cpu.push_stack(data),
+            Self::DummyReadPc => cpu.data = cpu.read_pc(),
+        }
+    }
 }
 
-const OPCODES: [&'static str; 256] = [
-    "BRK", "ORA", "STP", "SLO", "NOP", "ORA", "ASL", "SLO", "PHP", "ORA", "ASL", "ANC", "NOP",
-    "ORA", "ASL", "SLO", "BPL", "ORA", "STP", "SLO", "NOP", "ORA", "ASL", "SLO", "CLC", "ORA",
-    "NOP", "SLO", "NOP", "ORA", "ASL", "SLO", "JSR", "AND", "STP", "RLA", "BIT", "AND", "ROL",
-    "RLA", "PLP", "AND", "ROL", "ANC", "BIT", "AND", "ROL", "RLA", "BMI", "AND", "STP", "RLA",
-    "NOP", "AND", "ROL", "RLA", "SEC", "AND", "NOP", "RLA", "NOP", "AND", "ROL", "RLA", "RTI",
-    "EOR", "STP", "SRE", "NOP", "EOR", "LSR", "SRE", "PHA", "EOR", "LSR", "ALR", "JMP", "EOR",
-    "LSR", "SRE", "BVC", "EOR", "STP", "SRE", "NOP", "EOR", "LSR", "SRE", "CLI", "EOR", "NOP",
-    "SRE", "NOP", "EOR", "LSR", "SRE", "RTS", "ADC", "STP", "RRA", "NOP", "ADC", "ROR", "RRA",
-    "PLA", "ADC", "ROR", "ARR", "JMP", "ADC", "ROR", "RRA", "BVS", "ADC", "STP", "RRA", "NOP",
-    "ADC", "ROR", "RRA", "SEI", "ADC", "NOP", "RRA", "NOP", "ADC", "ROR", "RRA", "NOP", "STA",
-    "NOP", "SAX", "STY", "STA", "STX", "SAX", "DEY", "NOP", "TXA", "XAA", "STY", "STA", "STX",
-    "SAX", "BCC", "STA", "STP", "AHX", "STY", "STA", "STX", "SAX", "TYA", "STA", "TXS", "TAS",
-    "SHY", "STA", "SHX", "AHX", "LDY", "LDA", "LDX", "LAX", "LDY", "LDA", "LDX", "LAX", "TAY",
-    "LDA", "TAX", "LAX", "LDY", "LDA", "LDX", "LAX", "BCS", "LDA", "STP", "LAX", "LDY", "LDA",
-    "LDX", "LAX", "CLV", "LDA", "TSX", "LAS", "LDY", "LDA", "LDX", "LAX", "CPY", "CMP", "NOP",
-    "DCP", "CPY", "CMP", "DEC", "DCP", "INY", "CMP", "DEX", "AXS", "CPY", "CMP", "DEC", "DCP",
-    "BNE", "CMP", "STP", "DCP", "NOP", "CMP", "DEC", "DCP", "CLD", "CMP", "NOP", "DCP", "NOP",
-    "CMP", "DEC", "DCP", "CPX", "SBC", "NOP", "ISC", "CPX", "SBC", "INC", "ISC", "INX", "SBC",
-    "NOP", "SBC", "CPX", "SBC", "INC", "ISC", "BEQ", "SBC", "STP", "ISC", "NOP", "SBC", "INC",
-    "ISC", "SED", "SBC", "NOP", "ISC", "NOP", "SBC", "INC", "ISC",
-];
-
-type BusRead = fn(&mut Mos6502) -> u8;
-type BusWrite = fn(&mut Mos6502, data: u8);
 const STACK_OFFSET: u16 = 0x0100;
+// Banks in traces and breakpoints are counted in 16K units of PRG-ROM
+pub const PROGRAM_BANK_SIZE: usize = 0x4000;
 
 bitflags! {
     pub struct Status: u8 {
@@ -76,6 +168,18 @@ pub struct Mos6502 {
     pointer: u8,
     pub cycle: u32,
     pub mapper: Box<dyn Mapper>,
+    // Set by the KIL opcodes, nothing but a reset gets the CPU going again
+    pub jammed: bool,
+    // NMI is edge triggered so the last level seen is kept to spot the rising edge, IRQ is a level
+    nmi_line: bool,
+    nmi_pending: bool,
+    irq_line: bool,
+    interrupt_poll: InterruptPoll,
+    pub tracer: Option<Tracer>,
+    pub code_data_logger: Option<CodeDataLogger>,
+    pub cheats: Option<Cheats>,
+    // The bus access made by the last cycle, if it made one
+    pub last_access: Option<BusAccess>,
 
     cycle_microcode_queue: VecDeque<MicrocodeTask>,
 }
@@ -98,45 +202,100 @@ impl Mos6502 {
             pointer: 0x00,
             mapper,
             cycle: 0,
+            jammed: false,
+            nmi_line: false,
+            nmi_pending: false,
+            irq_line: false,
+            interrupt_poll: InterruptPoll::None,
+            tracer: None,
+            code_data_logger: None,
+            cheats: None,
+            last_access: None,
 
             cycle_microcode_queue: VecDeque::with_capacity(8),
         }
     }
 
-    fn queue_branch_microcode(&mut self, io: BusRead, op: BranchOperation, microcode: Microcode<BusRead, BranchOperation>) {
+    fn queue_branch_microcode(&mut self, io: BusRead, op: BranchOperation, microcode: BranchMicrocode) {
         self.cycle_microcode_queue.push_back(MicrocodeTask::Branch(io, op, microcode));
     }
 
     fn queue_read(&mut self, io: BusRead, op: ReadOperation)
     {
-        self.queue_read_microcode(io, op, |cpu, io, op| {
-            let data = io(cpu);
-            op(cpu, data)
-        });
+        self.queue_read_microcode(io, op, ReadMicrocode::Read);
     }
 
-    fn queue_read_microcode(&mut self, io: BusRead, op: ReadOperation, microcode: Microcode<BusRead, ReadOperation>)
+    fn queue_read_microcode(&mut self, io: BusRead, op: ReadOperation, microcode: ReadMicrocode)
     {
         self.cycle_microcode_queue.push_back(MicrocodeTask::Read(io, op, microcode));
     }
 
     fn queue_write(&mut self, io: BusWrite, op: WriteOperation) {
-        self.queue_write_microcode(io, op, |cpu, io, op| {
-            let data = op(cpu);
-            io(cpu, data)
-        });
+        self.queue_write_microcode(io, op, WriteMicrocode::Write);
     }
 
-    fn queue_write_microcode(&mut self, io: BusWrite, op: WriteOperation, microcode: Microcode<BusWrite, WriteOperation>) {
+    fn queue_write_microcode(&mut self, io: BusWrite, op: WriteOperation, microcode: WriteMicrocode) {
         self.cycle_microcode_queue.push_back(MicrocodeTask::Write(io, op, microcode));
     }
 
-    fn queue_read_write_microcode(&mut self, io: BusWrite, op: ReadWriteOperation, microcode: Microcode<BusWrite, ReadWriteOperation>) {
+    fn queue_read_write_microcode(&mut self, io: BusWrite, op: ReadWriteOperation, microcode: ReadWriteMicrocode) {
         self.cycle_microcode_queue.push_back(MicrocodeTask::ReadWrite(io, op, microcode));
     }
 
+    // The opcode of the instruction executing now, or the one that just finished at an instruction boundary
+    pub fn opcode(&self) -> u8 {
+        self.opcode
+    }
+
+    // True when the next cycle fetches an opcode, i.e. the previous instruction has fully retired
+    pub fn at_instruction_boundary(&self) -> bool {
+        self.cycle_microcode_queue.is_empty()
+    }
+
+    // The /NMI and /IRQ inputs as they stand at the end of a cycle, active high here
+    pub fn set_interrupt_lines(&mut self, nmi: bool, irq: bool) {
+        if nmi && !self.nmi_line {
+            self.nmi_pending = true;
+        }
+        self.nmi_line = nmi;
+        self.irq_line = irq;
+    }
+
+    fn poll_interrupts(&mut self) {
+        self.interrupt_poll = if self.nmi_pending {
+            InterruptPoll::Nmi
+        } else if self.irq_line && !self.p.contains(Status::INTERRUPT_DISABLE) {
+            InterruptPoll::Irq
+        } else {
+            InterruptPoll::None
+        };
+    }
+
+    // Same as BRK without the opcode fetch and with B clear in the pushed status
+    fn interrupt(&mut self, low: BusRead, high: BusRead) {
+        self.queue_read(BusRead::Pc, ReadOperation::Nop);
+        self.queue_read(BusRead::Pc, ReadOperation::Nop);
+        self.queue_write(BusWrite::PushStack, WriteOperation::PcHigh);
+        self.queue_write(BusWrite::PushStack, WriteOperation::PcLow);
+        self.queue_write(BusWrite::PushStack, WriteOperation::InterruptStatus);
+        self.queue_read(low, ReadOperation::SetPcLow);
+        self.queue_read(high, ReadOperation::SetPcHigh);
+    }
+
     pub fn read(&mut self, address: u16) -> u8 {
-        let data = self.mapper.read(address);
+        if let Some(logger) = self.code_data_logger.as_mut() {
+            // $2007 reads pull the pattern table byte at the VRAM address into the read buffer
+            if address & 0xe007 == 0x2007 {
+                let vram_address = self.mapper.get_ppu().vram_address;
+                logger.log_character_read(self.mapper.as_ref(), vram_address);
+            }
+        }
+
+        let mut data = self.mapper.read(address);
+        if let Some(cheats) = self.cheats.as_ref() {
+            data = cheats.read(address, data);
+        }
+        self.last_access = Some(BusAccess { kind: AccessKind::Read, address, data });
         //println!("\tCPU #${:02x} <- ${:04X}", data, address);
         data
     }
@@ -145,6 +304,13 @@ impl Mos6502 {
         self.read(self.address)
     }
 
+    // Indirect JMP never carries into the high byte of the pointer
+    fn read_address_page_wrapped(&mut self) -> u8 {
+        let low = self.address.get_low().wrapping_add(1);
+        self.address.set_low(low);
+        self.read(self.address)
+    }
+
     fn read_pc_increment(&mut self) -> u8 {
         let data = self.read_pc();
         self.pc += 1; 
@@ -204,8 +370,12 @@ impl Mos6502 {
         self.pc.set_high(data);
     }
 
-    pub fn write(&mut self, address: u16, data: u8) {
+    pub fn write(&mut self, address: u16, mut data: u8) {
+        if let Some(cheats) = self.cheats.as_ref() {
+            data = cheats.write(address, data);
+        }
         self.mapper.write(address, data);
+        self.last_access = Some(BusAccess { kind: AccessKind::Write, address, data });
         //println!("\tCPU #${:02x} -> ${:04X}", data, address);
     }
 
@@ -213,6 +383,58 @@ impl Mos6502 {
         self.write(self.address, data);
     }
 
+    fn jam(&mut self, opcode: u8) {
+        self.jammed = true;
+        self.pc -= 1;
+        if self.tracer.is_some() {
+            let event = self.trace_event(opcode);
+            if let Some(tracer) = self.tracer.as_mut() {
+                tracer.jam(&event);
+            }
+        }
+    }
+
+    // Called straight after the opcode fetch, so PC is already one past it
+    fn trace_event(&mut self, opcode: u8) -> TraceEvent {
+        let pc = self.pc.wrapping_sub(1);
+        let operands = [self.mapper.peek(self.pc), self.mapper.peek(self.pc.wrapping_add(1))];
+        let bank = self.mapper.program_rom_offset(pc).map(|offset| (offset / PROGRAM_BANK_SIZE) as u16);
+        let ppu = self.mapper.get_ppu();
+
+        TraceEvent {
+            pc,
+            opcode,
+            operands,
+            a: self.a,
+            x: self.x,
+            y: self.y,
+            p: self.p.bits,
+            s: self.s,
+            cycle: self.cycle as u64 - 1,
+            scanline: ppu.scanline,
+            dot: ppu.dot,
+            frame: ppu.frame,
+            bank,
+        }
+    }
+
+    fn log_instruction(&mut self, opcode: u8) {
+        if let Some(logger) = self.code_data_logger.as_mut() {
+            let length = disasm::OPCODES[opcode as usize].mode.length();
+            // self.opcode is still the previous instruction here
+            let indirect = self.opcode == 0x6c;
+            logger.log_instruction(self.mapper.as_ref(), self.pc.wrapping_sub(1), length, indirect);
+        }
+    }
+
+    fn log_read(&mut self, io: BusRead, address: u16) {
+        if let Some(logger) = self.code_data_logger.as_mut() {
+            let mode = disasm::OPCODES[self.opcode as usize].mode;
+            let indirect = io == BusRead::Address && matches!(mode, AddressingMode::IndexedIndirectX | AddressingMode::IndirectIndexedY);
+            logger.log_read(self.mapper.as_ref(), address, indirect);
+        }
+    }
+
     fn set_negative_flag(&mut self, data: u8) {
         self.p.set(Status::NEGATIVE, (data as i8) < 0);
     }
@@ -233,262 +455,292 @@ pub trait RP2A03 {
 impl RP2A03 for Mos6502 {
     fn cycle(&mut self) {
         self.cycle += 1;
+        self.last_access = None;
+        if self.jammed {
+            return;
+        }
+
+        // The lines are polled going into an instruction's last cycle, so a flag that cycle changes (CLI, SEI, PLP) or an
+        // NMI edge during it only counts after the next instruction. Taken branches that don't cross a page skip their
+        // last poll, and interrupt sequences never poll, the handler's first instruction always runs.
+        if self.cycle_microcode_queue.len() == 1 {
+            match self.cycle_microcode_queue[0] {
+                MicrocodeTask::Read(BusRead::Pc, ReadOperation::BranchPcLow, _) => {}
+                MicrocodeTask::Read(BusRead::ResetVectorHigh | BusRead::IrqVectorHigh | BusRead::NmiVectorHigh, ..) => {
+                    self.interrupt_poll = InterruptPoll::None;
+                }
+                _ => self.poll_interrupts(),
+            }
+        }
+
+        // Interrupts are only taken between instructions, NMI first
+        if self.cycle_microcode_queue.is_empty() {
+            match std::mem::replace(&mut self.interrupt_poll, InterruptPoll::None) {
+                InterruptPoll::Nmi => {
+                    self.nmi_pending = false;
+                    self.interrupt(BusRead::NmiVectorLow, BusRead::NmiVectorHigh);
+                }
+                InterruptPoll::Irq => self.interrupt(BusRead::IrqVectorLow, BusRead::IrqVectorHigh),
+                InterruptPoll::None => {}
+            }
+        }
+
         let microcode = match self.cycle_microcode_queue.pop_front() {
             Some(microcode) => microcode,
-            None => {
-                MicrocodeTask::Read(Self::read_pc_increment, Self::decode_opcode, 
-                    |cpu, io, op| {
-                        let data = io(cpu);
-                        op(cpu, data)
-                    })
-            },
+            None => MicrocodeTask::Read(BusRead::PcIncrement, ReadOperation::DecodeOpcode, ReadMicrocode::Read),
         };
         
         match microcode {
-            MicrocodeTask::Branch(io, op, microcode) =>  microcode(self, io, op),
-            MicrocodeTask::Read(io, op, microcode) => microcode(self, io, op),
-            MicrocodeTask::Write(io, op, microcode) => microcode(self, io, op),
-            MicrocodeTask::ReadWrite(io, op, microcode) => microcode(self, io, op),
+            MicrocodeTask::Branch(io, op, microcode) => microcode.run(self, io, op),
+            MicrocodeTask::Read(io, op, microcode) => microcode.run(self, io, op),
+            MicrocodeTask::Write(io, op, microcode) => microcode.run(self, io, op),
+            MicrocodeTask::ReadWrite(io, op, microcode) => microcode.run(self, io, op),
         }
     }
 
     //fn decode_opcode(self: &mut Self, mapper: &mut dyn Mapper) {
     fn decode_opcode(self: &mut Self, opcode: u8) {
-        let operand0 = self.mapper.read(self.pc);
-        let operand1 = self.mapper.read(self.pc + 1);
-        println!("{PC:04X} {OP:02X} {ARG0:02X} {ARG1:02X} {Code} A:{A:02X} X:{X:02X} Y:{Y:02X} P:{P:02X} SP:{SP:02X} CYC:{CYC}", 
-            PC = self.pc - 1, OP = opcode, ARG0 = operand0, ARG1 = operand1, Code = OPCODES[opcode as usize], A = self.a, X = self.x, Y = self.y, P = self.p.bits, SP = self.s, CYC = self.cycle);
+        if self.tracer.is_some() {
+            let event = self.trace_event(opcode);
+            if let Some(tracer) = self.tracer.as_mut() {
+                tracer.trace(&event);
+            }
+        }
+        self.log_instruction(opcode);
+
         self.opcode = opcode;
         match opcode {
             //00/04/08/0c/10/14/18/1c
             0x00 => self.brk(),
-            0x04 => (Self::nop as ReadOperation).zero_page(self),
+            0x04 => ReadOperation::Nop.zero_page(self),
             0x08 => self.php(),
-            0x0c => (Self::nop as ReadOperation).absolute(self),
-            0x10 => (Self::bpl as BranchOperation).relative(self),
-            0x14 => (Self::nop as ReadOperation).absolute_indexed_x(self),
-            0x18 => (Self::clc as ReadOperation).implied(self),
-            0x1c => (Self::nop as ReadOperation).absolute_indexed_x(self),
+            0x0c => ReadOperation::Nop.absolute(self),
+            0x10 => BranchOperation::Bpl.relative(self),
+            0x14 => ReadOperation::Nop.absolute_indexed_x(self),
+            0x18 => ReadOperation::Clc.implied(self),
+            0x1c => ReadOperation::Nop.absolute_indexed_x(self),
             0x20 => self.jsr(),
-            0x24 => (Self::bit as ReadOperation).zero_page(self),
+            0x24 => ReadOperation::Bit.zero_page(self),
             0x28 => self.plp(),
-            0x2c => (Self::bit as ReadOperation).absolute(self),
-            0x30 => (Self::bmi as BranchOperation).relative(self),
-            0x38 => (Self::sec as ReadOperation).implied(self),
-            0x3c => (Self::nop as ReadOperation).absolute_indexed_x(self),
+            0x2c => ReadOperation::Bit.absolute(self),
+            0x30 => BranchOperation::Bmi.relative(self),
+            0x38 => ReadOperation::Sec.implied(self),
+            0x3c => ReadOperation::Nop.absolute_indexed_x(self),
             0x40 => self.rti(),
-            0x44 => (Self::nop as ReadOperation).absolute_indexed_x(self),
+            0x44 => ReadOperation::Nop.absolute_indexed_x(self),
             0x48 => self.pha(),
             0x4c => self.jmp(),
-            0x50 => (Self::bvc as BranchOperation).relative(self),
-            0x58 => (Self::cli as ReadOperation).implied(self),
-            0x5c => (Self::nop as ReadOperation).absolute_indexed_x(self),
+            0x50 => BranchOperation::Bvc.relative(self),
+            0x58 => ReadOperation::Cli.implied(self),
+            0x5c => ReadOperation::Nop.absolute_indexed_x(self),
             0x60 => self.rts(),
             0x68 => self.pla(),
             0x6c => self.jmp_indrect(),
-            0x70 => (Self::bvs as BranchOperation).relative(self),
-            0x78 => (Self::sei as ReadOperation).implied(self),
-            0x7c => (Self::nop as ReadOperation).absolute_indexed_x(self),
-            0x80 => (Self::nop as ReadOperation).immediate(self),
-            0x84 => (Self::sty as WriteOperation).zero_page(self),
-            0x88 => (Self::dey as ReadOperation).implied(self),
-            0x8c => (Self::sty as WriteOperation).absolute(self),
-            0x90 => (Self::bcc as BranchOperation).relative(self),
-            0x94 => (Self::sty as WriteOperation).zero_page_indexed_x(self),
-            0x98 => (Self::tya as ReadOperation).implied(self),
-            0xa0 => (Self::ldy as ReadOperation).immediate(self),
-            0xa4 => (Self::ldy as ReadOperation).zero_page(self),
-            0xa8 => (Self::tay as ReadOperation).implied(self),
-            0xac => (Self::ldy as ReadOperation).absolute(self),
-            0xb0 => (Self::bcs as BranchOperation).relative(self),
-            0xb4 => (Self::ldy as ReadOperation).zero_page_indexed_x(self),
-            0xb8 => (Self::clv as ReadOperation).implied(self),
-            0xbc => (Self::ldy as ReadOperation).absolute_indexed_x(self),
-            0xc0 => (Self::cpy as ReadOperation).immediate(self),
-            0xc4 => (Self::cpy as ReadOperation).zero_page(self),
-            0xc8 => (Self::iny as ReadOperation).implied(self),
-            0xcc => (Self::cpy as ReadOperation).absolute(self),
-            0xd0 => (Self::bne as BranchOperation).relative(self),
-            0xd8 => (Self::cld as ReadOperation).implied(self),
-            0xdc => (Self::nop as ReadOperation).absolute_indexed_x(self),
-            0xe0 => (Self::cpx as ReadOperation).immediate(self),
-            0xe4 => (Self::cpx as ReadOperation).zero_page(self),
-            0xe8 => (Self::inx as ReadOperation).implied(self),
-            0xec => (Self::cpx as ReadOperation).absolute(self),
-            0xf0 => (Self::beq as BranchOperation).relative(self),
-            0xf8 => (Self::sed as ReadOperation).implied(self),
-            0xfc => (Self::nop as ReadOperation).absolute_indexed_x(self),
+            0x70 => BranchOperation::Bvs.relative(self),
+            0x78 => ReadOperation::Sei.implied(self),
+            0x7c => ReadOperation::Nop.absolute_indexed_x(self),
+            0x80 => ReadOperation::Nop.immediate(self),
+            0x84 => WriteOperation::Sty.zero_page(self),
+            0x88 => ReadOperation::Dey.implied(self),
+            0x8c => WriteOperation::Sty.absolute(self),
+            0x90 => BranchOperation::Bcc.relative(self),
+            0x94 => WriteOperation::Sty.zero_page_indexed_x(self),
+            0x98 => ReadOperation::Tya.implied(self),
+            0xa0 => ReadOperation::Ldy.immediate(self),
+            0xa4 => ReadOperation::Ldy.zero_page(self),
+            0xa8 => ReadOperation::Tay.implied(self),
+            0xac => ReadOperation::Ldy.absolute(self),
+            0xb0 => BranchOperation::Bcs.relative(self),
+            0xb4 => ReadOperation::Ldy.zero_page_indexed_x(self),
+            0xb8 => ReadOperation::Clv.implied(self),
+            0xbc => ReadOperation::Ldy.absolute_indexed_x(self),
+            0xc0 => ReadOperation::Cpy.immediate(self),
+            0xc4 => ReadOperation::Cpy.zero_page(self),
+            0xc8 => ReadOperation::Iny.implied(self),
+            0xcc => ReadOperation::Cpy.absolute(self),
+            0xd0 => BranchOperation::Bne.relative(self),
+            0xd8 => ReadOperation::Cld.implied(self),
+            0xdc => ReadOperation::Nop.absolute_indexed_x(self),
+            0xe0 => ReadOperation::Cpx.immediate(self),
+            0xe4 => ReadOperation::Cpx.zero_page(self),
+            0xe8 => ReadOperation::Inx.implied(self),
+            0xec => ReadOperation::Cpx.absolute(self),
+            0xf0 => BranchOperation::Beq.relative(self),
+            0xf8 => ReadOperation::Sed.implied(self),
+            0xfc => ReadOperation::Nop.absolute_indexed_x(self),
             //01/05/09/0d/11/15/19/1d
-            0x01 => (Self::ora as ReadOperation).indexed_indirect_x(self),
-            0x05 => (Self::ora as ReadOperation).zero_page(self),
-            0x09 => (Self::ora as ReadOperation).immediate(self),
-            0x0d => (Self::ora as ReadOperation).absolute(self),
-            0x11 => (Self::ora as ReadOperation).indirect_indexed_y(self),
-            0x15 => (Self::ora as ReadOperation).zero_page_indexed_x(self),
-            0x19 => (Self::ora as ReadOperation).absolute_indexed_y(self),
-            0x1d => (Self::ora as ReadOperation).absolute_indexed_x(self),
-            0x21 => (Self::and as ReadOperation).indexed_indirect_x(self),
-            0x25 => (Self::and as ReadOperation).zero_page(self),
-            0x29 => (Self::and as ReadOperation).immediate(self),
-            0x2d => (Self::and as ReadOperation).absolute(self),
-            0x31 => (Self::and as ReadOperation).indirect_indexed_y(self),
-            0x35 => (Self::and as ReadOperation).zero_page_indexed_x(self),
-            0x39 => (Self::and as ReadOperation).absolute_indexed_y(self),
-            0x3d => (Self::and as ReadOperation).absolute_indexed_x(self),
-            0x41 => (Self::eor as ReadOperation).indexed_indirect_x(self),
-            0x45 => (Self::eor as ReadOperation).zero_page(self),
-            0x49 => (Self::eor as ReadOperation).immediate(self),
-            0x4d => (Self::eor as ReadOperation).absolute(self),
-            0x51 => (Self::eor as ReadOperation).indirect_indexed_y(self),
-            0x55 => (Self::eor as ReadOperation).zero_page_indexed_x(self),
-            0x59 => (Self::eor as ReadOperation).absolute_indexed_y(self),
-            0x5d => (Self::eor as ReadOperation).absolute_indexed_x(self),
-            0x61 => (Self::adc as ReadOperation).indexed_indirect_x(self),
-            0x65 => (Self::adc as ReadOperation).zero_page(self),
-            0x69 => (Self::adc as ReadOperation).immediate(self),
-            0x6d => (Self::adc as ReadOperation).absolute(self),
-            0x71 => (Self::adc as ReadOperation).indirect_indexed_y(self),
-            0x75 => (Self::adc as ReadOperation).zero_page_indexed_x(self),
-            0x79 => (Self::adc as ReadOperation).absolute_indexed_y(self),
-            0x7d => (Self::adc as ReadOperation).absolute_indexed_x(self),
-            0x81 => (Self::sta as WriteOperation).indexed_indirect_x(self),
-            0x85 => (Self::sta as WriteOperation).zero_page(self),
-            0x8d => (Self::sta as WriteOperation).absolute(self),
-            0x91 => (Self::sta as WriteOperation).indirect_indexed_y(self),
-            0x95 => (Self::sta as WriteOperation).zero_page_indexed_x(self),
-            0x99 => (Self::sta as WriteOperation).absolute_indexed_y(self),
-            0x9d => (Self::sta as WriteOperation).absolute_indexed_x(self),
-            0xa5 => (Self::lda as ReadOperation).zero_page(self),
-            0xa1 => (Self::lda as ReadOperation).indexed_indirect_x(self),
-            0xad => (Self::lda as ReadOperation).absolute(self),
-            0xa9 => (Self::lda as ReadOperation).immediate(self),
-            0xb1 => (Self::lda as ReadOperation).indirect_indexed_y(self),
-            0xb5 => (Self::lda as ReadOperation).zero_page_indexed_x(self),
-            0xb9 => (Self::lda as ReadOperation).absolute_indexed_y(self),
-            0xbd => (Self::lda as ReadOperation).absolute_indexed_x(self),
-            0xc1 => (Self::cmp as ReadOperation).indexed_indirect_x(self),
-            0xc5 => (Self::cmp as ReadOperation).zero_page(self),
-            0xc9 => (Self::cmp as ReadOperation).immediate(self),
-            0xcd => (Self::cmp as ReadOperation).absolute(self),
-            0xd1 => (Self::cmp as ReadOperation).indirect_indexed_y(self),
-            0xd5 => (Self::cmp as ReadOperation).zero_page_indexed_x(self),
-            0xd9 => (Self::cmp as ReadOperation).absolute_indexed_y(self),
-            0xdd => (Self::cmp as ReadOperation).absolute_indexed_x(self),
-            0xe1 => (Self::sbc as ReadOperation).indexed_indirect_x(self),
-            0xe5 => (Self::sbc as ReadOperation).zero_page(self),
-            0xe9 => (Self::sbc as ReadOperation).immediate(self),
-            0xed => (Self::sbc as ReadOperation).absolute(self),
-            0xf1 => (Self::sbc as ReadOperation).indirect_indexed_y(self),
-            0xf5 => (Self::sbc as ReadOperation).zero_page_indexed_x(self),
-            0xf9 => (Self::sbc as ReadOperation).absolute_indexed_y(self),
-            0xfd => (Self::sbc as ReadOperation).absolute_indexed_x(self),
+            0x01 => ReadOperation::Ora.indexed_indirect_x(self),
+            0x05 => ReadOperation::Ora.zero_page(self),
+            0x09 => ReadOperation::Ora.immediate(self),
+            0x0d => ReadOperation::Ora.absolute(self),
+            0x11 => ReadOperation::Ora.indirect_indexed_y(self),
+            0x15 => ReadOperation::Ora.zero_page_indexed_x(self),
+            0x19 => ReadOperation::Ora.absolute_indexed_y(self),
+            0x1d => ReadOperation::Ora.absolute_indexed_x(self),
+            0x21 => ReadOperation::And.indexed_indirect_x(self),
+            0x25 => ReadOperation::And.zero_page(self),
+            0x29 => ReadOperation::And.immediate(self),
+            0x2d => ReadOperation::And.absolute(self),
+            0x31 => ReadOperation::And.indirect_indexed_y(self),
+            0x35 => ReadOperation::And.zero_page_indexed_x(self),
+            0x39 => ReadOperation::And.absolute_indexed_y(self),
+            0x3d => ReadOperation::And.absolute_indexed_x(self),
+            0x41 => ReadOperation::Eor.indexed_indirect_x(self),
+            0x45 => ReadOperation::Eor.zero_page(self),
+            0x49 => ReadOperation::Eor.immediate(self),
+            0x4d => ReadOperation::Eor.absolute(self),
+            0x51 => ReadOperation::Eor.indirect_indexed_y(self),
+            0x55 => ReadOperation::Eor.zero_page_indexed_x(self),
+            0x59 => ReadOperation::Eor.absolute_indexed_y(self),
+            0x5d => ReadOperation::Eor.absolute_indexed_x(self),
+            0x61 => ReadOperation::Adc.indexed_indirect_x(self),
+            0x65 => ReadOperation::Adc.zero_page(self),
+            0x69 => ReadOperation::Adc.immediate(self),
+            0x6d => ReadOperation::Adc.absolute(self),
+            0x71 => ReadOperation::Adc.indirect_indexed_y(self),
+            0x75 => ReadOperation::Adc.zero_page_indexed_x(self),
+            0x79 => ReadOperation::Adc.absolute_indexed_y(self),
+            0x7d => ReadOperation::Adc.absolute_indexed_x(self),
+            0x81 => WriteOperation::Sta.indexed_indirect_x(self),
+            0x85 => WriteOperation::Sta.zero_page(self),
+            0x8d => WriteOperation::Sta.absolute(self),
+            0x91 => WriteOperation::Sta.indirect_indexed_y(self),
+            0x95 => WriteOperation::Sta.zero_page_indexed_x(self),
+            0x99 => WriteOperation::Sta.absolute_indexed_y(self),
+            0x9d => WriteOperation::Sta.absolute_indexed_x(self),
+            0xa5 => ReadOperation::Lda.zero_page(self),
+            0xa1 => ReadOperation::Lda.indexed_indirect_x(self),
+            0xad => ReadOperation::Lda.absolute(self),
+            0xa9 => ReadOperation::Lda.immediate(self),
+            0xb1 => ReadOperation::Lda.indirect_indexed_y(self),
+            0xb5 => ReadOperation::Lda.zero_page_indexed_x(self),
+            0xb9 => ReadOperation::Lda.absolute_indexed_y(self),
+            0xbd => ReadOperation::Lda.absolute_indexed_x(self),
+            0xc1 => ReadOperation::Cmp.indexed_indirect_x(self),
+            0xc5 => ReadOperation::Cmp.zero_page(self),
+            0xc9 => ReadOperation::Cmp.immediate(self),
+            0xcd => ReadOperation::Cmp.absolute(self),
+            0xd1 => ReadOperation::Cmp.indirect_indexed_y(self),
+            0xd5 => ReadOperation::Cmp.zero_page_indexed_x(self),
+            0xd9 => ReadOperation::Cmp.absolute_indexed_y(self),
+            0xdd => ReadOperation::Cmp.absolute_indexed_x(self),
+            0xe1 => ReadOperation::Sbc.indexed_indirect_x(self),
+            0xe5 => ReadOperation::Sbc.zero_page(self),
+            0xe9 => ReadOperation::Sbc.immediate(self),
+            0xed => ReadOperation::Sbc.absolute(self),
+            0xf1 => ReadOperation::Sbc.indirect_indexed_y(self),
+            0xf5 => ReadOperation::Sbc.zero_page_indexed_x(self),
+            0xf9 => ReadOperation::Sbc.absolute_indexed_y(self),
+            0xfd => ReadOperation::Sbc.absolute_indexed_x(self),
             //02/06/0a/0e/12/16/1a/1e
-            0x06 => (Self::asl as ReadWriteOperation).zero_page(self),
-            0x0a => (Self::asl as ReadWriteOperation).accumulator(self),
-            0x0e => (Self::asl as ReadWriteOperation).absolute(self),
-            0x16 => (Self::asl as ReadWriteOperation).zero_page_indexed_x(self),
-            0x1a => (Self::nop as ReadOperation).implied(self),
-            0x1e => (Self::asl as ReadWriteOperation).absolute_indexed_x(self),
-            0x26 => (Self::rol as ReadWriteOperation).zero_page(self),
-            0x2a => (Self::rol as ReadWriteOperation).accumulator(self),
-            0x2e => (Self::rol as ReadWriteOperation).absolute(self),
-            0x36 => (Self::rol as ReadWriteOperation).zero_page_indexed_x(self),
-            0x3a => (Self::nop as ReadOperation).implied(self),
-            0x3e => (Self::rol as ReadWriteOperation).absolute_indexed_x(self),
-            0x46 => (Self::rol as ReadWriteOperation).zero_page(self),
-            0x4a => (Self::lsr as ReadWriteOperation).accumulator(self),
-            0x4e => (Self::lsr as ReadWriteOperation).absolute(self),
-            0x56 => (Self::lsr as ReadWriteOperation).zero_page_indexed_x(self),
-            0x5a => (Self::nop as ReadOperation).implied(self),
-            0x5e => (Self::lsr as ReadWriteOperation).absolute_indexed_x(self),
-            0x66 => (Self::ror as ReadWriteOperation).zero_page(self),
-            0x6a => (Self::ror as ReadWriteOperation).accumulator(self),
-            0x6e => (Self::ror as ReadWriteOperation).absolute(self),
-            0x76 => (Self::ror as ReadWriteOperation).zero_page_indexed_x(self),
-            0x7a => (Self::nop as ReadOperation).implied(self),
-            0x7e => (Self::ror as ReadWriteOperation).absolute_indexed_x(self),
-            0x8a => (Self::txa as ReadOperation).immediate(self),
-            0x86 => (Self::stx as WriteOperation).zero_page(self),
-            0x8e => (Self::stx as WriteOperation).absolute(self),
-            0x96 => (Self::stx as WriteOperation).zero_page_indexed_y(self),
-            0x9a => (Self::txs as ReadOperation).implied(self),
-            0xa2 => (Self::ldx as ReadOperation).immediate(self),
-            0xa6 => (Self::ldx as ReadOperation).zero_page(self),
-            0xaa => (Self::tax as ReadOperation).implied(self),
-            0xae => (Self::ldx as ReadOperation).absolute(self),
-            0xb6 => (Self::ldx as ReadOperation).zero_page_indexed_x(self),
-            0xba => (Self::tsx as ReadOperation).implied(self),
-            0xbe => (Self::ldx as ReadOperation).absolute_indexed_y(self),
-            0xc6 => (Self::dec as ReadWriteOperation).zero_page(self),
-            0xca => (Self::dex as ReadOperation).implied(self),
-            0xce => (Self::dec as ReadWriteOperation).absolute(self),
-            0xd6 => (Self::dec as ReadWriteOperation).zero_page_indexed_x(self),
-            0xda => (Self::nop as ReadOperation).implied(self),
-            0xde => (Self::dec as ReadWriteOperation).absolute_indexed_x(self),
-            0xe6 => (Self::inc as ReadWriteOperation).zero_page(self),
-            0xea => (Self::nop as ReadOperation).implied(self),
-            0xee => (Self::inc as ReadWriteOperation).absolute(self),
-            0xf6 => (Self::inc as ReadWriteOperation).zero_page_indexed_x(self),
-            0xfa => (Self::nop as ReadOperation).implied(self),
-            0xfe => (Self::inc as ReadWriteOperation).absolute_indexed_x(self),
+            0x06 => ReadWriteOperation::Asl.zero_page(self),
+            0x0a => ReadWriteOperation::Asl.accumulator(self),
+            0x0e => ReadWriteOperation::Asl.absolute(self),
+            0x16 => ReadWriteOperation::Asl.zero_page_indexed_x(self),
+            0x1a => ReadOperation::Nop.implied(self),
+            0x1e => ReadWriteOperation::Asl.absolute_indexed_x(self),
+            0x26 => ReadWriteOperation::Rol.zero_page(self),
+            0x2a => ReadWriteOperation::Rol.accumulator(self),
+            0x2e => ReadWriteOperation::Rol.absolute(self),
+            0x36 => ReadWriteOperation::Rol.zero_page_indexed_x(self),
+            0x3a => ReadOperation::Nop.implied(self),
+            0x3e => ReadWriteOperation::Rol.absolute_indexed_x(self),
+            0x46 => ReadWriteOperation::Rol.zero_page(self),
+            0x4a => ReadWriteOperation::Lsr.accumulator(self),
+            0x4e => ReadWriteOperation::Lsr.absolute(self),
+            0x56 => ReadWriteOperation::Lsr.zero_page_indexed_x(self),
+            0x5a => ReadOperation::Nop.implied(self),
+            0x5e => ReadWriteOperation::Lsr.absolute_indexed_x(self),
+            0x66 => ReadWriteOperation::Ror.zero_page(self),
+            0x6a => ReadWriteOperation::Ror.accumulator(self),
+            0x6e => ReadWriteOperation::Ror.absolute(self),
+            0x76 => ReadWriteOperation::Ror.zero_page_indexed_x(self),
+            0x7a => ReadOperation::Nop.implied(self),
+            0x7e => ReadWriteOperation::Ror.absolute_indexed_x(self),
+            0x8a => ReadOperation::Txa.immediate(self),
+            0x86 => WriteOperation::Stx.zero_page(self),
+            0x8e => WriteOperation::Stx.absolute(self),
+            0x96 => WriteOperation::Stx.zero_page_indexed_y(self),
+            0x9a => ReadOperation::Txs.implied(self),
+            0xa2 => ReadOperation::Ldx.immediate(self),
+            0xa6 => ReadOperation::Ldx.zero_page(self),
+            0xaa => ReadOperation::Tax.implied(self),
+            0xae => ReadOperation::Ldx.absolute(self),
+            0xb6 => ReadOperation::Ldx.zero_page_indexed_x(self),
+            0xba => ReadOperation::Tsx.implied(self),
+            0xbe => ReadOperation::Ldx.absolute_indexed_y(self),
+            0xc6 => ReadWriteOperation::Dec.zero_page(self),
+            0xca => ReadOperation::Dex.implied(self),
+            0xce => ReadWriteOperation::Dec.absolute(self),
+            0xd6 => ReadWriteOperation::Dec.zero_page_indexed_x(self),
+            0xda => ReadOperation::Nop.implied(self),
+            0xde => ReadWriteOperation::Dec.absolute_indexed_x(self),
+            0xe6 => ReadWriteOperation::Inc.zero_page(self),
+            0xea => ReadOperation::Nop.implied(self),
+            0xee => ReadWriteOperation::Inc.absolute(self),
+            0xf6 => ReadWriteOperation::Inc.zero_page_indexed_x(self),
+            0xfa => ReadOperation::Nop.implied(self),
+            0xfe => ReadWriteOperation::Inc.absolute_indexed_x(self),
             //03/07/0b/0f/13/17/1b/1f
-            0x03 => (Self::slo as ReadWriteOperation).indexed_indirect_x(self),
-            0x07 => (Self::slo as ReadWriteOperation).zero_page(self),
-            0x0f => (Self::slo as ReadWriteOperation).absolute(self),
-            0x13 => (Self::slo as ReadWriteOperation).indirect_indexed_y(self),
-            0x17 => (Self::slo as ReadWriteOperation).zero_page_indexed_x(self),
-            0x1b => (Self::slo as ReadWriteOperation).absolute_indexed_y(self),
-            0x1f => (Self::slo as ReadWriteOperation).absolute_indexed_x(self),
-
-            0x23 => (Self::rla as ReadWriteOperation).indexed_indirect_x(self),
-            0x27 => (Self::rla as ReadWriteOperation).zero_page(self),
-            0x2f => (Self::rla as ReadWriteOperation).absolute(self),
-            0x33 => (Self::rla as ReadWriteOperation).indirect_indexed_y(self),
-            0x37 => (Self::rla as ReadWriteOperation).zero_page_indexed_x(self),
-            0x3b => (Self::rla as ReadWriteOperation).absolute_indexed_y(self),
-            0x3f => (Self::rla as ReadWriteOperation).absolute_indexed_x(self),
-
-            0x43 => (Self::sre as ReadWriteOperation).indexed_indirect_x(self),
-            0x47 => (Self::sre as ReadWriteOperation).zero_page(self),
-            0x4f => (Self::sre as ReadWriteOperation).absolute(self),
-            0x53 => (Self::sre as ReadWriteOperation).indirect_indexed_y(self),
-            0x57 => (Self::sre as ReadWriteOperation).zero_page_indexed_x(self),
-            0x5b => (Self::sre as ReadWriteOperation).absolute_indexed_y(self),
-            0x5f => (Self::sre as ReadWriteOperation).absolute_indexed_x(self),
-
-            0x63 => (Self::rra as ReadWriteOperation).indexed_indirect_x(self),
-            0x67 => (Self::rra as ReadWriteOperation).zero_page(self),
-            0x6f => (Self::rra as ReadWriteOperation).absolute(self),
-            0x73 => (Self::rra as ReadWriteOperation).indirect_indexed_y(self),
-            0x77 => (Self::rra as ReadWriteOperation).zero_page_indexed_x(self),
-            0x7b => (Self::rra as ReadWriteOperation).absolute_indexed_y(self),
-            0x7f => (Self::rra as ReadWriteOperation).absolute_indexed_x(self),
-
-            0x83 => (Self::sax as WriteOperation).indexed_indirect_x(self),
-            0x87 => (Self::sax as WriteOperation).zero_page(self),
-            0x8f => (Self::sax as WriteOperation).absolute(self),
-            0x97 => (Self::sax as WriteOperation).zero_page_indexed_y(self),
-            0xa3 => (Self::lax as ReadOperation).indexed_indirect_x(self),
-            0xa7 => (Self::lax as ReadOperation).zero_page(self),
-            0xaf => (Self::lax as ReadOperation).absolute(self),
-            0xb3 => (Self::lax as ReadOperation).indirect_indexed_y(self),
-            0xb7 => (Self::lax as ReadOperation).zero_page_indexed_y(self),
-            0xbf => (Self::lax as ReadOperation).absolute_indexed_y(self),
-            0xc3 => (Self::dcp as ReadWriteOperation).indexed_indirect_x(self),
-            0xc7 => (Self::dcp as ReadWriteOperation).zero_page(self),
-            0xcf => (Self::dcp as ReadWriteOperation).absolute(self),
-            0xd3 => (Self::dcp as ReadWriteOperation).indexed_indirect_x(self),
-            0xd7 => (Self::dcp as ReadWriteOperation).zero_page_indexed_x(self),
-            0xdb => (Self::dcp as ReadWriteOperation).absolute_indexed_y(self),
-            0xdf => (Self::dcp as ReadWriteOperation).absolute_indexed_x(self),
-            0xe3 => (Self::isc as ReadWriteOperation).indexed_indirect_x(self),
-            0xe7 => (Self::isc as ReadWriteOperation).zero_page(self),
-            0xef => (Self::isc as ReadWriteOperation).absolute(self),
-            0xeb => (Self::sbc as ReadOperation).immediate(self),
-            0xf3 => (Self::isc as ReadWriteOperation).indirect_indexed_y(self),
-            0xf7 => (Self::isc as ReadWriteOperation).zero_page_indexed_x(self),
-            0xfb => (Self::isc as ReadWriteOperation).absolute_indexed_y(self),
-            0xff => (Self::isc as ReadWriteOperation).absolute_indexed_x(self),
+            0x03 => ReadWriteOperation::Slo.indexed_indirect_x(self),
+            0x07 => ReadWriteOperation::Slo.zero_page(self),
+            0x0f => ReadWriteOperation::Slo.absolute(self),
+            0x13 => ReadWriteOperation::Slo.indirect_indexed_y(self),
+            0x17 => ReadWriteOperation::Slo.zero_page_indexed_x(self),
+            0x1b => ReadWriteOperation::Slo.absolute_indexed_y(self),
+            0x1f => ReadWriteOperation::Slo.absolute_indexed_x(self),
+
+            0x23 => ReadWriteOperation::Rla.indexed_indirect_x(self),
+            0x27 => ReadWriteOperation::Rla.zero_page(self),
+            0x2f => ReadWriteOperation::Rla.absolute(self),
+            0x33 => ReadWriteOperation::Rla.indirect_indexed_y(self),
+            0x37 => ReadWriteOperation::Rla.zero_page_indexed_x(self),
+            0x3b => ReadWriteOperation::Rla.absolute_indexed_y(self),
+            0x3f => ReadWriteOperation::Rla.absolute_indexed_x(self),
+
+            0x43 => ReadWriteOperation::Sre.indexed_indirect_x(self),
+            0x47 => ReadWriteOperation::Sre.zero_page(self),
+            0x4f => ReadWriteOperation::Sre.absolute(self),
+            0x53 => ReadWriteOperation::Sre.indirect_indexed_y(self),
+            0x57 => ReadWriteOperation::Sre.zero_page_indexed_x(self),
+            0x5b => ReadWriteOperation::Sre.absolute_indexed_y(self),
+            0x5f => ReadWriteOperation::Sre.absolute_indexed_x(self),
+
+            0x63 => ReadWriteOperation::Rra.indexed_indirect_x(self),
+            0x67 => ReadWriteOperation::Rra.zero_page(self),
+            0x6f => ReadWriteOperation::Rra.absolute(self),
+            0x73 => ReadWriteOperation::Rra.indirect_indexed_y(self),
+            0x77 => ReadWriteOperation::Rra.zero_page_indexed_x(self),
+            0x7b => ReadWriteOperation::Rra.absolute_indexed_y(self),
+            0x7f => ReadWriteOperation::Rra.absolute_indexed_x(self),
+
+            0x83 => WriteOperation::Sax.indexed_indirect_x(self),
+            0x87 => WriteOperation::Sax.zero_page(self),
+            0x8f => WriteOperation::Sax.absolute(self),
+            0x97 => WriteOperation::Sax.zero_page_indexed_y(self),
+            0xa3 => ReadOperation::Lax.indexed_indirect_x(self),
+            0xa7 => ReadOperation::Lax.zero_page(self),
+            0xaf => ReadOperation::Lax.absolute(self),
+            0xb3 => ReadOperation::Lax.indirect_indexed_y(self),
+            0xb7 => ReadOperation::Lax.zero_page_indexed_y(self),
+            0xbf => ReadOperation::Lax.absolute_indexed_y(self),
+            0xc3 => ReadWriteOperation::Dcp.indexed_indirect_x(self),
+            0xc7 => ReadWriteOperation::Dcp.zero_page(self),
+            0xcf => ReadWriteOperation::Dcp.absolute(self),
+            0xd3 => ReadWriteOperation::Dcp.indexed_indirect_x(self),
+            0xd7 => ReadWriteOperation::Dcp.zero_page_indexed_x(self),
+            0xdb => ReadWriteOperation::Dcp.absolute_indexed_y(self),
+            0xdf => ReadWriteOperation::Dcp.absolute_indexed_x(self),
+            0xe3 => ReadWriteOperation::Isc.indexed_indirect_x(self),
+            0xe7 => ReadWriteOperation::Isc.zero_page(self),
+            0xef => ReadWriteOperation::Isc.absolute(self),
+            0xeb => ReadOperation::Sbc.immediate(self),
+            0xf3 => ReadWriteOperation::Isc.indirect_indexed_y(self),
+            0xf7 => ReadWriteOperation::Isc.zero_page_indexed_x(self),
+            0xfb => ReadWriteOperation::Isc.absolute_indexed_y(self),
+            0xff => ReadWriteOperation::Isc.absolute_indexed_x(self),
+
+            0x02 | 0x12 | 0x22 | 0x32 | 0x42 | 0x52 | 0x62 | 0x72 | 0x92 | 0xb2 | 0xd2 | 0xf2 => self.jam(opcode),
 
             _ => panic!("Unsupported opcode {:02x}", opcode),
         }
@@ -497,7 +749,64 @@ impl RP2A03 for Mos6502 {
     }
 
     fn reset(self: &mut Self) {
-        self.queue_read(Self::read_fixed::<0xfffc>, Self::set_pc_low);
-        self.queue_read(Self::read_fixed::<0xfffd>, Self::set_pc_high);
+        self.jammed = false;
+        self.nmi_pending = false;
+        self.interrupt_poll = InterruptPoll::None;
+        self.queue_read(BusRead::ResetVectorLow, ReadOperation::SetPcLow);
+        self.queue_read(BusRead::ResetVectorHigh, ReadOperation::SetPcHigh);
+    }
+}
+
+// The mapper is saved separately by the console since it owns the rest of the hardware
+impl SaveState for Mos6502 {
+    fn save_state(&self, writer: &mut dyn Write) -> io::Result<()> {
+        writer.write_all(&[self.a, self.p.bits, self.s, self.x, self.y, self.opcode, self.operand, self.data, self.pointer])?;
+        writer.write_u16::<LittleEndian>(self.pc)?;
+        writer.write_u16::<LittleEndian>(self.address)?;
+        writer.write_u8(self.address_carry as u8)?;
+        writer.write_u32::<LittleEndian>(self.cycle)?;
+        writer.write_u8(self.jammed as u8)?;
+        writer.write_all(&[self.nmi_line as u8, self.nmi_pending as u8, self.irq_line as u8, self.interrupt_poll as u8])?;
+        writer.write_u8(self.cycle_microcode_queue.len() as u8)?;
+        for task in self.cycle_microcode_queue.iter() {
+            task.save(writer)?;
+        }
+
+        Ok(())
+    }
+
+    fn load_state(&mut self, reader: &mut dyn Read) -> io::Result<()> {
+        let mut registers: [u8; 9] = [0; 9];
+        reader.read_exact(&mut registers)?;
+        let [a, p, s, x, y, opcode, operand, data, pointer] = registers;
+        self.a = a;
+        self.p = Status::from_bits_truncate(p);
+        self.s = s;
+        self.x = x;
+        self.y = y;
+        self.opcode = opcode;
+        self.operand = operand;
+        self.data = data;
+        self.pointer = pointer;
+        self.pc = reader.read_u16::<LittleEndian>()?;
+        self.address = reader.read_u16::<LittleEndian>()?;
+        self.address_carry = reader.read_u8()? != 0;
+        self.cycle = reader.read_u32::<LittleEndian>()?;
+        self.jammed = reader.read_u8()? != 0;
+        let mut lines: [u8; 4] = [0; 4];
+        reader.read_exact(&mut lines)?;
+        let [nmi_line, nmi_pending, irq_line, interrupt_poll] = lines;
+        self.nmi_line = nmi_line != 0;
+        self.nmi_pending = nmi_pending != 0;
+        self.irq_line = irq_line != 0;
+        self.interrupt_poll = decode(interrupt_poll)?;
+
+        self.cycle_microcode_queue.clear();
+        for _ in 0..reader.read_u8()? {
+            let task = MicrocodeTask::load(reader)?;
+            self.cycle_microcode_queue.push_back(task);
+        }
+
+        Ok(())
     }
 }
\ No newline at end of file
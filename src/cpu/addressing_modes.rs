@@ -1,36 +1,199 @@
 use std::cmp::Ordering;
 
+use num_enum::TryFromPrimitive;
+
 use crate::address::Address;
 
-use super::{Mos6502, instructions::{WriteOperation, BranchOperation, ReadWriteOperation, ReadOperation}, Operations};
+use super::{Mos6502, BusRead, BusWrite, instructions::{WriteOperation, BranchOperation, ReadWriteOperation, ReadOperation}};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, TryFromPrimitive)]
+#[repr(u8)]
+pub enum BranchMicrocode {
+    Relative,
+}
+
+impl BranchMicrocode {
+    pub(super) fn run(self, cpu: &mut Mos6502, io: BusRead, op: BranchOperation) {
+        match self {
+            Self::Relative => {
+                cpu.operand = io.read(cpu);
+                let should_branch = op.execute(cpu);
+                if should_branch {
+                    cpu.queue_read(BusRead::Pc, ReadOperation::BranchPcLow);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, TryFromPrimitive)]
+#[repr(u8)]
+pub enum ReadMicrocode {
+    Read,
+    AbsoluteIndexedX,
+    AbsoluteIndexedY,
+    IndirectIndexedY,
+}
+
+impl ReadMicrocode {
+    pub(super) fn run(self, cpu: &mut Mos6502, io: BusRead, op: ReadOperation) {
+        match self {
+            Self::Read => {
+                let data = io.read(cpu);
+                op.execute(cpu, data)
+            }
+            Self::AbsoluteIndexedX => {
+                let data = io.read(cpu);
+                cpu.set_address_high(data);
+                let (low, carry) = cpu.address.get_low().overflowing_add(cpu.x);
+                cpu.set_address_low(low);
+                if carry {
+                    cpu.queue_read(BusRead::Address, ReadOperation::AddAddressPage);
+                }
+
+                cpu.queue_read(BusRead::Address, op);
+            }
+            Self::AbsoluteIndexedY => {
+                let data = io.read(cpu);
+                cpu.set_address_high(data);
+                let (low, carry) = cpu.address.get_low().overflowing_add(cpu.y);
+                cpu.set_address_low(low);
+                if carry {
+                    cpu.queue_read(BusRead::Address, ReadOperation::AddAddressPage);
+                }
+
+                cpu.queue_read(BusRead::Address, op);
+            }
+            Self::IndirectIndexedY => {
+                let data = io.read(cpu);
+                cpu.set_address_high(data);
+                let (low, carry) = cpu.address.get_low().overflowing_add(cpu.y);
+                cpu.set_address_low(low);
+
+                if carry {
+                    cpu.queue_read(BusRead::Address, ReadOperation::AddAddressPage);
+                }
+
+                cpu.queue_read(BusRead::Address, op);
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, TryFromPrimitive)]
+#[repr(u8)]
+pub enum WriteMicrocode {
+    Write,
+}
+
+impl WriteMicrocode {
+    pub(super) fn run(self, cpu: &mut Mos6502, io: BusWrite, op: WriteOperation) {
+        match self {
+            Self::Write => {
+                let data = op.execute(cpu);
+                io.write(cpu, data)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, TryFromPrimitive)]
+#[repr(u8)]
+pub enum ReadWriteMicrocode {
+    Modify,
+    Accumulator,
+}
+
+impl ReadWriteMicrocode {
+    pub(super) fn run(self, cpu: &mut Mos6502, io: BusWrite, op: ReadWriteOperation) {
+        match self {
+            Self::Modify => {
+                io.write(cpu, cpu.data);
+                cpu.data = op.execute(cpu, cpu.operand);
+            }
+            Self::Accumulator => {
+                cpu.a = op.execute(cpu, cpu.a);
+                io.write(cpu, cpu.a);
+            }
+        }
+    }
+}
+
+// Address arithmetic done by the addressing modes between bus cycles
+impl Mos6502 {
+    pub(super) fn branch_pc_low(&mut self, _: u8) {
+        let (low, carry) = self.pc.get_low().overflowing_add_signed(self.operand as i8);
+        self.pc.set_low(low);
+
+        if carry {
+            self.queue_read(BusRead::Pc, ReadOperation::BranchPcHigh);
+        }
+    }
+
+    pub(super) fn branch_pc_high(&mut self, _: u8) {
+        let high = match (self.operand as i8).cmp(&0) {
+            Ordering::Less => self.pc.get_high() - 1,
+            Ordering::Equal | Ordering::Greater => self.pc.get_high() + 1,
+        };
+        self.pc.set_high(high);
+    }
+
+    pub(super) fn set_pointer(&mut self, data: u8) {
+        self.pointer = data;
+    }
+
+    pub(super) fn index_pointer_x(&mut self, _: u8) {
+        self.pointer = self.pointer.wrapping_add(self.x);
+    }
+
+    pub(super) fn index_zero_page_x(&mut self, data: u8) {
+        self.set_zero_page_address(data.wrapping_add(self.x));
+    }
+
+    pub(super) fn index_zero_page_y(&mut self, data: u8) {
+        self.set_zero_page_address(data.wrapping_add(self.y));
+    }
+
+    pub(super) fn set_address_high_index_x(&mut self, data: u8) {
+        self.set_address_high(data);
+        let (low, carry) = self.address.get_low().overflowing_add(self.x);
+        self.set_address_low(low);
+        self.address_carry = carry;
+    }
+
+    pub(super) fn set_address_high_index_y(&mut self, data: u8) {
+        self.set_address_high(data);
+        let (low, carry) = self.address.get_low().overflowing_add(self.y);
+        self.set_address_low(low);
+        self.address_carry = carry;
+    }
+
+    pub(super) fn fix_address_carry(&mut self, _: u8) {
+        if self.address_carry {
+            self.address = self.address.wrapping_add(0x100);
+        }
+    }
+
+    pub(super) fn add_address_page(&mut self, _: u8) {
+        self.address = self.address.wrapping_add(0x100);
+    }
+
+    pub(super) fn store_data(&mut self, data: u8) {
+        self.data = data;
+    }
+
+    pub(super) fn store_operand(&mut self, data: u8) {
+        self.operand = data;
+    }
+}
 
-pub type Microcode<TIo, TOp> = fn(&mut Mos6502, io: TIo, op: TOp);
 pub trait BranchAddressingModes {
     fn relative(self, cpu: &mut Mos6502);
 }
 
 impl BranchAddressingModes for BranchOperation {
     fn relative(self, cpu: &mut Mos6502) {
-        cpu.queue_branch_microcode(Mos6502::read_pc_increment, self, |cpu, io, op| {
-            cpu.operand = io(cpu);
-            let should_branch = op(cpu);
-            if should_branch {
-                cpu.queue_read(Mos6502::read_pc, |cpu, _| {
-                    let (low, carry) = cpu.pc.get_low().overflowing_add_signed(cpu.operand as i8);
-                    cpu.pc.set_low(low);
-
-                    if carry {
-                        cpu.queue_read(Mos6502::read_pc, |cpu, _| {
-                            let high = match (cpu.operand as i8).cmp(&0) {
-                                Ordering::Less => cpu.pc.get_high() - 1,
-                                Ordering::Equal | Ordering::Greater => cpu.pc.get_high() + 1,
-                            };
-                            cpu.pc.set_high(high);
-                        });
-                    }
-                });
-            }
-        });
+        cpu.queue_branch_microcode(BusRead::PcIncrement, self, BranchMicrocode::Relative);
     }
 }
 
@@ -50,39 +213,19 @@ pub trait AddressingModes {
 
 impl AddressingModes for ReadOperation {
     fn absolute(self, cpu: &mut Mos6502) {
-        cpu.queue_read(Mos6502::read_pc_increment, Mos6502::set_address_low);
-        cpu.queue_read(Mos6502::read_pc_increment, Mos6502::set_address_high);
-        cpu.queue_read(Mos6502::read_address, self);
+        cpu.queue_read(BusRead::PcIncrement, ReadOperation::SetAddressLow);
+        cpu.queue_read(BusRead::PcIncrement, ReadOperation::SetAddressHigh);
+        cpu.queue_read(BusRead::Address, self);
     }
 
     fn absolute_indexed_x(self, cpu: &mut Mos6502) {
-        cpu.queue_read(Mos6502::read_pc_increment, Mos6502::set_address_low);
-        cpu.queue_read_microcode(Mos6502::read_pc_increment, self, |cpu, io, op| {
-            let data = io(cpu);
-            cpu.set_address_high(data);
-            let (low, carry) = cpu.address.get_low().overflowing_add(cpu.x);
-            cpu.set_address_low(low);
-            if carry {
-                cpu.queue_read(Mos6502::read_address, |cpu, data| cpu.address += 0x100);
-            }
-
-            cpu.queue_read(Mos6502::read_address, op);
-        });
+        cpu.queue_read(BusRead::PcIncrement, ReadOperation::SetAddressLow);
+        cpu.queue_read_microcode(BusRead::PcIncrement, self, ReadMicrocode::AbsoluteIndexedX);
     }
 
     fn absolute_indexed_y(self, cpu: &mut Mos6502) {
-        cpu.queue_read(Mos6502::read_pc_increment, Mos6502::set_address_low);
-        cpu.queue_read_microcode(Mos6502::read_pc_increment, self, |cpu, io, op| {
-            let data = io(cpu);
-            cpu.set_address_high(data);
-            let (low, carry) = cpu.address.get_low().overflowing_add(cpu.y);
-            cpu.set_address_low(low);
-            if carry {
-                cpu.queue_read(Mos6502::read_address, |cpu, data| cpu.address = cpu.address.wrapping_add(0x100));
-            }
-
-            cpu.queue_read(Mos6502::read_address, op);
-        });
+        cpu.queue_read(BusRead::PcIncrement, ReadOperation::SetAddressLow);
+        cpu.queue_read_microcode(BusRead::PcIncrement, self, ReadMicrocode::AbsoluteIndexedY);
     }
 
     fn accumulator(self, cpu: &mut Mos6502) {
@@ -90,97 +233,64 @@ impl AddressingModes for ReadOperation {
     }
 
     fn immediate(self, cpu: &mut Mos6502) {
-        cpu.queue_read(Mos6502::read_pc_increment, self);
+        cpu.queue_read(BusRead::PcIncrement, self);
     }
 
     fn implied(self, cpu: &mut Mos6502) {
-        cpu.queue_read(Mos6502::read_pc, self);
+        cpu.queue_read(BusRead::Pc, self);
     }
 
     fn indexed_indirect_x(self, cpu: &mut Mos6502) {
-        cpu.queue_read(Mos6502::read_pc_increment, |cpu, data| cpu.pointer = data);
-        cpu.queue_read(Mos6502::read_pointer, |cpu, data| cpu.set_zero_page_address(data + cpu.x));
-        cpu.queue_read(Mos6502::read_pointer_increment, Mos6502::set_address_low);
-        cpu.queue_read(Mos6502::read_pointer_increment, Mos6502::set_address_high);
-        cpu.queue_read(Mos6502::read_address, self);
+        cpu.queue_read(BusRead::PcIncrement, ReadOperation::SetPointer);
+        cpu.queue_read(BusRead::Pointer, ReadOperation::IndexZeroPageX);
+        cpu.queue_read(BusRead::PointerIncrement, ReadOperation::SetAddressLow);
+        cpu.queue_read(BusRead::PointerIncrement, ReadOperation::SetAddressHigh);
+        cpu.queue_read(BusRead::Address, self);
     }
 
     fn indirect_indexed_y(self, cpu: &mut Mos6502) {
-        cpu.queue_read(Mos6502::read_pc_increment, |cpu, data| cpu.pointer = data);
-        cpu.queue_read(Mos6502::read_pointer_increment, Mos6502::set_address_low);
-        cpu.queue_read_microcode(Mos6502::read_pointer_increment, Mos6502::set_address_high, |cpu, io, op| {
-            let (low, carry) = cpu.address.get_low().overflowing_add(cpu.y);
-            cpu.set_address_low(low);                
-
-            if carry {
-                cpu.queue_read(Mos6502::read_address, |cpu, _| cpu.address += 0x100);
-            }
-
-            cpu.queue_read(Mos6502::read_address, op);
-        });
+        cpu.queue_read(BusRead::PcIncrement, ReadOperation::SetPointer);
+        cpu.queue_read(BusRead::PointerIncrement, ReadOperation::SetAddressLow);
+        cpu.queue_read_microcode(BusRead::PointerIncrement, self, ReadMicrocode::IndirectIndexedY);
     }
 
     fn zero_page(self, cpu: &mut Mos6502) {
-        cpu.queue_read(Mos6502::read_pc_increment, Mos6502::set_zero_page_address);
-        cpu.queue_read(Mos6502::read_address, self);
+        cpu.queue_read(BusRead::PcIncrement, ReadOperation::SetZeroPageAddress);
+        cpu.queue_read(BusRead::Address, self);
     }
 
     fn zero_page_indexed_x(self, cpu: &mut Mos6502) {
-        cpu.queue_read(Mos6502::read_pc_increment, Mos6502::set_zero_page_address);
-        cpu.queue_read(Mos6502::read_address, |cpu, data| {
-            cpu.set_zero_page_address(data.wrapping_add(cpu.x));
-        });
-        cpu.queue_read(Mos6502::read_address, self);
+        cpu.queue_read(BusRead::PcIncrement, ReadOperation::SetZeroPageAddress);
+        cpu.queue_read(BusRead::Address, ReadOperation::IndexZeroPageX);
+        cpu.queue_read(BusRead::Address, self);
     }
 
     fn zero_page_indexed_y(self, cpu: &mut Mos6502) {
-        cpu.queue_read(Mos6502::read_pc_increment, Mos6502::set_zero_page_address);
-        cpu.queue_read(Mos6502::read_address, |cpu, data| {
-            cpu.set_zero_page_address(data.wrapping_add(cpu.y));
-        });
-        cpu.queue_read(Mos6502::read_address, self);
+        cpu.queue_read(BusRead::PcIncrement, ReadOperation::SetZeroPageAddress);
+        cpu.queue_read(BusRead::Address, ReadOperation::IndexZeroPageY);
+        cpu.queue_read(BusRead::Address, self);
     }
 }
 
 impl AddressingModes for WriteOperation {
     fn absolute(self, cpu: &mut Mos6502) {
-        cpu.queue_read(Mos6502::read_pc_increment, Mos6502::set_address_low);
-        cpu.queue_read(Mos6502::read_pc_increment, Mos6502::set_address_high);
-        cpu.queue_write(Mos6502::write_address, self);
+        cpu.queue_read(BusRead::PcIncrement, ReadOperation::SetAddressLow);
+        cpu.queue_read(BusRead::PcIncrement, ReadOperation::SetAddressHigh);
+        cpu.queue_write(BusWrite::Address, self);
     }
 
     fn absolute_indexed_x(self, cpu: &mut Mos6502) {
-        cpu.queue_read(Mos6502::read_pc_increment, Mos6502::set_address_low);
-        cpu.queue_read(Mos6502::read_pc_increment, |cpu, data| {
-            cpu.set_address_high(data);
-            let (low, carry) = cpu.address.get_low().overflowing_add(cpu.x);
-            cpu.set_address_low(low);
-            cpu.address_carry = carry;
-        });
-        cpu.queue_read(Mos6502::read_address, |cpu, data| {
-            if cpu.address_carry {
-                let high = cpu.address.get_high();
-                cpu.set_address_high(high + 1);
-            }
-        });
-        cpu.queue_write(Mos6502::write_address, self);
+        cpu.queue_read(BusRead::PcIncrement, ReadOperation::SetAddressLow);
+        cpu.queue_read(BusRead::PcIncrement, ReadOperation::SetAddressHighIndexX);
+        cpu.queue_read(BusRead::Address, ReadOperation::FixAddressCarry);
+        cpu.queue_write(BusWrite::Address, self);
     }
 
     fn absolute_indexed_y(self, cpu: &mut Mos6502) {
-        cpu.queue_read(Mos6502::read_pc_increment, Mos6502::set_address_low);
-        cpu.queue_read(Mos6502::read_pc_increment, |cpu, data| {
-            cpu.set_address_high(data);
-            let (low, carry) = cpu.address.get_low().overflowing_add(cpu.y);
-            cpu.set_address_low(low);
-            cpu.address_carry = carry;
-        });
-        cpu.queue_read(Mos6502::read_address, |cpu, data| {
-            if cpu.address_carry {
-                let high = cpu.address.get_high();
-                cpu.set_address_high(high + 1);
-            }
-        });
-        cpu.queue_write(Mos6502::write_address, self);
+        cpu.queue_read(BusRead::PcIncrement, ReadOperation::SetAddressLow);
+        cpu.queue_read(BusRead::PcIncrement, ReadOperation::SetAddressHighIndexY);
+        cpu.queue_read(BusRead::Address, ReadOperation::FixAddressCarry);
+        cpu.queue_write(BusWrite::Address, self);
     }
 
     fn accumulator(self, cpu: &mut Mos6502) {
@@ -196,56 +306,42 @@ impl AddressingModes for WriteOperation {
     }
 
     fn indexed_indirect_x(self, cpu: &mut Mos6502) {
-        cpu.queue_read(Mos6502::read_pc_increment, |cpu, data| cpu.pointer = data);
-        cpu.queue_read(Mos6502::read_pointer, |cpu, data| cpu.pointer = cpu.pointer.wrapping_add(cpu.x));
-        cpu.queue_read(Mos6502::read_pointer_increment, Mos6502::set_address_low);
-        cpu.queue_read(Mos6502::read_pointer_increment, Mos6502::set_address_high);
-        cpu.queue_write(Mos6502::write_address, self);
+        cpu.queue_read(BusRead::PcIncrement, ReadOperation::SetPointer);
+        cpu.queue_read(BusRead::Pointer, ReadOperation::IndexPointerX);
+        cpu.queue_read(BusRead::PointerIncrement, ReadOperation::SetAddressLow);
+        cpu.queue_read(BusRead::PointerIncrement, ReadOperation::SetAddressHigh);
+        cpu.queue_write(BusWrite::Address, self);
     }
 
     fn indirect_indexed_y(self, cpu: &mut Mos6502) {
-        cpu.queue_read(Mos6502::read_pc_increment, |cpu, data| cpu.pointer = data);
-        cpu.queue_read(Mos6502::read_pointer_increment, Mos6502::set_address_low);
-        cpu.queue_read(Mos6502::read_pointer_increment, |cpu, data| {
-            cpu.set_address_high(data);
-            let (low, carry) = cpu.address.get_low().overflowing_add(cpu.y);
-            cpu.set_address_low(low);
-            cpu.address_carry = carry;
-        });
-        cpu.queue_read(Mos6502::read_address, |cpu, _| {
-            if cpu.address_carry {
-                let high = cpu.address.get_high() + 1;
-                cpu.address.set_high(high);
-            }
-        });
-        cpu.queue_write(Mos6502::write_address, self);
+        cpu.queue_read(BusRead::PcIncrement, ReadOperation::SetPointer);
+        cpu.queue_read(BusRead::PointerIncrement, ReadOperation::SetAddressLow);
+        cpu.queue_read(BusRead::PointerIncrement, ReadOperation::SetAddressHighIndexY);
+        cpu.queue_read(BusRead::Address, ReadOperation::FixAddressCarry);
+        cpu.queue_write(BusWrite::Address, self);
     }
 
     fn zero_page(self, cpu: &mut Mos6502) {
-        cpu.queue_read(Mos6502::read_pc_increment, Mos6502::set_zero_page_address);
-        cpu.queue_write(Mos6502::write_address, self);
+        cpu.queue_read(BusRead::PcIncrement, ReadOperation::SetZeroPageAddress);
+        cpu.queue_write(BusWrite::Address, self);
     }
 
     fn zero_page_indexed_x(self, cpu: &mut Mos6502) {
         // 2     PC      R  fetch address, increment PC
-        cpu.queue_read(Mos6502::read_pc_increment, Mos6502::set_zero_page_address);
+        cpu.queue_read(BusRead::PcIncrement, ReadOperation::SetZeroPageAddress);
         // 3   address   R  read from address, add index register to it
-        cpu.queue_read(Mos6502::read_address, |cpu, data| {
-            cpu.set_zero_page_address(data.wrapping_add(cpu.x));
-        });
+        cpu.queue_read(BusRead::Address, ReadOperation::IndexZeroPageX);
         // 4  address+I* W  write to effective address
-        cpu.queue_write(Mos6502::write_address, self);
+        cpu.queue_write(BusWrite::Address, self);
     }
 
     fn zero_page_indexed_y(self, cpu: &mut Mos6502) {
         // 2     PC      R  fetch address, increment PC
-        cpu.queue_read(Mos6502::read_pc_increment, Mos6502::set_zero_page_address);
+        cpu.queue_read(BusRead::PcIncrement, ReadOperation::SetZeroPageAddress);
         // 3   address   R  read from address, add index register to it
-        cpu.queue_read(Mos6502::read_address, |cpu, data| {
-            cpu.set_zero_page_address(data.wrapping_add(cpu.y));
-        });
+        cpu.queue_read(BusRead::Address, ReadOperation::IndexZeroPageY);
         // 4  address+I* W  write to effective address
-        cpu.queue_write(Mos6502::write_address, self);
+        cpu.queue_write(BusWrite::Address, self);
     }
 }
 
@@ -253,19 +349,16 @@ impl AddressingModes for ReadWriteOperation {
     fn absolute(self, cpu: &mut Mos6502) {
         // 1    PC     R  fetch opcode, increment PC
         // 2    PC     R  fetch low byte of address, increment PC
-        cpu.queue_read(Mos6502::read_pc_increment, Mos6502::set_address_low);
+        cpu.queue_read(BusRead::PcIncrement, ReadOperation::SetAddressLow);
         // 3    PC     R  fetch high byte of address, increment PC
-        cpu.queue_read(Mos6502::read_pc_increment, Mos6502::set_address_high);
+        cpu.queue_read(BusRead::PcIncrement, ReadOperation::SetAddressHigh);
         // 4  address  R  read from effective address
-        cpu.queue_read(Mos6502::read_address, |cpu, data| cpu.data = data);
+        cpu.queue_read(BusRead::Address, ReadOperation::StoreData);
         // 5  address  W  write the value back to effective address,
         //                and do the operation on it
-        cpu.queue_read_write_microcode(Mos6502::write_address, self, |cpu, io, op| {
-            io(cpu, cpu.data);
-            cpu.data = op(cpu, cpu.operand);
-        });
+        cpu.queue_read_write_microcode(BusWrite::Address, self, ReadWriteMicrocode::Modify);
         // 6  address  W  write the new value to effective address
-        cpu.queue_write(Mos6502::write_address, |cpu| cpu.data);
+        cpu.queue_write(BusWrite::Address, WriteOperation::Data);
     }
 
     fn absolute_indexed_x(self, cpu: &mut Mos6502) {
@@ -273,33 +366,21 @@ impl AddressingModes for ReadWriteOperation {
         //    --- --------- --- ------------------------------------------
         //     1    PC       R  fetch opcode, increment PC
         //     2    PC       R  fetch low byte of address, increment PC
-        cpu.queue_read(Mos6502::read_pc_increment, Mos6502::set_address_low);
+        cpu.queue_read(BusRead::PcIncrement, ReadOperation::SetAddressLow);
         //     3    PC       R  fetch high byte of address,
         //                      add index register X to low address byte,
         //                      increment PC
-        cpu.queue_read(Mos6502::read_pc_increment, |cpu, data| {
-            cpu.set_address_high(data);
-            let (low, carry) = cpu.address.get_low().overflowing_add(cpu.x);
-            cpu.set_address_low(low);            
-            cpu.address_carry = carry;
-        });
+        cpu.queue_read(BusRead::PcIncrement, ReadOperation::SetAddressHighIndexX);
         //     4  address+X* R  read from effective address,
         //                      fix the high byte of effective address
-        cpu.queue_read(Mos6502::read_address, |cpu, data| {
-            if cpu.address_carry {
-                cpu.address += 0x100;
-            }
-        });
+        cpu.queue_read(BusRead::Address, ReadOperation::FixAddressCarry);
         //     5  address+X  R  re-read from effective address
-        cpu.queue_read(Mos6502::read_address, |cpu, data| cpu.data = data);
+        cpu.queue_read(BusRead::Address, ReadOperation::StoreData);
         //     6  address+X  W  write the value back to effective address,
         //                      and do the operation on it
-        cpu.queue_read_write_microcode(Mos6502::write_address, self, |cpu, io, op| {
-            io(cpu, cpu.data);
-            cpu.data = op(cpu, cpu.operand);
-        });
+        cpu.queue_read_write_microcode(BusWrite::Address, self, ReadWriteMicrocode::Modify);
         //     7  address+X  W  write the new value to effective address
-        cpu.queue_write(Mos6502::write_address, |cpu| cpu.data);
+        cpu.queue_write(BusWrite::Address, WriteOperation::Data);
 
         //    Notes: * The high byte of the effective address may be invalid
         //             at this time, i.e. it may be smaller by $100.
@@ -310,43 +391,28 @@ impl AddressingModes for ReadWriteOperation {
         //    --- --------- --- ------------------------------------------
         //     1    PC       R  fetch opcode, increment PC
         //     2    PC       R  fetch low byte of address, increment PC
-        cpu.queue_read(Mos6502::read_pc_increment, Mos6502::set_address_low);
+        cpu.queue_read(BusRead::PcIncrement, ReadOperation::SetAddressLow);
         //     3    PC       R  fetch high byte of address,
         //                      add index register X to low address byte,
         //                      increment PC
-        cpu.queue_read(Mos6502::read_pc_increment, |cpu, data| {
-            cpu.set_address_high(data);
-            let (low, carry) = cpu.address.get_low().overflowing_add(cpu.y);
-            cpu.set_address_low(low);            
-            cpu.address_carry = carry;
-        });
+        cpu.queue_read(BusRead::PcIncrement, ReadOperation::SetAddressHighIndexY);
         //     4  address+X* R  read from effective address,
         //                      fix the high byte of effective address
-        cpu.queue_read(Mos6502::read_address, |cpu, data| {
-            if cpu.address_carry {
-                cpu.address += 0x100;
-            }
-        });
+        cpu.queue_read(BusRead::Address, ReadOperation::FixAddressCarry);
         //     5  address+X  R  re-read from effective address
-        cpu.queue_read(Mos6502::read_address, |cpu, data| cpu.data = data);
+        cpu.queue_read(BusRead::Address, ReadOperation::StoreData);
         //     6  address+X  W  write the value back to effective address,
         //                      and do the operation on it
-        cpu.queue_read_write_microcode(Mos6502::write_address, self, |cpu, io, op| {
-            io(cpu, cpu.data);
-            cpu.data = op(cpu, cpu.operand);
-        });
+        cpu.queue_read_write_microcode(BusWrite::Address, self, ReadWriteMicrocode::Modify);
         //     7  address+X  W  write the new value to effective address
-        cpu.queue_write(Mos6502::write_address, |cpu| cpu.data);
+        cpu.queue_write(BusWrite::Address, WriteOperation::Data);
 
         //    Notes: * The high byte of the effective address may be invalid
         //             at this time, i.e. it may be smaller by $100.
     }
 
     fn accumulator(self, cpu: &mut Mos6502) {
-        cpu.queue_read_write_microcode(|cpu, _| cpu.data = cpu.read_pc(), self, |cpu, io, op| {
-            cpu.a = op(cpu, cpu.a);
-            io(cpu, cpu.a);
-        })
+        cpu.queue_read_write_microcode(BusWrite::DummyReadPc, self, ReadWriteMicrocode::Accumulator)
     }
 
     fn immediate(self, cpu: &mut Mos6502) {
@@ -362,23 +428,20 @@ impl AddressingModes for ReadWriteOperation {
         //    --- ----------- --- ------------------------------------------
         //     1      PC       R  fetch opcode, increment PC
         //     2      PC       R  fetch pointer address, increment PC
-        cpu.queue_read(Mos6502::read_pc_increment, |cpu, data| cpu.pointer = data);
+        cpu.queue_read(BusRead::PcIncrement, ReadOperation::SetPointer);
         //     3    pointer    R  read from the address, add X to it
-        cpu.queue_read(Mos6502::read_pointer, |cpu, data| cpu.pointer = cpu.pointer.wrapping_add(cpu.x));
+        cpu.queue_read(BusRead::Pointer, ReadOperation::IndexPointerX);
         //     4   pointer+X   R  fetch effective address low
-        cpu.queue_read(Mos6502::read_pointer_increment, Mos6502::set_address_low);
+        cpu.queue_read(BusRead::PointerIncrement, ReadOperation::SetAddressLow);
         //     5  pointer+X+1  R  fetch effective address high
-        cpu.queue_read(Mos6502::read_pointer_increment, Mos6502::set_address_high);
+        cpu.queue_read(BusRead::PointerIncrement, ReadOperation::SetAddressHigh);
         //     6    address    R  read from effective address
-        cpu.queue_read(Mos6502::read_address, Mos6502::nop);
+        cpu.queue_read(BusRead::Address, ReadOperation::Nop);
         //     7    address    W  write the value back to effective address,
         //                        and do the operation on it
-        cpu.queue_read_write_microcode(Mos6502::write_address, self, |cpu, io, op| {
-            io(cpu, cpu.data);
-            cpu.data = op(cpu, cpu.operand);
-        });
+        cpu.queue_read_write_microcode(BusWrite::Address, self, ReadWriteMicrocode::Modify);
         //     8    address    W  write the new value to effective address
-        cpu.queue_write(Mos6502::write_address, |cpu| cpu.data);
+        cpu.queue_write(BusWrite::Address, WriteOperation::Data);
 
         //    Note: The effective address is always fetched from zero page,
         //          i.e. the zero page boundary crossing is not handled.
@@ -389,63 +452,43 @@ impl AddressingModes for ReadWriteOperation {
         // --- ----------- --- ------------------------------------------
         //  1      PC       R  fetch opcode, increment PC
         //  2      PC       R  fetch pointer address, increment PC
-        cpu.queue_read(Mos6502::read_pc_increment, |cpu, data| cpu.pointer = data);
+        cpu.queue_read(BusRead::PcIncrement, ReadOperation::SetPointer);
         //  3    pointer    R  fetch effective address low
-        cpu.queue_read(Mos6502::read_pointer, Mos6502::set_address_low);
+        cpu.queue_read(BusRead::Pointer, ReadOperation::SetAddressLow);
         //  4   pointer+1   R  fetch effective address high,
         //                     add Y to low byte of effective address
-        cpu.queue_read(Mos6502::read_pointer, |cpu, data| {
-            cpu.set_address_high(data);
-            let (low, carry) = cpu.address.get_low().overflowing_add(cpu.y);
-            cpu.set_address_low(low);
-            cpu.address_carry = carry;
-        });
+        cpu.queue_read(BusRead::Pointer, ReadOperation::SetAddressHighIndexY);
         //  5   address+Y*  R  read from effective address,
         //                     fix high byte of effective address
-        cpu.queue_read(Mos6502::read_address, |cpu, data| {
-            if cpu.address_carry {
-                cpu.address += 0x100;
-            }
-        });
+        cpu.queue_read(BusRead::Address, ReadOperation::FixAddressCarry);
         //  6   address+Y   R  read from effective address
-        cpu.queue_read(Mos6502::read_address, |cpu, data| cpu.operand = data);
+        cpu.queue_read(BusRead::Address, ReadOperation::StoreOperand);
         //  7   address+Y   W  write the value back to effective address,
         //                     and do the operation on it
-        cpu.queue_read_write_microcode(Mos6502::write_address, self, |cpu, io, op| {
-            io(cpu, cpu.data);
-            cpu.data = op(cpu, cpu.operand);
-        });
+        cpu.queue_read_write_microcode(BusWrite::Address, self, ReadWriteMicrocode::Modify);
         //  8   address+Y   W  write the new value to effective address
-        cpu.queue_write(Mos6502::write_address, |cpu| cpu.data);
- 
+        cpu.queue_write(BusWrite::Address, WriteOperation::Data);
+
         // Notes: The effective address is always fetched from zero page,
         //        i.e. the zero page boundary crossing is not handled.
- 
+
         //        * The high byte of the effective address may be invalid
         //          at this time, i.e. it may be smaller by $100.
     }
 
     fn zero_page(self, cpu: &mut Mos6502) {
-        cpu.queue_read(Mos6502::read_pc_increment, Mos6502::set_zero_page_address);
-        cpu.queue_read(Mos6502::read_address, |cpu, data| cpu.operand = data);
-        cpu.queue_read_write_microcode(Mos6502::write_address, self, |cpu, io, op| {
-            io(cpu, cpu.data);
-            cpu.data = op(cpu, cpu.operand);
-        });
-        cpu.queue_write(Mos6502::write_address, |cpu| cpu.data);
+        cpu.queue_read(BusRead::PcIncrement, ReadOperation::SetZeroPageAddress);
+        cpu.queue_read(BusRead::Address, ReadOperation::StoreOperand);
+        cpu.queue_read_write_microcode(BusWrite::Address, self, ReadWriteMicrocode::Modify);
+        cpu.queue_write(BusWrite::Address, WriteOperation::Data);
     }
 
     fn zero_page_indexed_x(self, cpu: &mut Mos6502) {
-        cpu.queue_read(Mos6502::read_pc_increment, Mos6502::set_zero_page_address);
-        cpu.queue_read(Mos6502::read_address, |cpu, data| {
-            cpu.set_zero_page_address(data.wrapping_add(cpu.x));
-        });
-        cpu.queue_read(Mos6502::read_address, |cpu, data| cpu.operand = data);
-        cpu.queue_read_write_microcode(Mos6502::write_address, self, |cpu, io, op| {
-            io(cpu, cpu.data);
-            cpu.data = op(cpu, cpu.operand);
-        });
-        cpu.queue_write(Mos6502::write_address, |cpu| cpu.data);
+        cpu.queue_read(BusRead::PcIncrement, ReadOperation::SetZeroPageAddress);
+        cpu.queue_read(BusRead::Address, ReadOperation::IndexZeroPageX);
+        cpu.queue_read(BusRead::Address, ReadOperation::StoreOperand);
+        cpu.queue_read_write_microcode(BusWrite::Address, self, ReadWriteMicrocode::Modify);
+        cpu.queue_write(BusWrite::Address, WriteOperation::Data);
     }
 
     fn zero_page_indexed_y(self, cpu: &mut Mos6502) {
@@ -1,11 +1,206 @@
+use num_enum::TryFromPrimitive;
+
 use crate::address::Address;
 
-use super::{Mos6502, Status};
+use super::{Mos6502, Status, BusRead, BusWrite, RP2A03};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, TryFromPrimitive)]
+#[repr(u8)]
+pub enum BranchOperation {
+    Bcc, Bcs, Beq, Bmi, Bne, Bpl, Bvc, Bvs,
+}
+
+impl BranchOperation {
+    pub(super) fn execute(self, cpu: &mut Mos6502) -> bool {
+        match self {
+            Self::Bcc => cpu.bcc(),
+            Self::Bcs => cpu.bcs(),
+            Self::Beq => cpu.beq(),
+            Self::Bmi => cpu.bmi(),
+            Self::Bne => cpu.bne(),
+            Self::Bpl => cpu.bpl(),
+            Self::Bvc => cpu.bvc(),
+            Self::Bvs => cpu.bvs(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, TryFromPrimitive)]
+#[repr(u8)]
+pub enum ReadOperation {
+    Adc, And, Bit, Clc, Cld, Cli, Clv, Cmp, Cpx, Cpy, Dex, Dey, Eor, Inx, Iny, Lax, Lda, Ldx, Ldy,
+    Nop, Ora, Sbc, Sec, Sed, Sei, Tax, Tay, Tsx, Txa, Txs, Tya,
+
+    DecodeOpcode,
+    SetAddressLow,
+    SetAddressHigh,
+    SetZeroPageAddress,
+    SetPcLow,
+    SetPcHigh,
+    SetPointer,
+    IndexPointerX,
+    IndexZeroPageX,
+    IndexZeroPageY,
+    SetAddressHighIndexX,
+    SetAddressHighIndexY,
+    FixAddressCarry,
+    AddAddressPage,
+    StoreData,
+    StoreOperand,
+    BranchPcLow,
+    BranchPcHigh,
+    JumpAbsolute,
+    JumpSubroutine,
+    BreakVectorHigh,
+    PullAccumulator,
+    PullStatus,
+}
+
+impl ReadOperation {
+    pub(super) fn execute(self, cpu: &mut Mos6502, data: u8) {
+        match self {
+            Self::Adc => cpu.adc(data),
+            Self::And => cpu.and(data),
+            Self::Bit => cpu.bit(data),
+            Self::Clc => cpu.clc(data),
+            Self::Cld => cpu.cld(data),
+            Self::Cli => cpu.cli(data),
+            Self::Clv => cpu.clv(data),
+            Self::Cmp => cpu.cmp(data),
+            Self::Cpx => cpu.cpx(data),
+            Self::Cpy => cpu.cpy(data),
+            Self::Dex => cpu.dex(data),
+            Self::Dey => cpu.dey(data),
+            Self::Eor => cpu.eor(data),
+            Self::Inx => cpu.inx(data),
+            Self::Iny => cpu.iny(data),
+            Self::Lax => cpu.lax(data),
+            Self::Lda => cpu.lda(data),
+            Self::Ldx => cpu.ldx(data),
+            Self::Ldy => cpu.ldy(data),
+            Self::Nop => cpu.nop(data),
+            Self::Ora => cpu.ora(data),
+            Self::Sbc => cpu.sbc(data),
+            Self::Sec => cpu.sec(data),
+            Self::Sed => cpu.sed(data),
+            Self::Sei => cpu.sei(data),
+            Self::Tax => cpu.tax(data),
+            Self::Tay => cpu.tay(data),
+            Self::Tsx => cpu.tsx(data),
+            Self::Txa => cpu.txa(data),
+            Self::Txs => cpu.txs(data),
+            Self::Tya => cpu.tya(data),
+
+            Self::DecodeOpcode => cpu.decode_opcode(data),
+            Self::SetAddressLow => cpu.set_address_low(data),
+            Self::SetAddressHigh => cpu.set_address_high(data),
+            Self::SetZeroPageAddress => cpu.set_zero_page_address(data),
+            Self::SetPcLow => cpu.set_pc_low(data),
+            Self::SetPcHigh => cpu.set_pc_high(data),
+            Self::SetPointer => cpu.set_pointer(data),
+            Self::IndexPointerX => cpu.index_pointer_x(data),
+            Self::IndexZeroPageX => cpu.index_zero_page_x(data),
+            Self::IndexZeroPageY => cpu.index_zero_page_y(data),
+            Self::SetAddressHighIndexX => cpu.set_address_high_index_x(data),
+            Self::SetAddressHighIndexY => cpu.set_address_high_index_y(data),
+            Self::FixAddressCarry => cpu.fix_address_carry(data),
+            Self::AddAddressPage => cpu.add_address_page(data),
+            Self::StoreData => cpu.store_data(data),
+            Self::StoreOperand => cpu.store_operand(data),
+            Self::BranchPcLow => cpu.branch_pc_low(data),
+            Self::BranchPcHigh => cpu.branch_pc_high(data),
+            Self::JumpAbsolute => cpu.jump_absolute(data),
+            Self::JumpSubroutine => cpu.jump_subroutine(data),
+            Self::BreakVectorHigh => cpu.break_vector_high(data),
+            Self::PullAccumulator => cpu.pull_accumulator(data),
+            Self::PullStatus => cpu.pull_status(data),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, TryFromPrimitive)]
+#[repr(u8)]
+pub enum WriteOperation {
+    Sax, Sta, Stx, Sty,
+
+    Data,
+    PcHigh,
+    PcLow,
+    Status,
+    // Pushed by NMI and IRQ, which then mask IRQs
+    InterruptStatus,
+}
+
+impl WriteOperation {
+    pub(super) fn execute(self, cpu: &mut Mos6502) -> u8 {
+        match self {
+            Self::Sax => cpu.sax(),
+            Self::Sta => cpu.sta(),
+            Self::Stx => cpu.stx(),
+            Self::Sty => cpu.sty(),
+
+            Self::Data => cpu.data,
+            Self::PcHigh => cpu.pc.get_high(),
+            Self::PcLow => cpu.pc.get_low(),
+            Self::Status => cpu.p.bits,
+            Self::InterruptStatus => {
+                let status = (cpu.p.bits & !0x10) | 0x20;
+                cpu.p.insert(Status::INTERRUPT_DISABLE);
+                status
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, TryFromPrimitive)]
+#[repr(u8)]
+pub enum ReadWriteOperation {
+    Asl, Dec, Inc, Lsr, Rol, Ror, Dcp, Isc, Rla, Rra, Slo, Sre,
+}
+
+impl ReadWriteOperation {
+    pub(super) fn execute(self, cpu: &mut Mos6502, data: u8) -> u8 {
+        match self {
+            Self::Asl => cpu.asl(data),
+            Self::Dec => cpu.dec(data),
+            Self::Inc => cpu.inc(data),
+            Self::Lsr => cpu.lsr(data),
+            Self::Rol => cpu.rol(data),
+            Self::Ror => cpu.ror(data),
+            Self::Dcp => cpu.dcp(data),
+            Self::Isc => cpu.isc(data),
+            Self::Rla => cpu.rla(data),
+            Self::Rra => cpu.rra(data),
+            Self::Slo => cpu.slo(data),
+            Self::Sre => cpu.sre(data),
+        }
+    }
+}
+
+// Register updates the stack and jump instructions make between bus cycles
+impl Mos6502 {
+    fn jump_absolute(&mut self, data: u8) {
+        self.pc = u16::from_high_low(data, self.address.get_low());
+    }
+
+    fn jump_subroutine(&mut self, data: u8) {
+        self.address.set_high(data);
+        self.pc = self.address;
+    }
+
+    fn break_vector_high(&mut self, data: u8) {
+        self.pc.set_high(data);
+        self.p.set(Status::BREAK, true);
+    }
+
+    fn pull_accumulator(&mut self, data: u8) {
+        self.a = data;
+    }
 
-pub type BranchOperation = fn(&mut Mos6502) -> bool;
-pub type ReadOperation = fn(&mut Mos6502, data: u8);
-pub type WriteOperation = fn(&mut Mos6502) -> u8;
-pub type ReadWriteOperation = fn(&mut Mos6502, data: u8) -> u8;
+    fn pull_status(&mut self, data: u8) {
+        self.p = Status::from_bits_truncate(data);
+    }
+}
 
 pub trait Operations {
     fn adc(&mut self, data: u8);
@@ -127,15 +322,12 @@ impl Operations for Mos6502 {
     }
 
     fn brk(&mut self) {
-        self.queue_read(Self::read_pc_increment, |cpu, _| cpu.p.set(Status::INTERRUPT_DISABLE, true));
-        self.queue_write(Self::push_stack, |cpu| cpu.pc.get_high());
-        self.queue_write(Self::push_stack, |cpu| cpu.pc.get_low());
-        self.queue_write(Self::push_stack, |cpu| cpu.p.bits);
-        self.queue_read(Self::read_fixed::<0xfffe>, |cpu, data| cpu.pc.set_low(data));
-        self.queue_read(Self::read_fixed::<0xffff>, |cpu, data| {
-            cpu.pc.set_high(data);
-            cpu.p.set(Status::BREAK, true);
-        });
+        self.queue_read(BusRead::PcIncrement, ReadOperation::Sei);
+        self.queue_write(BusWrite::PushStack, WriteOperation::PcHigh);
+        self.queue_write(BusWrite::PushStack, WriteOperation::PcLow);
+        self.queue_write(BusWrite::PushStack, WriteOperation::Status);
+        self.queue_read(BusRead::IrqVectorLow, ReadOperation::SetPcLow);
+        self.queue_read(BusRead::IrqVectorHigh, ReadOperation::BreakVectorHigh);
     }
 
     fn bvc(&mut self) -> bool {
@@ -229,10 +421,8 @@ impl Operations for Mos6502 {
     }
 
     fn jmp(&mut self) {
-        self.queue_read(Self::read_pc_increment, Self::set_address_low);
-        self.queue_read(Self::read_pc, |cpu, data| {
-            cpu.pc = u16::from_high_low(data, cpu.address.get_low());
-        });
+        self.queue_read(BusRead::PcIncrement, ReadOperation::SetAddressLow);
+        self.queue_read(BusRead::Pc, ReadOperation::JumpAbsolute);
     }
 
     fn jmp_indrect(&mut self) {
@@ -241,29 +431,21 @@ impl Operations for Mos6502 {
         // --- --------- --- ------------------------------------------
         //  1     PC      R  fetch opcode, increment PC
         //  2     PC      R  fetch pointer address low, increment PC
-        self.queue_read(Self::read_pc_increment, Self::set_address_low);
+        self.queue_read(BusRead::PcIncrement, ReadOperation::SetAddressLow);
         //  3     PC      R  fetch pointer address high, increment PC
-        self.queue_read(Self::read_pc_increment, Self::set_address_high);
+        self.queue_read(BusRead::PcIncrement, ReadOperation::SetAddressHigh);
         //  4   pointer   R  fetch low address to latch
-        self.queue_read(Self::read_address, Self::set_pc_low);
+        self.queue_read(BusRead::Address, ReadOperation::SetPcLow);
         //  5  pointer+1* R  fetch PCH, copy latch to PCL
-        self.queue_read(|cpu| {
-            let low = cpu.address.get_low().wrapping_add(1);
-            cpu.address.set_low(low);
-            let data = cpu.read(cpu.address);
-            data
-        }, Self::set_pc_high);
+        self.queue_read(BusRead::AddressPageWrapped, ReadOperation::SetPcHigh);
     }
 
     fn jsr(&mut self) {
-        self.queue_read(Self::read_pc_increment, |cpu, data| cpu.address.set_low(data));
-        self.queue_read(Self::read_stack, Self::nop);
-        self.queue_write(Self::push_stack, |cpu| cpu.pc.get_high());
-        self.queue_write(Self::push_stack, |cpu| cpu.pc.get_low());
-        self.queue_read(Self::read_pc, |cpu, data| {
-            cpu.address.set_high(data);
-            cpu.pc = cpu.address;
-        });
+        self.queue_read(BusRead::PcIncrement, ReadOperation::SetAddressLow);
+        self.queue_read(BusRead::Stack, ReadOperation::Nop);
+        self.queue_write(BusWrite::PushStack, WriteOperation::PcHigh);
+        self.queue_write(BusWrite::PushStack, WriteOperation::PcLow);
+        self.queue_read(BusRead::Pc, ReadOperation::JumpSubroutine);
     }
 
     fn lda(&mut self, data: u8) {
@@ -301,25 +483,25 @@ impl Operations for Mos6502 {
     }
 
     fn pha(&mut self) {
-        self.queue_read(Self::read_pc, Self::nop);
-        self.queue_write(Self::push_stack, |cpu| cpu.a);
+        self.queue_read(BusRead::Pc, ReadOperation::Nop);
+        self.queue_write(BusWrite::PushStack, WriteOperation::Sta);
     }
 
     fn php(&mut self) {
-        self.queue_read(Self::read_pc, Self::nop);
-        self.queue_write(Self::push_stack, |cpu| cpu.p.bits);
+        self.queue_read(BusRead::Pc, ReadOperation::Nop);
+        self.queue_write(BusWrite::PushStack, WriteOperation::Status);
     }
 
     fn pla(&mut self) {
-        self.queue_read(Self::read_pc, Self::nop);
-        self.queue_read(Self::read_stack, Self::nop);
-        self.queue_read(Self::pop_stack, |cpu, data| cpu.a = data);
+        self.queue_read(BusRead::Pc, ReadOperation::Nop);
+        self.queue_read(BusRead::Stack, ReadOperation::Nop);
+        self.queue_read(BusRead::PopStack, ReadOperation::PullAccumulator);
     }
 
     fn plp(&mut self) {
-        self.queue_read(Self::read_pc, Self::nop);
-        self.queue_read(Self::read_stack, Self::nop);
-        self.queue_read(Self::pop_stack, |cpu, data| cpu.p = Status::from_bits_truncate(data));
+        self.queue_read(BusRead::Pc, ReadOperation::Nop);
+        self.queue_read(BusRead::Stack, ReadOperation::Nop);
+        self.queue_read(BusRead::PopStack, ReadOperation::PullStatus);
     }
 
     fn rol(&mut self, data: u8) -> u8 {
@@ -341,19 +523,19 @@ impl Operations for Mos6502 {
     }
 
     fn rti(&mut self) {
-        self.queue_read(Self::read_pc, Self::nop);
-        self.queue_read(Self::read_stack, Self::nop);
-        self.queue_read(Self::pop_stack, |cpu, data| cpu.p = Status::from_bits_truncate(data));
-        self.queue_read(Self::pop_stack, Self::set_pc_low);
-        self.queue_read(Self::pop_stack, Self::set_pc_high);
+        self.queue_read(BusRead::Pc, ReadOperation::Nop);
+        self.queue_read(BusRead::Stack, ReadOperation::Nop);
+        self.queue_read(BusRead::PopStack, ReadOperation::PullStatus);
+        self.queue_read(BusRead::PopStack, ReadOperation::SetPcLow);
+        self.queue_read(BusRead::PopStack, ReadOperation::SetPcHigh);
     }
 
     fn rts(&mut self) {
-        self.queue_read(Self::read_pc, Self::nop);
-        self.queue_read(Self::read_stack, Self::nop);
-        self.queue_read(Self::pop_stack, Self::set_pc_low);
-        self.queue_read(Self::pop_stack, Self::set_pc_high);
-        self.queue_read(Self::read_pc_increment, Self::nop);
+        self.queue_read(BusRead::Pc, ReadOperation::Nop);
+        self.queue_read(BusRead::Stack, ReadOperation::Nop);
+        self.queue_read(BusRead::PopStack, ReadOperation::SetPcLow);
+        self.queue_read(BusRead::PopStack, ReadOperation::SetPcHigh);
+        self.queue_read(BusRead::PcIncrement, ReadOperation::Nop);
     }
 
     fn sbc(&mut self, data: u8) {
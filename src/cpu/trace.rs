@@ -0,0 +1,219 @@
+use std::any::Any;
+use std::collections::VecDeque;
+use std::io::Write;
+use std::ops::{Range, RangeInclusive};
+
+use crate::disasm::Instruction;
+
+// CPU state at the start of an instruction, taken right after the opcode fetch
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceEvent {
+    pub pc: u16,
+    pub opcode: u8,
+    pub operands: [u8; 2],
+    pub a: u8,
+    pub x: u8,
+    pub y: u8,
+    pub p: u8,
+    pub s: u8,
+    // CPU cycles before the opcode fetch
+    pub cycle: u64,
+    pub scanline: u16,
+    pub dot: u16,
+    pub frame: u64,
+    // Switchable PRG bank the instruction was fetched from, None outside PRG-ROM
+    pub bank: Option<u16>,
+}
+
+impl TraceEvent {
+    pub fn instruction(&self) -> Instruction {
+        Instruction::decode(self.pc, &[self.opcode, self.operands[0], self.operands[1]])
+    }
+}
+
+pub trait TraceSink: Any {
+    fn trace(&mut self, event: &TraceEvent);
+
+    // The CPU hit a KIL opcode and stopped, `event` is the instruction that did it
+    fn jam(&mut self, _event: &TraceEvent) {}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceFormat {
+    Nestest,
+    Mesen,
+    Fceux,
+}
+
+impl TraceFormat {
+    pub fn format(&self, event: &TraceEvent) -> String {
+        let instruction = event.instruction();
+        let bytes: Vec<String> = instruction.bytes().iter().map(|byte| format!("{:02X}", byte)).collect();
+        let (bytes, text) = (bytes.join(" "), instruction.text());
+
+        match self {
+            TraceFormat::Nestest => format!(
+                "{:04X}  {:8}  {:30}  A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X} PPU:{:3},{:3} CYC:{}",
+                event.pc, bytes, text, event.a, event.x, event.y, event.p, event.s, event.scanline, event.dot, event.cycle
+            ),
+            TraceFormat::Mesen => format!(
+                "{:04X}  {:9} {:32} A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X} CYC:{:3} SL:{:3} FC:{} CPU Cycle:{}",
+                event.pc, bytes, text, event.a, event.x, event.y, event.p, event.s, event.dot, event.scanline, event.frame, event.cycle
+            ),
+            TraceFormat::Fceux => format!(
+                "f{:<6} c{:<11} A:{:02X} X:{:02X} Y:{:02X} S:{:02X} P:{}  ${:04X}:{:9} {}",
+                event.frame, event.cycle, event.a, event.x, event.y, event.s, flags(event.p), event.pc, bytes, text
+            ),
+        }
+    }
+}
+
+// FCEUX style, upper case for set flags
+fn flags(p: u8) -> String {
+    "nvubdizc"
+        .chars()
+        .enumerate()
+        .map(|(i, flag)| match p & (0x80 >> i) {
+            0 => flag,
+            _ => flag.to_ascii_uppercase(),
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TraceFilter {
+    pub pc: Option<RangeInclusive<u16>>,
+    pub bank: Option<u16>,
+    pub cycles: Option<Range<u64>>,
+}
+
+impl TraceFilter {
+    pub fn matches(&self, event: &TraceEvent) -> bool {
+        self.pc.as_ref().is_none_or(|pc| pc.contains(&event.pc))
+            && self.bank.is_none_or(|bank| event.bank == Some(bank))
+            && self.cycles.as_ref().is_none_or(|cycles| cycles.contains(&event.cycle))
+    }
+}
+
+// What the CPU holds on to, only instructions the filter lets through reach the sink
+pub struct Tracer {
+    pub filter: TraceFilter,
+    pub sink: Box<dyn TraceSink>,
+}
+
+impl Tracer {
+    pub fn new(sink: Box<dyn TraceSink>) -> Self {
+        Self { filter: TraceFilter::default(), sink }
+    }
+
+    pub fn with_filter(sink: Box<dyn TraceSink>, filter: TraceFilter) -> Self {
+        Self { filter, sink }
+    }
+
+    pub fn trace(&mut self, event: &TraceEvent) {
+        if self.filter.matches(event) {
+            self.sink.trace(event);
+        }
+    }
+
+    pub fn jam(&mut self, event: &TraceEvent) {
+        self.sink.jam(event);
+    }
+
+    pub fn sink_mut<T: TraceSink>(&mut self) -> Option<&mut T> {
+        let sink: &mut dyn Any = self.sink.as_mut();
+        sink.downcast_mut::<T>()
+    }
+}
+
+// Writes every instruction as a line of text, e.g. to a log file or stdout
+pub struct WriterSink<W: Write> {
+    pub format: TraceFormat,
+    writer: W,
+}
+
+impl<W: Write> WriterSink<W> {
+    pub fn new(writer: W, format: TraceFormat) -> Self {
+        Self { format, writer }
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Write + 'static> TraceSink for WriterSink<W> {
+    fn trace(&mut self, event: &TraceEvent) {
+        // A trace that can't be written isn't worth stopping emulation for
+        let _ = writeln!(self.writer, "{}", self.format.format(event));
+    }
+
+    fn jam(&mut self, event: &TraceEvent) {
+        let _ = writeln!(self.writer, "CPU jammed at ${:04X}", event.pc);
+        let _ = self.writer.flush();
+    }
+}
+
+// Keeps only the last `capacity` instructions so there's something to look at after a crash
+pub struct RingBufferSink {
+    pub format: TraceFormat,
+    capacity: usize,
+    events: VecDeque<TraceEvent>,
+    jam_output: Option<Box<dyn Write>>,
+}
+
+impl RingBufferSink {
+    pub fn new(capacity: usize, format: TraceFormat) -> Self {
+        Self {
+            format,
+            capacity,
+            events: VecDeque::with_capacity(capacity),
+            jam_output: None,
+        }
+    }
+
+    // Where the history goes when the CPU jams
+    pub fn dump_on_jam(mut self, output: Box<dyn Write>) -> Self {
+        self.jam_output = Some(output);
+        self
+    }
+
+    pub fn events(&self) -> impl Iterator<Item = &TraceEvent> {
+        self.events.iter()
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        self.events.iter().map(|event| self.format.format(event)).collect()
+    }
+
+    pub fn dump(&self, output: &mut dyn Write) -> std::io::Result<()> {
+        for line in self.lines() {
+            writeln!(output, "{}", line)?;
+        }
+        output.flush()
+    }
+}
+
+impl TraceSink for RingBufferSink {
+    fn trace(&mut self, event: &TraceEvent) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.events.len() == self.capacity {
+            self.events.pop_front();
+        }
+        self.events.push_back(*event);
+    }
+
+    fn jam(&mut self, event: &TraceEvent) {
+        if let Some(mut output) = self.jam_output.take() {
+            let _ = writeln!(output, "CPU jammed at ${:04X}, last {} instructions:", event.pc, self.events.len());
+            let _ = self.dump(output.as_mut());
+            self.jam_output = Some(output);
+        }
+    }
+}
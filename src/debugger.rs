@@ -0,0 +1,242 @@
+mod commands;
+mod expression;
+mod gdb;
+
+use std::ops::RangeInclusive;
+
+use crate::bus::{AccessKind, BusAccess};
+use crate::cpu::PROGRAM_BANK_SIZE;
+use crate::disasm::{Instruction, Labels};
+use crate::system::ConsoleSystem;
+
+pub use self::expression::{Expression, Operator, ParseError, Variable};
+pub use self::gdb::GdbServer;
+
+// Keeps `continue` from hanging the REPL forever when nothing ever hits, about ten seconds of NTSC time
+pub const DEFAULT_RUN_LIMIT: u64 = 1_789_773 * 10;
+
+const JSR: u8 = 0x20;
+const RTS: u8 = 0x60;
+const RTI: u8 = 0x40;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Breakpoint {
+    pub address: u16,
+    // Only stop when this 16K PRG bank is the one mapped at `address`
+    pub bank: Option<u16>,
+    pub condition: Option<Expression>,
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressSpace {
+    Cpu,
+    Ppu,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Watchpoint {
+    pub space: AddressSpace,
+    pub range: RangeInclusive<u16>,
+    pub read: bool,
+    pub write: bool,
+    pub condition: Option<Expression>,
+    pub enabled: bool,
+}
+
+impl Watchpoint {
+    fn matches(&self, space: AddressSpace, access: &BusAccess) -> bool {
+        self.enabled
+            && self.space == space
+            && self.range.contains(&access.address)
+            && match access.kind {
+                AccessKind::Read => self.read,
+                AccessKind::Write => self.write,
+            }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    // Finished whatever step was asked for
+    Step,
+    Breakpoint(usize),
+    Watchpoint(usize, AddressSpace, BusAccess),
+    Scanline,
+    Jammed,
+    // Ran for the whole cycle budget without anything stopping it
+    Limit,
+}
+
+pub struct Debugger {
+    pub system: ConsoleSystem,
+    pub labels: Labels,
+    pub breakpoints: Vec<Breakpoint>,
+    pub watchpoints: Vec<Watchpoint>,
+    pub run_limit: u64,
+}
+
+impl Debugger {
+    pub fn new(system: ConsoleSystem) -> Self {
+        let mut debugger = Self {
+            system,
+            labels: Labels::new(),
+            breakpoints: Vec::new(),
+            watchpoints: Vec::new(),
+            run_limit: DEFAULT_RUN_LIMIT,
+        };
+        debugger.system.cpu.mapper.get_ppu().last_access = None;
+        debugger
+    }
+
+    pub fn add_breakpoint(&mut self, address: u16, bank: Option<u16>, condition: Option<Expression>) -> usize {
+        self.breakpoints.push(Breakpoint { address, bank, condition, enabled: true });
+        self.breakpoints.len() - 1
+    }
+
+    pub fn add_watchpoint(&mut self, space: AddressSpace, range: RangeInclusive<u16>, read: bool, write: bool, condition: Option<Expression>) -> usize {
+        self.watchpoints.push(Watchpoint { space, range, read, write, condition, enabled: true });
+        self.watchpoints.len() - 1
+    }
+
+    // The instruction at PC, only meaningful at an instruction boundary
+    pub fn current_instruction(&self) -> Instruction {
+        Instruction::read(self.system.cpu.mapper.as_ref(), self.system.cpu.pc)
+    }
+
+    // One CPU cycle. Watchpoints are checked, breakpoints aren't since nothing has been fetched yet.
+    pub fn step_cycle(&mut self) -> StopReason {
+        self.cycle().unwrap_or(StopReason::Step)
+    }
+
+    // Runs to the next instruction boundary, stopping early for watchpoints
+    pub fn step_instruction(&mut self) -> StopReason {
+        if self.system.cpu.jammed {
+            return StopReason::Jammed;
+        }
+
+        loop {
+            if let Some(reason) = self.cycle() {
+                return reason;
+            }
+            if self.system.cpu.at_instruction_boundary() {
+                return StopReason::Step;
+            }
+        }
+    }
+
+    // Like step_instruction but runs a JSR through to its return
+    pub fn step_over(&mut self) -> StopReason {
+        let instruction = self.current_instruction();
+        if instruction.opcode != JSR {
+            return self.step_instruction();
+        }
+
+        let (return_address, stack) = (instruction.next_address(), self.system.cpu.s);
+        self.run_until(|system| system.cpu.pc == return_address && system.cpu.s == stack)
+    }
+
+    // Runs until the current subroutine (or interrupt handler) returns
+    pub fn step_out(&mut self) -> StopReason {
+        let stack = self.system.cpu.s;
+        self.run_until(|system| matches!(system.cpu.opcode(), RTS | RTI) && system.cpu.s > stack)
+    }
+
+    // Stops on the first CPU cycle that ends inside `scanline`, dot 0 itself can fall in the middle of a CPU cycle
+    pub fn run_to_scanline(&mut self, scanline: u16) -> StopReason {
+        let mut cycles = 0;
+        loop {
+            let before = self.system.cpu.mapper.get_ppu().scanline;
+            if let Some(reason) = self.cycle() {
+                return reason;
+            }
+            if self.system.cpu.mapper.get_ppu().scanline == scanline && before != scanline {
+                return StopReason::Scanline;
+            }
+            cycles += 1;
+            if cycles >= self.run_limit {
+                return StopReason::Limit;
+            }
+        }
+    }
+
+    // Runs until a breakpoint, watchpoint or the run limit
+    pub fn resume(&mut self) -> StopReason {
+        self.run_until(|_| false)
+    }
+
+    // Checks `done` and breakpoints at each instruction boundary reached. The instruction at the starting PC always gets
+    // to run so resuming from a breakpoint doesn't hit it again straight away.
+    fn run_until(&mut self, mut done: impl FnMut(&mut ConsoleSystem) -> bool) -> StopReason {
+        let mut cycles = 0;
+
+        loop {
+            if self.system.cpu.jammed && self.system.cpu.at_instruction_boundary() {
+                return StopReason::Jammed;
+            }
+            if let Some(reason) = self.cycle() {
+                return reason;
+            }
+
+            if self.system.cpu.at_instruction_boundary() {
+                if done(&mut self.system) {
+                    return StopReason::Step;
+                }
+                if let Some(index) = self.breakpoint_hit() {
+                    return StopReason::Breakpoint(index);
+                }
+            }
+            cycles += 1;
+            if cycles >= self.run_limit {
+                return StopReason::Limit;
+            }
+        }
+    }
+
+    fn breakpoint_hit(&mut self) -> Option<usize> {
+        let pc = self.system.cpu.pc;
+        let bank = self.system.cpu.mapper.program_rom_offset(pc).map(|offset| (offset / PROGRAM_BANK_SIZE) as u16);
+
+        for index in 0..self.breakpoints.len() {
+            let breakpoint = &self.breakpoints[index];
+            if !breakpoint.enabled || breakpoint.address != pc || breakpoint.bank.is_some_and(|wanted| bank != Some(wanted)) {
+                continue;
+            }
+
+            let condition = breakpoint.condition.clone();
+            if condition.is_none_or(|condition| condition.is_true(&mut self.system, 0)) {
+                return Some(index);
+            }
+        }
+        None
+    }
+
+    fn cycle(&mut self) -> Option<StopReason> {
+        self.system.cycle();
+
+        let cpu_access = self.system.cpu.last_access;
+        let ppu_access = self.system.cpu.mapper.get_ppu().last_access.take();
+        if self.watchpoints.is_empty() {
+            return None;
+        }
+
+        let accesses = [(AddressSpace::Cpu, cpu_access), (AddressSpace::Ppu, ppu_access)];
+        for (space, access) in accesses {
+            let Some(access) = access else {
+                continue;
+            };
+
+            for index in 0..self.watchpoints.len() {
+                if !self.watchpoints[index].matches(space, &access) {
+                    continue;
+                }
+
+                let condition = self.watchpoints[index].condition.clone();
+                if condition.is_none_or(|condition| condition.is_true(&mut self.system, access.data)) {
+                    return Some(StopReason::Watchpoint(index, space, access));
+                }
+            }
+        }
+        None
+    }
+}
@@ -0,0 +1,317 @@
+use std::fmt::Write as _;
+use std::path::Path;
+
+use crate::bus::AccessKind;
+use crate::disasm::{Instruction, Labels};
+
+use super::{AddressSpace, Debugger, Expression, StopReason};
+
+const HELP: &str = "\
+s, step [n]              step n instructions
+sc, cycle [n]            step n CPU cycles
+n, next                  step over a JSR
+finish                   run until the current subroutine returns
+c, continue              run until a breakpoint or watchpoint
+scanline <n>             run until the PPU reaches scanline n
+b <addr> [bank <n>] [if <expr>]
+                         break when PC reaches addr
+w [r|w|rw] [cpu|ppu] <start>[-<end>] [if <expr>]
+                         break on reads and/or writes (default rw cpu)
+bl, wl                   list breakpoints or watchpoints
+bd <n>, wd <n>           delete a breakpoint or watchpoint
+r, regs                  show registers
+m <addr> [len]           dump CPU memory
+d [addr] [count]         disassemble
+? <expr>                 evaluate an expression
+labels <file>            load .dbg, .nl or .mlb symbols
+reset                    press reset";
+
+impl Debugger {
+    // Runs one REPL command and returns what to print
+    pub fn execute(&mut self, line: &str) -> Result<String, String> {
+        let line = line.trim();
+        let (command, arguments) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let arguments = arguments.trim();
+
+        match command {
+            "" => Ok(String::new()),
+            "help" | "h" => Ok(HELP.to_string()),
+            "r" | "regs" => Ok(self.describe_position()),
+            "s" | "step" => self.repeat(arguments, Debugger::step_instruction),
+            "sc" | "cycle" => self.repeat(arguments, Debugger::step_cycle),
+            "n" | "next" => {
+                let reason = self.step_over();
+                Ok(self.report(reason))
+            }
+            "finish" => {
+                let reason = self.step_out();
+                Ok(self.report(reason))
+            }
+            "c" | "continue" => {
+                let reason = self.resume();
+                Ok(self.report(reason))
+            }
+            "scanline" => {
+                let scanline = self.number(arguments)? as u16;
+                let reason = self.run_to_scanline(scanline);
+                Ok(self.report(reason))
+            }
+            "b" | "break" => self.break_command(arguments),
+            "w" | "watch" => self.watch_command(arguments),
+            "bl" => Ok(self.list_breakpoints()),
+            "wl" => Ok(self.list_watchpoints()),
+            "bd" => {
+                let index = self.number(arguments)? as usize;
+                (index < self.breakpoints.len()).then(|| self.breakpoints.remove(index)).ok_or("No such breakpoint")?;
+                Ok(format!("Deleted breakpoint {}", index))
+            }
+            "wd" => {
+                let index = self.number(arguments)? as usize;
+                (index < self.watchpoints.len()).then(|| self.watchpoints.remove(index)).ok_or("No such watchpoint")?;
+                Ok(format!("Deleted watchpoint {}", index))
+            }
+            "m" | "mem" => self.memory_command(arguments),
+            "d" | "disasm" => self.disassemble_command(arguments),
+            "?" | "eval" => {
+                let value = self.number(arguments)?;
+                Ok(format!("{} (${:X})", value, value))
+            }
+            "labels" => {
+                let labels = Labels::load(Path::new(arguments)).map_err(|e| format!("Couldn't load {}: {}", arguments, e))?;
+                let count = labels.len();
+                self.labels.merge(labels);
+                Ok(format!("Loaded {} labels", count))
+            }
+            "reset" => {
+                self.system.reset();
+                Ok("Reset".to_string())
+            }
+            _ => Err(format!("Unknown command {}, try help", command)),
+        }
+    }
+
+    fn repeat(&mut self, arguments: &str, step: fn(&mut Debugger) -> StopReason) -> Result<String, String> {
+        let count = match arguments {
+            "" => 1,
+            count => self.number(count)?,
+        };
+
+        let mut reason = StopReason::Step;
+        for _ in 0..count {
+            reason = step(self);
+            if reason != StopReason::Step {
+                break;
+            }
+        }
+        Ok(self.report(reason))
+    }
+
+    fn parse(&self, text: &str) -> Result<Expression, String> {
+        Expression::parse_with_labels(text, &self.labels).map_err(|e| e.to_string())
+    }
+
+    fn number(&mut self, text: &str) -> Result<i64, String> {
+        if text.is_empty() {
+            return Err("Expected a number".to_string());
+        }
+        let expression = self.parse(text)?;
+        Ok(expression.evaluate(&mut self.system, 0))
+    }
+
+    fn break_command(&mut self, arguments: &str) -> Result<String, String> {
+        let (location, condition) = split_condition(arguments);
+        let (address, bank) = match location.split_once(" bank ") {
+            Some((address, bank)) => (address, Some(self.number(bank.trim())? as u16)),
+            None => (location, None),
+        };
+
+        let address = self.number(address.trim())? as u16;
+        let condition = condition.map(|text| self.parse(text)).transpose()?;
+        let index = self.add_breakpoint(address, bank, condition);
+        Ok(format!("Breakpoint {} at ${:04X}", index, address))
+    }
+
+    fn watch_command(&mut self, arguments: &str) -> Result<String, String> {
+        let (location, condition) = split_condition(arguments);
+        let mut words = location.split_whitespace().peekable();
+
+        let (read, write) = match words.peek() {
+            Some(&"r") => (true, false),
+            Some(&"w") => (false, true),
+            Some(&"rw") => (true, true),
+            _ => (true, true),
+        };
+        if matches!(words.peek(), Some(&"r" | &"w" | &"rw")) {
+            words.next();
+        }
+
+        let space = match words.peek() {
+            Some(&"ppu") => AddressSpace::Ppu,
+            _ => AddressSpace::Cpu,
+        };
+        if matches!(words.peek(), Some(&"ppu" | &"cpu")) {
+            words.next();
+        }
+
+        let range: Vec<&str> = words.collect();
+        let range = range.join(" ");
+        let (start, end) = match range.split_once('-') {
+            Some((start, end)) if !start.trim().is_empty() => (self.number(start.trim())? as u16, self.number(end.trim())? as u16),
+            _ => {
+                let address = self.number(range.trim())? as u16;
+                (address, address)
+            }
+        };
+
+        let condition = condition.map(|text| self.parse(text)).transpose()?;
+        let index = self.add_watchpoint(space, start..=end, read, write, condition);
+        Ok(format!("Watchpoint {} on {}", index, describe_range(space, start, end)))
+    }
+
+    fn list_breakpoints(&self) -> String {
+        let mut list = String::new();
+        for (index, breakpoint) in self.breakpoints.iter().enumerate() {
+            let _ = write!(list, "{}: ${:04X}", index, breakpoint.address);
+            if let Some(bank) = breakpoint.bank {
+                let _ = write!(list, " bank {}", bank);
+            }
+            if breakpoint.condition.is_some() {
+                let _ = write!(list, " (conditional)");
+            }
+            let _ = writeln!(list);
+        }
+        list
+    }
+
+    fn list_watchpoints(&self) -> String {
+        let mut list = String::new();
+        for (index, watchpoint) in self.watchpoints.iter().enumerate() {
+            let kind = match (watchpoint.read, watchpoint.write) {
+                (true, true) => "rw",
+                (true, false) => "r",
+                _ => "w",
+            };
+            let range = describe_range(watchpoint.space, *watchpoint.range.start(), *watchpoint.range.end());
+            let _ = write!(list, "{}: {} {}", index, kind, range);
+            if watchpoint.condition.is_some() {
+                let _ = write!(list, " (conditional)");
+            }
+            let _ = writeln!(list);
+        }
+        list
+    }
+
+    fn memory_command(&mut self, arguments: &str) -> Result<String, String> {
+        let mut words = arguments.split_whitespace();
+        let start = self.number(words.next().unwrap_or(""))? as u16;
+        let length = match words.next() {
+            Some(length) => self.number(length)? as u16,
+            None => 0x40,
+        };
+
+        let mut dump = String::new();
+        for row in (0..length).step_by(16) {
+            let address = start.wrapping_add(row);
+            let _ = write!(dump, "{:04X}:", address);
+            for column in 0..16.min(length - row) {
+                let _ = write!(dump, " {:02X}", self.system.cpu.mapper.peek(address.wrapping_add(column)));
+            }
+            let _ = writeln!(dump);
+        }
+        Ok(dump)
+    }
+
+    fn disassemble_command(&mut self, arguments: &str) -> Result<String, String> {
+        let mut words = arguments.split_whitespace();
+        let mut address = match words.next() {
+            Some(address) => self.number(address)? as u16,
+            None => self.system.cpu.pc,
+        };
+        let count = match words.next() {
+            Some(count) => self.number(count)?,
+            None => 10,
+        };
+
+        let mut listing = String::new();
+        for _ in 0..count {
+            let instruction = Instruction::read(self.system.cpu.mapper.as_ref(), address);
+            let _ = writeln!(listing, "{}", self.describe_instruction(&instruction));
+            address = instruction.next_address();
+        }
+        Ok(listing)
+    }
+
+    fn describe_instruction(&self, instruction: &Instruction) -> String {
+        let mapper = self.system.cpu.mapper.as_ref();
+        let bytes: Vec<String> = instruction.bytes().iter().map(|byte| format!("{:02X}", byte)).collect();
+        let label = match self.labels.lookup(instruction.address, mapper) {
+            Some(label) => format!("{}:", label),
+            None => String::new(),
+        };
+
+        format!("{:04X}  {:8}  {:16} {}", instruction.address, bytes.join(" "), label, instruction.annotate(&self.labels, mapper))
+    }
+
+    fn describe_position(&mut self) -> String {
+        let instruction = self.current_instruction();
+        let cpu = &self.system.cpu;
+        let registers = format!(
+            "A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X} CYC:{}",
+            cpu.a, cpu.x, cpu.y, cpu.p.bits(), cpu.s, cpu.cycle
+        );
+        let ppu = self.system.cpu.mapper.get_ppu();
+        let timing = format!("SL:{} DOT:{} FRAME:{}", ppu.scanline, ppu.dot, ppu.frame);
+
+        let position = match self.system.cpu.at_instruction_boundary() {
+            true => self.describe_instruction(&instruction),
+            false => format!("{:04X}  (mid-instruction)", self.system.cpu.pc),
+        };
+        format!("{}\n{} {}", position, registers, timing)
+    }
+
+    fn report(&mut self, reason: StopReason) -> String {
+        let headline = match reason {
+            StopReason::Step => None,
+            StopReason::Breakpoint(index) => Some(format!("Breakpoint {}", index)),
+            StopReason::Watchpoint(index, space, access) => {
+                let (kind, arrow) = match access.kind {
+                    AccessKind::Read => ("read", "->"),
+                    AccessKind::Write => ("write", "<-"),
+                };
+                let space = match space {
+                    AddressSpace::Cpu => "",
+                    AddressSpace::Ppu => " PPU",
+                };
+                Some(format!("Watchpoint {}: {}{} ${:04X} {} ${:02X}", index, kind, space, access.address, arrow, access.data))
+            }
+            StopReason::Scanline => None,
+            StopReason::Jammed => Some("CPU jammed".to_string()),
+            StopReason::Limit => Some(format!("Stopped after {} cycles", self.run_limit)),
+        };
+
+        let position = self.describe_position();
+        match headline {
+            Some(headline) => format!("{}\n{}", headline, position),
+            None => position,
+        }
+    }
+}
+
+// "<location> if <expression>"
+fn split_condition(arguments: &str) -> (&str, Option<&str>) {
+    match arguments.split_once(" if ") {
+        Some((location, condition)) => (location.trim(), Some(condition.trim())),
+        None => (arguments.trim(), None),
+    }
+}
+
+fn describe_range(space: AddressSpace, start: u16, end: u16) -> String {
+    let space = match space {
+        AddressSpace::Cpu => "CPU",
+        AddressSpace::Ppu => "PPU",
+    };
+    match start == end {
+        true => format!("{} ${:04X}", space, start),
+        false => format!("{} ${:04X}-${:04X}", space, start, end),
+    }
+}
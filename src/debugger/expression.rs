@@ -0,0 +1,300 @@
+use std::fmt;
+
+use crate::disasm::Labels;
+use crate::system::ConsoleSystem;
+
+// Conditions for breakpoints and watchpoints, e.g. `a == $10 && [$0300] != 0` or `x >= 3 || value & $80`.
+// Registers: a x y s p pc, timing: cycle scanline dot frame, `value` is the byte a watchpoint saw and `[addr]` reads
+// a byte of CPU memory. Anything non-zero is true.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expression {
+    Number(i64),
+    Variable(Variable),
+    Memory(Box<Expression>),
+    Not(Box<Expression>),
+    Negate(Box<Expression>),
+    Binary(Box<Expression>, Operator, Box<Expression>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Variable {
+    A,
+    X,
+    Y,
+    S,
+    P,
+    Pc,
+    Cycle,
+    Scanline,
+    Dot,
+    Frame,
+    Value,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Or,
+    And,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    BitOr,
+    BitXor,
+    BitAnd,
+    Add,
+    Subtract,
+}
+
+impl Operator {
+    fn precedence(&self) -> u8 {
+        match self {
+            Operator::Or => 1,
+            Operator::And => 2,
+            Operator::BitOr => 3,
+            Operator::BitXor => 4,
+            Operator::BitAnd => 5,
+            Operator::Equal | Operator::NotEqual => 6,
+            Operator::Less | Operator::LessEqual | Operator::Greater | Operator::GreaterEqual => 7,
+            Operator::Add | Operator::Subtract => 8,
+        }
+    }
+
+    fn apply(&self, left: i64, right: i64) -> i64 {
+        match self {
+            Operator::Or => (left != 0 || right != 0) as i64,
+            Operator::And => (left != 0 && right != 0) as i64,
+            Operator::Equal => (left == right) as i64,
+            Operator::NotEqual => (left != right) as i64,
+            Operator::Less => (left < right) as i64,
+            Operator::LessEqual => (left <= right) as i64,
+            Operator::Greater => (left > right) as i64,
+            Operator::GreaterEqual => (left >= right) as i64,
+            Operator::BitOr => left | right,
+            Operator::BitXor => left ^ right,
+            Operator::BitAnd => left & right,
+            Operator::Add => left.wrapping_add(right),
+            Operator::Subtract => left.wrapping_sub(right),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError(pub String);
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Number(i64),
+    Name(String),
+    Operator(Operator),
+    Not,
+    Minus,
+    Open,
+    Close,
+    OpenBracket,
+    CloseBracket,
+}
+
+impl Expression {
+    pub fn parse(text: &str) -> Result<Self, ParseError> {
+        Self::parse_with_labels(text, &Labels::new())
+    }
+
+    // Label names can stand in for the address they're attached to
+    pub fn parse_with_labels(text: &str, labels: &Labels) -> Result<Self, ParseError> {
+        let tokens = tokenize(text)?;
+        let mut parser = Parser { tokens, position: 0, labels };
+        let expression = parser.expression(0)?;
+        match parser.tokens.get(parser.position) {
+            None => Ok(expression),
+            Some(token) => Err(ParseError(format!("Unexpected {:?}", token))),
+        }
+    }
+
+    pub fn evaluate(&self, system: &mut ConsoleSystem, value: u8) -> i64 {
+        match self {
+            Expression::Number(number) => *number,
+            Expression::Variable(variable) => {
+                let cpu = &system.cpu;
+                match variable {
+                    Variable::A => cpu.a as i64,
+                    Variable::X => cpu.x as i64,
+                    Variable::Y => cpu.y as i64,
+                    Variable::S => cpu.s as i64,
+                    Variable::P => cpu.p.bits() as i64,
+                    Variable::Pc => cpu.pc as i64,
+                    Variable::Cycle => cpu.cycle as i64,
+                    Variable::Scanline => system.cpu.mapper.get_ppu().scanline as i64,
+                    Variable::Dot => system.cpu.mapper.get_ppu().dot as i64,
+                    Variable::Frame => system.cpu.mapper.get_ppu().frame as i64,
+                    Variable::Value => value as i64,
+                }
+            }
+            Expression::Memory(address) => {
+                let address = address.evaluate(system, value) as u16;
+                system.cpu.mapper.peek(address) as i64
+            }
+            Expression::Not(inner) => (inner.evaluate(system, value) == 0) as i64,
+            Expression::Negate(inner) => inner.evaluate(system, value).wrapping_neg(),
+            Expression::Binary(left, Operator::And, right) => {
+                (left.evaluate(system, value) != 0 && right.evaluate(system, value) != 0) as i64
+            }
+            Expression::Binary(left, Operator::Or, right) => {
+                (left.evaluate(system, value) != 0 || right.evaluate(system, value) != 0) as i64
+            }
+            Expression::Binary(left, operator, right) => operator.apply(left.evaluate(system, value), right.evaluate(system, value)),
+        }
+    }
+
+    pub fn is_true(&self, system: &mut ConsoleSystem, value: u8) -> bool {
+        self.evaluate(system, value) != 0
+    }
+}
+
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    position: usize,
+    labels: &'a Labels,
+}
+
+impl Parser<'_> {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), ParseError> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            other => Err(ParseError(format!("Expected {:?}, found {:?}", expected, other))),
+        }
+    }
+
+    // Precedence climbing, everything is left associative
+    fn expression(&mut self, minimum: u8) -> Result<Expression, ParseError> {
+        let mut left = self.unary()?;
+        while let Some(Token::Operator(operator)) = self.tokens.get(self.position).cloned() {
+            if operator.precedence() < minimum {
+                break;
+            }
+            self.position += 1;
+            let right = self.expression(operator.precedence() + 1)?;
+            left = Expression::Binary(Box::new(left), operator, Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expression, ParseError> {
+        match self.next() {
+            Some(Token::Number(number)) => Ok(Expression::Number(number)),
+            Some(Token::Name(name)) => variable(&name)
+                .map(Expression::Variable)
+                .or_else(|| self.labels.address_of(&name).map(|address| Expression::Number(address as i64)))
+                .ok_or_else(|| ParseError(format!("Unknown name {}", name))),
+            Some(Token::Not) => Ok(Expression::Not(Box::new(self.unary()?))),
+            Some(Token::Minus) => Ok(Expression::Negate(Box::new(self.unary()?))),
+            Some(Token::Open) => {
+                let inner = self.expression(0)?;
+                self.expect(Token::Close)?;
+                Ok(inner)
+            }
+            Some(Token::OpenBracket) => {
+                let inner = self.expression(0)?;
+                self.expect(Token::CloseBracket)?;
+                Ok(Expression::Memory(Box::new(inner)))
+            }
+            other => Err(ParseError(format!("Unexpected {:?}", other))),
+        }
+    }
+}
+
+fn variable(name: &str) -> Option<Variable> {
+    Some(match name.to_ascii_lowercase().as_str() {
+        "a" => Variable::A,
+        "x" => Variable::X,
+        "y" => Variable::Y,
+        "s" | "sp" => Variable::S,
+        "p" => Variable::P,
+        "pc" => Variable::Pc,
+        "cycle" => Variable::Cycle,
+        "scanline" => Variable::Scanline,
+        "dot" => Variable::Dot,
+        "frame" => Variable::Frame,
+        "value" => Variable::Value,
+        _ => return None,
+    })
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, ParseError> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let token = match c {
+            ' ' | '\t' => continue,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '[' => Token::OpenBracket,
+            ']' => Token::CloseBracket,
+            '+' => Token::Operator(Operator::Add),
+            '-' => match tokens.last() {
+                Some(Token::Number(_) | Token::Name(_) | Token::Close | Token::CloseBracket) => Token::Operator(Operator::Subtract),
+                _ => Token::Minus,
+            },
+            '^' => Token::Operator(Operator::BitXor),
+            '&' if chars.next_if_eq(&'&').is_some() => Token::Operator(Operator::And),
+            '&' => Token::Operator(Operator::BitAnd),
+            '|' if chars.next_if_eq(&'|').is_some() => Token::Operator(Operator::Or),
+            '|' => Token::Operator(Operator::BitOr),
+            '=' if chars.next_if_eq(&'=').is_some() => Token::Operator(Operator::Equal),
+            '!' if chars.next_if_eq(&'=').is_some() => Token::Operator(Operator::NotEqual),
+            '!' => Token::Not,
+            '<' if chars.next_if_eq(&'=').is_some() => Token::Operator(Operator::LessEqual),
+            '<' => Token::Operator(Operator::Less),
+            '>' if chars.next_if_eq(&'=').is_some() => Token::Operator(Operator::GreaterEqual),
+            '>' => Token::Operator(Operator::Greater),
+            '$' => Token::Number(hex_number(&mut chars)?),
+            '0'..='9' => {
+                let mut digits = c.to_string();
+                if c == '0' && chars.next_if(|c| *c == 'x' || *c == 'X').is_some() {
+                    Token::Number(hex_number(&mut chars)?)
+                } else {
+                    while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+                        digits.push(digit);
+                    }
+                    Token::Number(digits.parse().map_err(|_| ParseError(format!("Bad number {}", digits)))?)
+                }
+            }
+            c if c.is_alphabetic() || c == '_' || c == '@' => {
+                let mut name = c.to_string();
+                while let Some(c) = chars.next_if(|c| c.is_alphanumeric() || *c == '_' || *c == '@') {
+                    name.push(c);
+                }
+                Token::Name(name)
+            }
+            c => return Err(ParseError(format!("Unexpected character {}", c))),
+        };
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+fn hex_number(chars: &mut std::iter::Peekable<std::str::Chars>) -> Result<i64, ParseError> {
+    let mut digits = String::new();
+    while let Some(digit) = chars.next_if(char::is_ascii_hexdigit) {
+        digits.push(digit);
+    }
+    i64::from_str_radix(&digits, 16).map_err(|_| ParseError(format!("Bad hex number ${}", digits)))
+}
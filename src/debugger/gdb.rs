@@ -0,0 +1,414 @@
+use std::fmt::Write as _;
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+
+use super::{AddressSpace, Debugger, StopReason};
+
+// How long `continue` runs between checks for a ^C from the client, about a frame
+const INTERRUPT_POLL_CYCLES: u64 = 29_781;
+
+// Largest packet the client may send or get back, what qSupported advertises
+const PACKET_SIZE: usize = 0x1000;
+
+const SIGINT: u8 = 2;
+const SIGILL: u8 = 4;
+const SIGTRAP: u8 = 5;
+
+// Registers in `g` packet order, PC is sent little endian
+const TARGET_XML: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE target SYSTEM "gdb-target.dtd">
+<target version="1.0">
+  <feature name="org.gnu.gdb.mos6502.core">
+    <reg name="a" bitsize="8" regnum="0" type="uint8"/>
+    <reg name="x" bitsize="8" regnum="1" type="uint8"/>
+    <reg name="y" bitsize="8" regnum="2" type="uint8"/>
+    <reg name="p" bitsize="8" regnum="3" type="uint8"/>
+    <reg name="s" bitsize="8" regnum="4" type="uint8"/>
+    <reg name="pc" bitsize="16" regnum="5" type="code_ptr"/>
+  </feature>
+</target>
+"#;
+
+// A GDB remote serial protocol stub on top of the debugger, one client at a time
+pub struct GdbServer {
+    pub debugger: Debugger,
+    no_ack: bool,
+}
+
+impl GdbServer {
+    pub fn new(mut debugger: Debugger) -> Self {
+        debugger.run_limit = INTERRUPT_POLL_CYCLES;
+        Self { debugger, no_ack: false }
+    }
+
+    // Waits for a client and serves it until it detaches or hangs up
+    pub fn listen(&mut self, address: impl ToSocketAddrs) -> io::Result<()> {
+        let listener = TcpListener::bind(address)?;
+        let (stream, _) = listener.accept()?;
+        self.serve(stream)
+    }
+
+    pub fn serve(&mut self, stream: TcpStream) -> io::Result<()> {
+        stream.set_nodelay(true)?;
+        let mut connection = Connection { stream, pending: Vec::new() };
+        self.no_ack = false;
+
+        loop {
+            let packet = match connection.read_packet(self.no_ack)? {
+                Some(Incoming::Packet(packet)) => packet,
+                Some(Incoming::Interrupt) => {
+                    connection.send(&signal(SIGINT))?;
+                    continue;
+                }
+                None => return Ok(()),
+            };
+
+            match self.handle(&packet, &mut connection)? {
+                Some(reply) => connection.send(&reply)?,
+                None => return Ok(()),
+            }
+        }
+    }
+
+    // The reply for one packet, None once the client is done with us
+    fn handle(&mut self, packet: &str, connection: &mut Connection) -> io::Result<Option<String>> {
+        // By char rather than byte, anything that wasn't UTF-8 came through as U+FFFD
+        let (command, arguments) = packet.split_at(packet.chars().next().map_or(0, char::len_utf8));
+
+        let reply = match command {
+            "?" => signal(SIGTRAP),
+            "g" => self.registers(),
+            "G" => self.write_registers(arguments),
+            "p" => match usize::from_str_radix(arguments, 16).ok().and_then(|register| self.register(register)) {
+                Some(value) => value,
+                None => error(1),
+            },
+            "P" => self.write_register(arguments),
+            "m" => self.read_memory(arguments),
+            "M" => self.write_memory(arguments),
+            "c" => {
+                self.jump(arguments);
+                self.resume(connection)?
+            }
+            "s" => {
+                self.jump(arguments);
+                let reason = self.debugger.step_instruction();
+                self.stop_reply(reason)
+            }
+            "Z" => self.set_point(arguments, true),
+            "z" => self.set_point(arguments, false),
+            "q" => self.query(arguments),
+            "Q" if arguments == "StartNoAckMode" => {
+                // This reply still gets acknowledged, only later packets don't
+                self.no_ack = true;
+                "OK".to_string()
+            }
+            "H" => "OK".to_string(),
+            "D" => {
+                connection.send("OK")?;
+                return Ok(None);
+            }
+            "k" => return Ok(None),
+            _ => String::new(),
+        };
+        Ok(Some(reply))
+    }
+
+    fn query(&mut self, query: &str) -> String {
+        if query.starts_with("Supported") {
+            return format!("PacketSize={:x};qXfer:features:read+;QStartNoAckMode+", PACKET_SIZE);
+        }
+        if let Some(range) = query.strip_prefix("Xfer:features:read:target.xml:") {
+            return match parse_pair(range, ',') {
+                Some((offset, length)) => {
+                    let (offset, length) = (offset as usize, length as usize);
+                    let chunk = TARGET_XML.get(offset..TARGET_XML.len().min(offset + length)).unwrap_or("");
+                    match offset + chunk.len() >= TARGET_XML.len() {
+                        true => format!("l{}", chunk),
+                        false => format!("m{}", chunk),
+                    }
+                }
+                None => error(1),
+            };
+        }
+
+        match query {
+            "Attached" => "1",
+            "C" => "QC1",
+            "fThreadInfo" => "m1",
+            "sThreadInfo" => "l",
+            _ => "",
+        }
+        .to_string()
+    }
+
+    fn register(&self, register: usize) -> Option<String> {
+        let cpu = &self.debugger.system.cpu;
+        Some(match register {
+            0 => hex(&[cpu.a]),
+            1 => hex(&[cpu.x]),
+            2 => hex(&[cpu.y]),
+            3 => hex(&[cpu.p.bits()]),
+            4 => hex(&[cpu.s]),
+            5 => hex(&cpu.pc.to_le_bytes()),
+            _ => return None,
+        })
+    }
+
+    fn registers(&self) -> String {
+        (0..6).filter_map(|register| self.register(register)).collect()
+    }
+
+    fn set_register(&mut self, register: usize, bytes: &[u8]) -> bool {
+        let cpu = &mut self.debugger.system.cpu;
+        match (register, bytes) {
+            (0, [value]) => cpu.a = *value,
+            (1, [value]) => cpu.x = *value,
+            (2, [value]) => cpu.y = *value,
+            (3, [value]) => cpu.p = crate::cpu::Status::from_bits_truncate(*value),
+            (4, [value]) => cpu.s = *value,
+            (5, [low, high]) => cpu.pc = u16::from_le_bytes([*low, *high]),
+            _ => return false,
+        }
+        true
+    }
+
+    fn write_register(&mut self, arguments: &str) -> String {
+        let parsed = arguments
+            .split_once('=')
+            .and_then(|(register, value)| Some((usize::from_str_radix(register, 16).ok()?, unhex(value)?)));
+        match parsed {
+            Some((register, bytes)) if self.set_register(register, &bytes) => "OK".to_string(),
+            _ => error(1),
+        }
+    }
+
+    fn write_registers(&mut self, arguments: &str) -> String {
+        match unhex(arguments) {
+            Some(bytes) if bytes.len() == 7 => {
+                for (register, value) in bytes[..5].chunks(1).enumerate() {
+                    self.set_register(register, value);
+                }
+                self.set_register(5, &bytes[5..]);
+                "OK".to_string()
+            }
+            _ => error(1),
+        }
+    }
+
+    fn read_memory(&self, arguments: &str) -> String {
+        let Some((address, length)) = parse_pair(arguments, ',') else {
+            return error(1);
+        };
+        // Two hex digits a byte, and no more than fits in a packet
+        let length = length.min(PACKET_SIZE as u32 / 2);
+        let mapper = self.debugger.system.cpu.mapper.as_ref();
+        let bytes: Vec<u8> = (0..length).map(|offset| mapper.peek(address.wrapping_add(offset) as u16)).collect();
+        hex(&bytes)
+    }
+
+    fn write_memory(&mut self, arguments: &str) -> String {
+        let Some((range, data)) = arguments.split_once(':') else {
+            return error(1);
+        };
+        match (parse_pair(range, ','), unhex(data)) {
+            (Some((address, length)), Some(bytes)) if bytes.len() == length as usize => {
+                for (offset, byte) in bytes.into_iter().enumerate() {
+                    self.debugger.system.cpu.mapper.poke(address.wrapping_add(offset as u32) as u16, byte);
+                }
+                "OK".to_string()
+            }
+            _ => error(1),
+        }
+    }
+
+    // `c addr` and `s addr` resume somewhere else
+    fn jump(&mut self, arguments: &str) {
+        if let Ok(address) = u16::from_str_radix(arguments, 16) {
+            self.debugger.system.cpu.pc = address;
+        }
+    }
+
+    fn resume(&mut self, connection: &mut Connection) -> io::Result<String> {
+        loop {
+            match self.debugger.resume() {
+                StopReason::Limit => {
+                    if connection.interrupted()? {
+                        return Ok(signal(SIGINT));
+                    }
+                }
+                reason => return Ok(self.stop_reply(reason)),
+            }
+        }
+    }
+
+    fn stop_reply(&self, reason: StopReason) -> String {
+        match reason {
+            StopReason::Watchpoint(index, AddressSpace::Cpu, access) => {
+                let watchpoint = &self.debugger.watchpoints[index];
+                let kind = match (watchpoint.read, watchpoint.write) {
+                    (true, true) => "awatch",
+                    (true, false) => "rwatch",
+                    _ => "watch",
+                };
+                format!("T{:02x}{}:{:04x};", SIGTRAP, kind, access.address)
+            }
+            StopReason::Jammed => signal(SIGILL),
+            _ => signal(SIGTRAP),
+        }
+    }
+
+    // Z0/Z1 breakpoints, Z2 write, Z3 read and Z4 access watchpoints
+    fn set_point(&mut self, arguments: &str, insert: bool) -> String {
+        let mut fields = arguments.split(',');
+        let (Some(kind), Some(address), Some(length)) = (fields.next(), fields.next(), fields.next()) else {
+            return error(1);
+        };
+        let (Ok(address), Ok(length)) = (u16::from_str_radix(address, 16), u16::from_str_radix(length, 16)) else {
+            return error(1);
+        };
+
+        let (read, write) = match kind {
+            "0" | "1" => {
+                let existing = self.debugger.breakpoints.iter().position(|breakpoint| breakpoint.address == address);
+                match (insert, existing) {
+                    (true, None) => {
+                        self.debugger.add_breakpoint(address, None, None);
+                    }
+                    (false, Some(index)) => {
+                        self.debugger.breakpoints.remove(index);
+                    }
+                    _ => {}
+                }
+                return "OK".to_string();
+            }
+            "2" => (false, true),
+            "3" => (true, false),
+            "4" => (true, true),
+            _ => return String::new(),
+        };
+
+        let range = address..=address.wrapping_add(length.max(1) - 1);
+        match insert {
+            true => {
+                self.debugger.add_watchpoint(AddressSpace::Cpu, range, read, write, None);
+            }
+            false => self.debugger.watchpoints.retain(|watchpoint| {
+                watchpoint.space != AddressSpace::Cpu || watchpoint.range != range || (watchpoint.read, watchpoint.write) != (read, write)
+            }),
+        }
+        "OK".to_string()
+    }
+}
+
+enum Incoming {
+    Packet(String),
+    Interrupt,
+}
+
+struct Connection {
+    stream: TcpStream,
+    pending: Vec<u8>,
+}
+
+impl Connection {
+    // The next packet with its framing and checksum checked, None when the client hangs up
+    fn read_packet(&mut self, no_ack: bool) -> io::Result<Option<Incoming>> {
+        loop {
+            // Acks from the client and anything before a packet start don't matter
+            while let Some(&byte) = self.pending.first() {
+                match byte {
+                    0x03 => {
+                        self.pending.remove(0);
+                        return Ok(Some(Incoming::Interrupt));
+                    }
+                    b'$' => break,
+                    _ => {
+                        self.pending.remove(0);
+                    }
+                }
+            }
+
+            if let Some(end) = self.pending.iter().position(|byte| *byte == b'#') {
+                if self.pending.len() >= end + 3 {
+                    let frame: Vec<u8> = self.pending.drain(..end + 3).collect();
+                    let data = &frame[1..end];
+                    let checksum = std::str::from_utf8(&frame[end + 1..]).ok().and_then(|text| u8::from_str_radix(text, 16).ok());
+
+                    if checksum != Some(checksum_of(data)) {
+                        if !no_ack {
+                            self.stream.write_all(b"-")?;
+                        }
+                        continue;
+                    }
+                    if !no_ack {
+                        self.stream.write_all(b"+")?;
+                    }
+                    return Ok(Some(Incoming::Packet(String::from_utf8_lossy(data).into_owned())));
+                }
+            }
+
+            let mut buffer = [0; 1024];
+            match self.stream.read(&mut buffer)? {
+                0 => return Ok(None),
+                count => self.pending.extend_from_slice(&buffer[..count]),
+            }
+        }
+    }
+
+    fn send(&mut self, data: &str) -> io::Result<()> {
+        self.stream.write_all(format!("${}#{:02x}", data, checksum_of(data.as_bytes())).as_bytes())
+    }
+
+    // Whether a ^C has come in while running, without blocking
+    fn interrupted(&mut self) -> io::Result<bool> {
+        self.stream.set_nonblocking(true)?;
+        let mut buffer = [0; 64];
+        let result = self.stream.read(&mut buffer);
+        self.stream.set_nonblocking(false)?;
+
+        match result {
+            Ok(0) => Err(io::Error::new(io::ErrorKind::ConnectionAborted, "GDB client hung up while running")),
+            Ok(count) => {
+                self.pending.extend_from_slice(&buffer[..count]);
+                let interrupt = self.pending.iter().position(|byte| *byte == 0x03);
+                Ok(interrupt.map(|index| self.pending.remove(index)).is_some())
+            }
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+fn checksum_of(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte))
+}
+
+fn signal(number: u8) -> String {
+    format!("S{:02x}", number)
+}
+
+fn error(number: u8) -> String {
+    format!("E{:02x}", number)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    let mut text = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        let _ = write!(text, "{:02x}", byte);
+    }
+    text
+}
+
+fn unhex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len()).step_by(2).map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok()).collect()
+}
+
+// "addr,length" style pairs
+fn parse_pair(text: &str, separator: char) -> Option<(u32, u32)> {
+    let (first, second) = text.split_once(separator)?;
+    Some((u32::from_str_radix(first, 16).ok()?, u32::from_str_radix(second, 16).ok()?))
+}
@@ -0,0 +1,281 @@
+mod labels;
+
+use std::fmt;
+
+use crate::roms::Mapper;
+
+pub use self::labels::Labels;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressingMode {
+    Implied,
+    Accumulator,
+    Immediate,
+    ZeroPage,
+    ZeroPageX,
+    ZeroPageY,
+    Absolute,
+    AbsoluteX,
+    AbsoluteY,
+    Indirect,
+    IndexedIndirectX,
+    IndirectIndexedY,
+    Relative,
+}
+
+impl AddressingMode {
+    // Opcode plus operand bytes
+    pub fn length(&self) -> u8 {
+        match self {
+            AddressingMode::Implied | AddressingMode::Accumulator => 1,
+            AddressingMode::Absolute | AddressingMode::AbsoluteX | AddressingMode::AbsoluteY | AddressingMode::Indirect => 3,
+            _ => 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpcodeInfo {
+    pub mnemonic: &'static str,
+    pub mode: AddressingMode,
+    // Without the extra cycle for a page cross or a taken branch
+    pub cycles: u8,
+    pub page_cross_penalty: bool,
+}
+
+impl OpcodeInfo {
+    pub fn illegal(&self, opcode: u8) -> bool {
+        match self.mnemonic {
+            "NOP" => opcode != 0xea,
+            "SBC" => opcode == 0xeb,
+            mnemonic => ILLEGAL_MNEMONICS.contains(&mnemonic),
+        }
+    }
+}
+
+const ILLEGAL_MNEMONICS: [&str; 19] = [
+    "STP", "SLO", "RLA", "SRE", "RRA", "SAX", "LAX", "DCP", "ISC", "ANC", "ALR", "ARR", "XAA", "AXS", "AHX", "TAS", "SHY", "SHX", "LAS",
+];
+
+const IMP: AddressingMode = AddressingMode::Implied;
+const ACC: AddressingMode = AddressingMode::Accumulator;
+const IMM: AddressingMode = AddressingMode::Immediate;
+const ZP: AddressingMode = AddressingMode::ZeroPage;
+const ZPX: AddressingMode = AddressingMode::ZeroPageX;
+const ZPY: AddressingMode = AddressingMode::ZeroPageY;
+const ABS: AddressingMode = AddressingMode::Absolute;
+const ABX: AddressingMode = AddressingMode::AbsoluteX;
+const ABY: AddressingMode = AddressingMode::AbsoluteY;
+const IND: AddressingMode = AddressingMode::Indirect;
+const IZX: AddressingMode = AddressingMode::IndexedIndirectX;
+const IZY: AddressingMode = AddressingMode::IndirectIndexedY;
+const REL: AddressingMode = AddressingMode::Relative;
+
+const fn op(mnemonic: &'static str, mode: AddressingMode, cycles: u8, page_cross_penalty: bool) -> OpcodeInfo {
+    OpcodeInfo { mnemonic, mode, cycles, page_cross_penalty }
+}
+
+pub const OPCODES: [OpcodeInfo; 256] = [
+    // $00
+    op("BRK", IMP, 7, false), op("ORA", IZX, 6, false), op("STP", IMP, 2, false), op("SLO", IZX, 8, false),
+    op("NOP", ZP, 3, false), op("ORA", ZP, 3, false), op("ASL", ZP, 5, false), op("SLO", ZP, 5, false),
+    op("PHP", IMP, 3, false), op("ORA", IMM, 2, false), op("ASL", ACC, 2, false), op("ANC", IMM, 2, false),
+    op("NOP", ABS, 4, false), op("ORA", ABS, 4, false), op("ASL", ABS, 6, false), op("SLO", ABS, 6, false),
+    // $10
+    op("BPL", REL, 2, false), op("ORA", IZY, 5, true), op("STP", IMP, 2, false), op("SLO", IZY, 8, false),
+    op("NOP", ZPX, 4, false), op("ORA", ZPX, 4, false), op("ASL", ZPX, 6, false), op("SLO", ZPX, 6, false),
+    op("CLC", IMP, 2, false), op("ORA", ABY, 4, true), op("NOP", IMP, 2, false), op("SLO", ABY, 7, false),
+    op("NOP", ABX, 4, true), op("ORA", ABX, 4, true), op("ASL", ABX, 7, false), op("SLO", ABX, 7, false),
+    // $20
+    op("JSR", ABS, 6, false), op("AND", IZX, 6, false), op("STP", IMP, 2, false), op("RLA", IZX, 8, false),
+    op("BIT", ZP, 3, false), op("AND", ZP, 3, false), op("ROL", ZP, 5, false), op("RLA", ZP, 5, false),
+    op("PLP", IMP, 4, false), op("AND", IMM, 2, false), op("ROL", ACC, 2, false), op("ANC", IMM, 2, false),
+    op("BIT", ABS, 4, false), op("AND", ABS, 4, false), op("ROL", ABS, 6, false), op("RLA", ABS, 6, false),
+    // $30
+    op("BMI", REL, 2, false), op("AND", IZY, 5, true), op("STP", IMP, 2, false), op("RLA", IZY, 8, false),
+    op("NOP", ZPX, 4, false), op("AND", ZPX, 4, false), op("ROL", ZPX, 6, false), op("RLA", ZPX, 6, false),
+    op("SEC", IMP, 2, false), op("AND", ABY, 4, true), op("NOP", IMP, 2, false), op("RLA", ABY, 7, false),
+    op("NOP", ABX, 4, true), op("AND", ABX, 4, true), op("ROL", ABX, 7, false), op("RLA", ABX, 7, false),
+    // $40
+    op("RTI", IMP, 6, false), op("EOR", IZX, 6, false), op("STP", IMP, 2, false), op("SRE", IZX, 8, false),
+    op("NOP", ZP, 3, false), op("EOR", ZP, 3, false), op("LSR", ZP, 5, false), op("SRE", ZP, 5, false),
+    op("PHA", IMP, 3, false), op("EOR", IMM, 2, false), op("LSR", ACC, 2, false), op("ALR", IMM, 2, false),
+    op("JMP", ABS, 3, false), op("EOR", ABS, 4, false), op("LSR", ABS, 6, false), op("SRE", ABS, 6, false),
+    // $50
+    op("BVC", REL, 2, false), op("EOR", IZY, 5, true), op("STP", IMP, 2, false), op("SRE", IZY, 8, false),
+    op("NOP", ZPX, 4, false), op("EOR", ZPX, 4, false), op("LSR", ZPX, 6, false), op("SRE", ZPX, 6, false),
+    op("CLI", IMP, 2, false), op("EOR", ABY, 4, true), op("NOP", IMP, 2, false), op("SRE", ABY, 7, false),
+    op("NOP", ABX, 4, true), op("EOR", ABX, 4, true), op("LSR", ABX, 7, false), op("SRE", ABX, 7, false),
+    // $60
+    op("RTS", IMP, 6, false), op("ADC", IZX, 6, false), op("STP", IMP, 2, false), op("RRA", IZX, 8, false),
+    op("NOP", ZP, 3, false), op("ADC", ZP, 3, false), op("ROR", ZP, 5, false), op("RRA", ZP, 5, false),
+    op("PLA", IMP, 4, false), op("ADC", IMM, 2, false), op("ROR", ACC, 2, false), op("ARR", IMM, 2, false),
+    op("JMP", IND, 5, false), op("ADC", ABS, 4, false), op("ROR", ABS, 6, false), op("RRA", ABS, 6, false),
+    // $70
+    op("BVS", REL, 2, false), op("ADC", IZY, 5, true), op("STP", IMP, 2, false), op("RRA", IZY, 8, false),
+    op("NOP", ZPX, 4, false), op("ADC", ZPX, 4, false), op("ROR", ZPX, 6, false), op("RRA", ZPX, 6, false),
+    op("SEI", IMP, 2, false), op("ADC", ABY, 4, true), op("NOP", IMP, 2, false), op("RRA", ABY, 7, false),
+    op("NOP", ABX, 4, true), op("ADC", ABX, 4, true), op("ROR", ABX, 7, false), op("RRA", ABX, 7, false),
+    // $80
+    op("NOP", IMM, 2, false), op("STA", IZX, 6, false), op("NOP", IMM, 2, false), op("SAX", IZX, 6, false),
+    op("STY", ZP, 3, false), op("STA", ZP, 3, false), op("STX", ZP, 3, false), op("SAX", ZP, 3, false),
+    op("DEY", IMP, 2, false), op("NOP", IMM, 2, false), op("TXA", IMP, 2, false), op("XAA", IMM, 2, false),
+    op("STY", ABS, 4, false), op("STA", ABS, 4, false), op("STX", ABS, 4, false), op("SAX", ABS, 4, false),
+    // $90
+    op("BCC", REL, 2, false), op("STA", IZY, 6, false), op("STP", IMP, 2, false), op("AHX", IZY, 6, false),
+    op("STY", ZPX, 4, false), op("STA", ZPX, 4, false), op("STX", ZPY, 4, false), op("SAX", ZPY, 4, false),
+    op("TYA", IMP, 2, false), op("STA", ABY, 5, false), op("TXS", IMP, 2, false), op("TAS", ABY, 5, false),
+    op("SHY", ABX, 5, false), op("STA", ABX, 5, false), op("SHX", ABY, 5, false), op("AHX", ABY, 5, false),
+    // $A0
+    op("LDY", IMM, 2, false), op("LDA", IZX, 6, false), op("LDX", IMM, 2, false), op("LAX", IZX, 6, false),
+    op("LDY", ZP, 3, false), op("LDA", ZP, 3, false), op("LDX", ZP, 3, false), op("LAX", ZP, 3, false),
+    op("TAY", IMP, 2, false), op("LDA", IMM, 2, false), op("TAX", IMP, 2, false), op("LAX", IMM, 2, false),
+    op("LDY", ABS, 4, false), op("LDA", ABS, 4, false), op("LDX", ABS, 4, false), op("LAX", ABS, 4, false),
+    // $B0
+    op("BCS", REL, 2, false), op("LDA", IZY, 5, true), op("STP", IMP, 2, false), op("LAX", IZY, 5, true),
+    op("LDY", ZPX, 4, false), op("LDA", ZPX, 4, false), op("LDX", ZPY, 4, false), op("LAX", ZPY, 4, false),
+    op("CLV", IMP, 2, false), op("LDA", ABY, 4, true), op("TSX", IMP, 2, false), op("LAS", ABY, 4, true),
+    op("LDY", ABX, 4, true), op("LDA", ABX, 4, true), op("LDX", ABY, 4, true), op("LAX", ABY, 4, true),
+    // $C0
+    op("CPY", IMM, 2, false), op("CMP", IZX, 6, false), op("NOP", IMM, 2, false), op("DCP", IZX, 8, false),
+    op("CPY", ZP, 3, false), op("CMP", ZP, 3, false), op("DEC", ZP, 5, false), op("DCP", ZP, 5, false),
+    op("INY", IMP, 2, false), op("CMP", IMM, 2, false), op("DEX", IMP, 2, false), op("AXS", IMM, 2, false),
+    op("CPY", ABS, 4, false), op("CMP", ABS, 4, false), op("DEC", ABS, 6, false), op("DCP", ABS, 6, false),
+    // $D0
+    op("BNE", REL, 2, false), op("CMP", IZY, 5, true), op("STP", IMP, 2, false), op("DCP", IZY, 8, false),
+    op("NOP", ZPX, 4, false), op("CMP", ZPX, 4, false), op("DEC", ZPX, 6, false), op("DCP", ZPX, 6, false),
+    op("CLD", IMP, 2, false), op("CMP", ABY, 4, true), op("NOP", IMP, 2, false), op("DCP", ABY, 7, false),
+    op("NOP", ABX, 4, true), op("CMP", ABX, 4, true), op("DEC", ABX, 7, false), op("DCP", ABX, 7, false),
+    // $E0
+    op("CPX", IMM, 2, false), op("SBC", IZX, 6, false), op("NOP", IMM, 2, false), op("ISC", IZX, 8, false),
+    op("CPX", ZP, 3, false), op("SBC", ZP, 3, false), op("INC", ZP, 5, false), op("ISC", ZP, 5, false),
+    op("INX", IMP, 2, false), op("SBC", IMM, 2, false), op("NOP", IMP, 2, false), op("SBC", IMM, 2, false),
+    op("CPX", ABS, 4, false), op("SBC", ABS, 4, false), op("INC", ABS, 6, false), op("ISC", ABS, 6, false),
+    // $F0
+    op("BEQ", REL, 2, false), op("SBC", IZY, 5, true), op("STP", IMP, 2, false), op("ISC", IZY, 8, false),
+    op("NOP", ZPX, 4, false), op("SBC", ZPX, 4, false), op("INC", ZPX, 6, false), op("ISC", ZPX, 6, false),
+    op("SED", IMP, 2, false), op("SBC", ABY, 4, true), op("NOP", IMP, 2, false), op("ISC", ABY, 7, false),
+    op("NOP", ABX, 4, true), op("SBC", ABX, 4, true), op("INC", ABX, 7, false), op("ISC", ABX, 7, false),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instruction {
+    pub address: u16,
+    pub opcode: u8,
+    pub operands: Vec<u8>,
+    pub info: OpcodeInfo,
+}
+
+impl Instruction {
+    // `bytes` starts at the opcode, anything past the instruction's length is ignored and anything missing reads as 0
+    pub fn decode(address: u16, bytes: &[u8]) -> Self {
+        let opcode = bytes.first().copied().unwrap_or(0);
+        let info = OPCODES[opcode as usize];
+        let operands = (1..info.mode.length() as usize).map(|i| bytes.get(i).copied().unwrap_or(0)).collect();
+
+        Self { address, opcode, operands, info }
+    }
+
+    pub fn read(mapper: &dyn Mapper, address: u16) -> Self {
+        let bytes: Vec<u8> = (0..3).map(|i| mapper.peek(address.wrapping_add(i))).collect();
+        Self::decode(address, &bytes)
+    }
+
+    pub fn mnemonic(&self) -> &'static str {
+        self.info.mnemonic
+    }
+
+    pub fn mode(&self) -> AddressingMode {
+        self.info.mode
+    }
+
+    pub fn length(&self) -> u8 {
+        self.info.mode.length()
+    }
+
+    pub fn bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![self.opcode];
+        bytes.extend(&self.operands);
+        bytes
+    }
+
+    pub fn illegal(&self) -> bool {
+        self.info.illegal(self.opcode)
+    }
+
+    // Base cycles and the most the instruction can take with page crossings and taken branches
+    pub fn cycles(&self) -> (u8, u8) {
+        let extra = match (self.info.mode, self.info.page_cross_penalty) {
+            (AddressingMode::Relative, _) => 2,
+            (_, true) => 1,
+            _ => 0,
+        };
+        (self.info.cycles, self.info.cycles + extra)
+    }
+
+    pub fn next_address(&self) -> u16 {
+        self.address.wrapping_add(self.length() as u16)
+    }
+
+    // Where a branch, JMP or JSR goes, when that's known without running it
+    pub fn target(&self) -> Option<u16> {
+        match (self.info.mode, self.opcode) {
+            (AddressingMode::Relative, _) => Some(self.next_address().wrapping_add_signed(self.operands[0] as i8 as i16)),
+            (AddressingMode::Absolute, 0x4c | 0x20) => self.operand_address(),
+            _ => None,
+        }
+    }
+
+    // The address written in the operand, for modes that have one
+    pub fn operand_address(&self) -> Option<u16> {
+        match self.info.mode {
+            AddressingMode::Implied | AddressingMode::Accumulator | AddressingMode::Immediate => None,
+            AddressingMode::Relative => self.target(),
+            _ if self.operands.len() == 2 => Some(u16::from_le_bytes([self.operands[0], self.operands[1]])),
+            _ => Some(self.operands[0] as u16),
+        }
+    }
+
+    pub fn text(&self) -> String {
+        self.text_with(|_| None)
+    }
+
+    // Disassembly with operand addresses replaced by labels where there are any
+    pub fn annotate(&self, labels: &Labels, mapper: &dyn Mapper) -> String {
+        self.text_with(|address| labels.lookup(address, mapper).map(str::to_string))
+    }
+
+    fn text_with(&self, label: impl Fn(u16) -> Option<String>) -> String {
+        let address = self.operand_address();
+        let operand = match (address.and_then(&label), address) {
+            (Some(name), _) => name,
+            (None, Some(address)) if self.operands.len() == 2 || self.info.mode == AddressingMode::Relative => format!("${:04X}", address),
+            (None, Some(address)) => format!("${:02X}", address),
+            (None, None) => String::new(),
+        };
+
+        let operand = match self.info.mode {
+            AddressingMode::Implied => String::new(),
+            AddressingMode::Accumulator => "A".to_string(),
+            AddressingMode::Immediate => format!("#${:02X}", self.operands[0]),
+            AddressingMode::ZeroPage | AddressingMode::Absolute | AddressingMode::Relative => operand,
+            AddressingMode::ZeroPageX | AddressingMode::AbsoluteX => format!("{},X", operand),
+            AddressingMode::ZeroPageY | AddressingMode::AbsoluteY => format!("{},Y", operand),
+            AddressingMode::Indirect => format!("({})", operand),
+            AddressingMode::IndexedIndirectX => format!("({},X)", operand),
+            AddressingMode::IndirectIndexedY => format!("({}),Y", operand),
+        };
+
+        match operand.is_empty() {
+            true => self.info.mnemonic.to_string(),
+            false => format!("{} {}", self.info.mnemonic, operand),
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bytes: Vec<String> = self.bytes().iter().map(|byte| format!("{:02X}", byte)).collect();
+        write!(f, "{:04X}  {:8}  {}", self.address, bytes.join(" "), self.text())
+    }
+}
@@ -0,0 +1,161 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::roms::Mapper;
+
+const FCEUX_BANK_SIZE: usize = 0x4000;
+
+// Names for addresses, either fixed CPU addresses (RAM, registers) or PRG-ROM offsets that only apply while their bank
+// is mapped in
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Labels {
+    cpu: HashMap<u16, String>,
+    program_rom: HashMap<usize, String>,
+}
+
+impl Labels {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, address: u16, name: &str) {
+        self.cpu.insert(address, name.to_string());
+    }
+
+    pub fn insert_program_rom(&mut self, offset: usize, name: &str) {
+        self.program_rom.insert(offset, name.to_string());
+    }
+
+    pub fn merge(&mut self, other: Labels) {
+        self.cpu.extend(other.cpu);
+        self.program_rom.extend(other.program_rom);
+    }
+
+    pub fn len(&self) -> usize {
+        self.cpu.len() + self.program_rom.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn lookup(&self, address: u16, mapper: &dyn Mapper) -> Option<&str> {
+        mapper
+            .program_rom_offset(address)
+            .and_then(|offset| self.program_rom.get(&offset))
+            .or_else(|| self.cpu.get(&address))
+            .map(String::as_str)
+    }
+
+    // Reverse lookup for typing labels into the debugger, CPU addresses only
+    pub fn address_of(&self, name: &str) -> Option<u16> {
+        self.cpu.iter().find(|(_, label)| label.as_str() == name).map(|(address, _)| *address)
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        let file_name = path.file_name().and_then(|name| name.to_str()).unwrap_or("");
+
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("dbg") => Self::parse_ca65_dbg(&text),
+            Some("mlb") => Self::parse_mesen_mlb(&text),
+            Some("nl") => Self::parse_fceux_nl(&text, fceux_bank(file_name)),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidData, "Unknown symbol file type")),
+        }
+    }
+
+    // ld65 --dbgfile output, only the symbols with a value are of any use here
+    pub fn parse_ca65_dbg(text: &str) -> io::Result<Self> {
+        let mut labels = Self::new();
+
+        for line in text.lines() {
+            let Some(fields) = line.strip_prefix("sym\t") else {
+                continue;
+            };
+
+            let mut name = None;
+            let mut value = None;
+            for field in fields.split(',') {
+                match field.split_once('=') {
+                    Some(("name", quoted)) => name = Some(quoted.trim_matches('"')),
+                    Some(("val", number)) => value = parse_hex(number.trim_start_matches("0x")),
+                    _ => {}
+                }
+            }
+
+            if let (Some(name), Some(value)) = (name, value) {
+                if value <= 0xffff {
+                    labels.insert(value as u16, name);
+                }
+            }
+        }
+
+        Ok(labels)
+    }
+
+    // FCEUX keeps one .nl file per 16K PRG bank (game.nes.0.nl, ...) plus game.nes.ram.nl, lines like
+    // "$C000#Reset#comment". `bank` is None for the RAM file.
+    pub fn parse_fceux_nl(text: &str, bank: Option<usize>) -> io::Result<Self> {
+        let mut labels = Self::new();
+
+        for line in text.lines() {
+            let Some(rest) = line.strip_prefix('$') else {
+                continue;
+            };
+
+            let mut fields = rest.split('#');
+            let address = fields.next().and_then(parse_hex).filter(|address| *address <= 0xffff);
+            let name = fields.next().filter(|name| !name.is_empty());
+            if let (Some(address), Some(name)) = (address, name) {
+                match bank {
+                    Some(bank) if address >= 0x8000 => {
+                        labels.insert_program_rom(bank * FCEUX_BANK_SIZE + (address as usize & (FCEUX_BANK_SIZE - 1)), name)
+                    }
+                    _ => labels.insert(address as u16, name),
+                }
+            }
+        }
+
+        Ok(labels)
+    }
+
+    // Mesen label export, "P:1A2B:name:comment" for PRG-ROM offsets, "R:" internal RAM, "S:"/"W:" cartridge RAM and
+    // "G:" registers. Mesen 2 spells the types out (NesPrgRom, NesInternalRam, ...).
+    pub fn parse_mesen_mlb(text: &str) -> io::Result<Self> {
+        let mut labels = Self::new();
+
+        for line in text.lines() {
+            let mut fields = line.trim().splitn(4, ':');
+            let (Some(kind), Some(range), Some(name)) = (fields.next(), fields.next(), fields.next()) else {
+                continue;
+            };
+            let Some(start) = range.split('-').next().and_then(parse_hex) else {
+                continue;
+            };
+            if name.is_empty() {
+                continue;
+            }
+
+            match kind {
+                "P" | "NesPrgRom" => labels.insert_program_rom(start as usize, name),
+                "R" | "NesInternalRam" => labels.insert(start as u16 & 0x7ff, name),
+                "S" | "W" | "NesSaveRam" | "NesWorkRam" => labels.insert(0x6000 + (start as u16 & 0x1fff), name),
+                "G" | "NesMemory" if start <= 0xffff => labels.insert(start as u16, name),
+                _ => {}
+            }
+        }
+
+        Ok(labels)
+    }
+}
+
+fn parse_hex(text: &str) -> Option<u32> {
+    u32::from_str_radix(text.trim(), 16).ok()
+}
+
+// game.nes.3.nl -> Some(3), game.nes.ram.nl -> None
+fn fceux_bank(file_name: &str) -> Option<usize> {
+    file_name.strip_suffix(".nl")?.rsplit('.').next()?.parse().ok()
+}
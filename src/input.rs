@@ -0,0 +1,316 @@
+mod four_score;
+mod keyboard;
+mod power_pad;
+mod vaus;
+mod zapper;
+
+use std::any::Any;
+use std::io::{self, Read, Write};
+
+use bitflags::bitflags;
+use byteorder::{ReadBytesExt, WriteBytesExt};
+
+use crate::ppu::PPU;
+use crate::roms::RomImageHeader;
+use crate::state::SaveState;
+
+pub use self::{
+    four_score::{FamicomFourPlayer, FourScore},
+    keyboard::FamilyKeyboard,
+    power_pad::PowerPad,
+    vaus::ArkanoidVaus,
+    zapper::Zapper,
+};
+
+// Upper bits of $4016/$4017 are open bus, which is nearly always the $40 left over from the address
+pub const OPEN_BUS: u8 = 0x40;
+
+// Something plugged into one of the two front controller ports
+pub trait InputDevice: Any {
+    // Data lines D0-D4 as seen by the CPU when it reads this port
+    fn read(&mut self, ppu: &PPU) -> u8;
+    // Every $4016 write, bit 0 of which is the shared strobe line for both ports
+    fn strobe(&mut self, data: u8);
+
+    // Latches and shift registers, not what the player is holding. Devices without any can leave these out.
+    fn save_state(&self, _writer: &mut dyn Write) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn load_state(&mut self, _reader: &mut dyn Read) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// Something plugged into the Famicom expansion port, which sees all of OUT0-2 and answers on both $4016 and $4017
+pub trait ExpansionDevice: Any {
+    fn read(&mut self, address: u16, ppu: &PPU) -> u8;
+    fn write(&mut self, data: u8);
+
+    fn save_state(&self, _writer: &mut dyn Write) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn load_state(&mut self, _reader: &mut dyn Read) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Port {
+    One,
+    Two,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PortDeviceKind {
+    Unplugged,
+    StandardController,
+    Zapper,
+    FourScore,
+    ArkanoidVaus,
+    PowerPad,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExpansionDeviceKind {
+    Unplugged,
+    FamicomFourPlayer,
+    ArkanoidVaus,
+    FamilyKeyboard,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InputLayout {
+    pub port1: PortDeviceKind,
+    pub port2: PortDeviceKind,
+    pub expansion: ExpansionDeviceKind,
+}
+
+impl InputLayout {
+    pub fn from_header(header: &RomImageHeader) -> Self {
+        header.default_expansion_device
+            .map(Self::from_expansion_device)
+            .unwrap_or_default()
+    }
+
+    // NES 2.0 header byte 15
+    pub fn from_expansion_device(device: u8) -> Self {
+        let standard = Self::default();
+        match device {
+            0x02 => Self { port1: PortDeviceKind::FourScore, port2: PortDeviceKind::FourScore, ..standard },
+            0x03 => Self { expansion: ExpansionDeviceKind::FamicomFourPlayer, ..standard },
+            0x08 => Self { port2: PortDeviceKind::Zapper, ..standard },
+            0x09 => Self { port1: PortDeviceKind::Zapper, port2: PortDeviceKind::Zapper, ..standard },
+            0x0b | 0x0c => Self { port2: PortDeviceKind::PowerPad, ..standard },
+            0x0f => Self { port2: PortDeviceKind::ArkanoidVaus, ..standard },
+            0x10 => Self { expansion: ExpansionDeviceKind::ArkanoidVaus, ..standard },
+            0x23 => Self { expansion: ExpansionDeviceKind::FamilyKeyboard, ..standard },
+            _ => standard,
+        }
+    }
+}
+
+impl Default for InputLayout {
+    fn default() -> Self {
+        Self {
+            port1: PortDeviceKind::StandardController,
+            port2: PortDeviceKind::StandardController,
+            expansion: ExpansionDeviceKind::Unplugged,
+        }
+    }
+}
+
+pub struct ControllerPorts {
+    pub port1: Box<dyn InputDevice>,
+    pub port2: Box<dyn InputDevice>,
+    pub expansion: Box<dyn ExpansionDevice>,
+    // Set by any $4016/$4017 read, cleared by whoever's counting lag frames
+    pub polled: bool,
+}
+
+impl ControllerPorts {
+    pub fn new() -> Self {
+        Self::with_layout(InputLayout::default())
+    }
+
+    pub fn with_layout(layout: InputLayout) -> Self {
+        let mut ports = Self {
+            port1: Box::new(Unplugged),
+            port2: Box::new(Unplugged),
+            expansion: Box::new(Unplugged),
+            polled: false,
+        };
+        ports.apply(layout);
+        ports
+    }
+
+    pub fn apply(&mut self, layout: InputLayout) {
+        self.select(Port::One, layout.port1);
+        self.select(Port::Two, layout.port2);
+        self.select_expansion(layout.expansion);
+    }
+
+    pub fn select(&mut self, port: Port, kind: PortDeviceKind) {
+        let device: Box<dyn InputDevice> = match kind {
+            PortDeviceKind::Unplugged => Box::new(Unplugged),
+            PortDeviceKind::StandardController => Box::new(StandardController::new()),
+            PortDeviceKind::Zapper => Box::new(Zapper::new()),
+            PortDeviceKind::FourScore => Box::new(FourScore::new(port)),
+            PortDeviceKind::ArkanoidVaus => Box::new(ArkanoidVaus::new()),
+            PortDeviceKind::PowerPad => Box::new(PowerPad::new()),
+        };
+        self.attach(port, device);
+    }
+
+    pub fn select_expansion(&mut self, kind: ExpansionDeviceKind) {
+        self.expansion = match kind {
+            ExpansionDeviceKind::Unplugged => Box::new(Unplugged),
+            ExpansionDeviceKind::FamicomFourPlayer => Box::new(FamicomFourPlayer::new()),
+            ExpansionDeviceKind::ArkanoidVaus => Box::new(ArkanoidVaus::new()),
+            ExpansionDeviceKind::FamilyKeyboard => Box::new(FamilyKeyboard::new()),
+        };
+    }
+
+    pub fn attach(&mut self, port: Port, device: Box<dyn InputDevice>) {
+        *self.port_mut(port) = device;
+    }
+
+    pub fn device_mut<T: InputDevice>(&mut self, port: Port) -> Option<&mut T> {
+        let device: &mut dyn Any = self.port_mut(port).as_mut();
+        device.downcast_mut::<T>()
+    }
+
+    pub fn expansion_mut<T: ExpansionDevice>(&mut self) -> Option<&mut T> {
+        let device: &mut dyn Any = self.expansion.as_mut();
+        device.downcast_mut::<T>()
+    }
+
+    pub fn read(&mut self, address: u16, ppu: &PPU) -> u8 {
+        self.polled = true;
+        let data = match address {
+            0x4016 => self.port1.read(ppu),
+            _ => self.port2.read(ppu),
+        };
+
+        OPEN_BUS | ((data | self.expansion.read(address, ppu)) & 0x1f)
+    }
+
+    pub fn write(&mut self, data: u8) {
+        self.port1.strobe(data);
+        self.port2.strobe(data);
+        self.expansion.write(data);
+    }
+
+    fn port_mut(&mut self, port: Port) -> &mut Box<dyn InputDevice> {
+        match port {
+            Port::One => &mut self.port1,
+            Port::Two => &mut self.port2,
+        }
+    }
+}
+
+impl Default for ControllerPorts {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Expects the same devices plugged in as when it was saved
+impl SaveState for ControllerPorts {
+    fn save_state(&self, writer: &mut dyn Write) -> io::Result<()> {
+        self.port1.save_state(writer)?;
+        self.port2.save_state(writer)?;
+        self.expansion.save_state(writer)
+    }
+
+    fn load_state(&mut self, reader: &mut dyn Read) -> io::Result<()> {
+        self.port1.load_state(reader)?;
+        self.port2.load_state(reader)?;
+        self.expansion.load_state(reader)
+    }
+}
+
+pub struct Unplugged;
+
+impl InputDevice for Unplugged {
+    fn read(&mut self, _: &PPU) -> u8 {
+        0
+    }
+
+    fn strobe(&mut self, _: u8) {}
+}
+
+impl ExpansionDevice for Unplugged {
+    fn read(&mut self, _: u16, _: &PPU) -> u8 {
+        0
+    }
+
+    fn write(&mut self, _: u8) {}
+}
+
+bitflags! {
+    pub struct Buttons: u8 {
+        const A = 0b0000_0001;
+        const B = 0b0000_0010;
+        const SELECT = 0b0000_0100;
+        const START = 0b0000_1000;
+        const UP = 0b0001_0000;
+        const DOWN = 0b0010_0000;
+        const LEFT = 0b0100_0000;
+        const RIGHT = 0b1000_0000;
+    }
+}
+
+pub struct StandardController {
+    pub buttons: Buttons,
+    shift: u8,
+    strobe: bool,
+}
+
+impl StandardController {
+    pub fn new() -> Self {
+        Self {
+            buttons: Buttons::empty(),
+            shift: 0,
+            strobe: false,
+        }
+    }
+}
+
+impl Default for StandardController {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InputDevice for StandardController {
+    fn read(&mut self, _: &PPU) -> u8 {
+        if self.strobe {
+            self.shift = self.buttons.bits;
+        }
+
+        // Once all eight buttons are out an official controller keeps returning 1
+        let data = self.shift & 1;
+        self.shift = (self.shift >> 1) | 0x80;
+        data
+    }
+
+    fn strobe(&mut self, data: u8) {
+        self.strobe = data & 1 == 1;
+        if self.strobe {
+            self.shift = self.buttons.bits;
+        }
+    }
+
+    fn save_state(&self, writer: &mut dyn Write) -> io::Result<()> {
+        writer.write_u8(self.shift)?;
+        writer.write_u8(self.strobe as u8)
+    }
+
+    fn load_state(&mut self, reader: &mut dyn Read) -> io::Result<()> {
+        self.shift = reader.read_u8()?;
+        self.strobe = reader.read_u8()? != 0;
+        Ok(())
+    }
+}
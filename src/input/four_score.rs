@@ -0,0 +1,119 @@
+use std::io::{self, Read, Write};
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use crate::ppu::PPU;
+
+use super::{ExpansionDevice, InputDevice, Port, StandardController};
+
+// Reads 17-24 of each port identify the adapter, sent LSB first
+const PORT1_SIGNATURE: u32 = 0b0000_1000;
+const PORT2_SIGNATURE: u32 = 0b0000_0100;
+
+// NES Four Score. One of these goes in each port, carrying players 1 and 3 or players 2 and 4.
+pub struct FourScore {
+    pub controllers: [StandardController; 2],
+    signature: u32,
+    shift: u32,
+    strobe: bool,
+}
+
+impl FourScore {
+    pub fn new(port: Port) -> Self {
+        Self {
+            controllers: [StandardController::new(), StandardController::new()],
+            signature: match port {
+                Port::One => PORT1_SIGNATURE,
+                Port::Two => PORT2_SIGNATURE,
+            },
+            shift: 0,
+            strobe: false,
+        }
+    }
+
+    fn latch(&mut self) {
+        self.shift = self.controllers[0].buttons.bits as u32
+            | (self.controllers[1].buttons.bits as u32) << 8
+            | self.signature << 16;
+    }
+}
+
+impl InputDevice for FourScore {
+    fn read(&mut self, _: &PPU) -> u8 {
+        if self.strobe {
+            self.latch();
+        }
+
+        let data = (self.shift & 1) as u8;
+        self.shift = (self.shift >> 1) | 0x80_0000;
+        data
+    }
+
+    fn strobe(&mut self, data: u8) {
+        self.strobe = data & 1 == 1;
+        if self.strobe {
+            self.latch();
+        }
+    }
+
+    fn save_state(&self, writer: &mut dyn Write) -> io::Result<()> {
+        writer.write_u32::<LittleEndian>(self.shift)?;
+        writer.write_u8(self.strobe as u8)
+    }
+
+    fn load_state(&mut self, reader: &mut dyn Read) -> io::Result<()> {
+        self.shift = reader.read_u32::<LittleEndian>()?;
+        self.strobe = reader.read_u8()? != 0;
+        Ok(())
+    }
+}
+
+// Famicom four player adapter in its simple mode, players 3 and 4 show up on D1 of $4016 and $4017
+pub struct FamicomFourPlayer {
+    pub controllers: [StandardController; 2],
+}
+
+impl FamicomFourPlayer {
+    pub fn new() -> Self {
+        Self {
+            controllers: [StandardController::new(), StandardController::new()],
+        }
+    }
+}
+
+impl Default for FamicomFourPlayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ExpansionDevice for FamicomFourPlayer {
+    fn read(&mut self, address: u16, ppu: &PPU) -> u8 {
+        let controller = match address {
+            0x4016 => &mut self.controllers[0],
+            _ => &mut self.controllers[1],
+        };
+
+        (controller.read(ppu) & 1) << 1
+    }
+
+    fn write(&mut self, data: u8) {
+        for controller in self.controllers.iter_mut() {
+            controller.strobe(data);
+        }
+    }
+
+    fn save_state(&self, writer: &mut dyn Write) -> io::Result<()> {
+        for controller in self.controllers.iter() {
+            InputDevice::save_state(controller, writer)?;
+        }
+        Ok(())
+    }
+
+    fn load_state(&mut self, reader: &mut dyn Read) -> io::Result<()> {
+        for controller in self.controllers.iter_mut() {
+            InputDevice::load_state(controller, reader)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,87 @@
+use std::io::{self, Read, Write};
+
+use byteorder::{ReadBytesExt, WriteBytesExt};
+
+use crate::ppu::PPU;
+
+use super::ExpansionDevice;
+
+pub const KEYBOARD_ROWS: usize = 9;
+
+// Family BASIC keyboard. $4016 writes pick a row and a four key column, $4017 D1-D4 return the
+// selected keys with 0 meaning pressed.
+pub struct FamilyKeyboard {
+    // One byte per row, column 0 in the low nibble and column 1 in the high nibble, 1 = pressed
+    pub keys: [u8; KEYBOARD_ROWS],
+    row: usize,
+    column: u8,
+    enabled: bool,
+}
+
+impl FamilyKeyboard {
+    pub fn new() -> Self {
+        Self {
+            keys: [0; KEYBOARD_ROWS],
+            row: 0,
+            column: 0,
+            enabled: false,
+        }
+    }
+
+    pub fn set_key(&mut self, row: usize, key: u8, pressed: bool) {
+        if pressed {
+            self.keys[row] |= 1 << key;
+        } else {
+            self.keys[row] &= !(1 << key);
+        }
+    }
+}
+
+impl Default for FamilyKeyboard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ExpansionDevice for FamilyKeyboard {
+    fn read(&mut self, address: u16, _: &PPU) -> u8 {
+        if !self.enabled || address == 0x4016 {
+            return 0;
+        }
+
+        match self.keys.get(self.row) {
+            Some(keys) => (!(keys >> (self.column * 4)) & 0x0f) << 1,
+            None => 0x1e,
+        }
+    }
+
+    fn write(&mut self, data: u8) {
+        self.enabled = data & 0b100 != 0;
+        if !self.enabled {
+            return;
+        }
+
+        let column = (data >> 1) & 1;
+        if data & 1 == 1 {
+            self.row = 0;
+        } else if self.column == 1 && column == 0 {
+            // Dropping back to column 0 advances to the next row
+            self.row += 1;
+        }
+
+        self.column = column;
+    }
+
+    fn save_state(&self, writer: &mut dyn Write) -> io::Result<()> {
+        writer.write_u8(self.row as u8)?;
+        writer.write_u8(self.column)?;
+        writer.write_u8(self.enabled as u8)
+    }
+
+    fn load_state(&mut self, reader: &mut dyn Read) -> io::Result<()> {
+        self.row = reader.read_u8()? as usize;
+        self.column = reader.read_u8()?;
+        self.enabled = reader.read_u8()? != 0;
+        Ok(())
+    }
+}
@@ -0,0 +1,90 @@
+use std::io::{self, Read, Write};
+
+use byteorder::{ReadBytesExt, WriteBytesExt};
+
+use crate::ppu::PPU;
+
+use super::InputDevice;
+
+// Order the pad shifts its buttons out on D3 and D4
+const D3_BUTTONS: [u8; 8] = [2, 1, 5, 9, 6, 10, 11, 7];
+const D4_BUTTONS: [u8; 4] = [4, 3, 12, 8];
+
+pub struct PowerPad {
+    // Bit n - 1 is button n, using the numbering printed on side B
+    pub buttons: u16,
+    d3_shift: u8,
+    d4_shift: u8,
+    strobe: bool,
+}
+
+impl PowerPad {
+    pub fn new() -> Self {
+        Self {
+            buttons: 0,
+            d3_shift: 0,
+            d4_shift: 0,
+            strobe: false,
+        }
+    }
+
+    pub fn set_button(&mut self, button: u8, pressed: bool) {
+        let mask = 1 << (button - 1);
+        if pressed {
+            self.buttons |= mask;
+        } else {
+            self.buttons &= !mask;
+        }
+    }
+
+    fn pressed(&self, button: u8) -> u8 {
+        ((self.buttons >> (button - 1)) & 1) as u8
+    }
+
+    fn latch(&mut self) {
+        self.d3_shift = D3_BUTTONS.iter().enumerate()
+            .fold(0, |shift, (bit, &button)| shift | self.pressed(button) << bit);
+        // Only four buttons on D4, the rest of the report is 1s
+        self.d4_shift = D4_BUTTONS.iter().enumerate()
+            .fold(0xf0, |shift, (bit, &button)| shift | self.pressed(button) << bit);
+    }
+}
+
+impl Default for PowerPad {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InputDevice for PowerPad {
+    fn read(&mut self, _: &PPU) -> u8 {
+        if self.strobe {
+            self.latch();
+        }
+
+        let data = ((self.d3_shift & 1) << 3) | ((self.d4_shift & 1) << 4);
+        self.d3_shift = (self.d3_shift >> 1) | 0x80;
+        self.d4_shift = (self.d4_shift >> 1) | 0x80;
+        data
+    }
+
+    fn strobe(&mut self, data: u8) {
+        self.strobe = data & 1 == 1;
+        if self.strobe {
+            self.latch();
+        }
+    }
+
+    fn save_state(&self, writer: &mut dyn Write) -> io::Result<()> {
+        writer.write_u8(self.d3_shift)?;
+        writer.write_u8(self.d4_shift)?;
+        writer.write_u8(self.strobe as u8)
+    }
+
+    fn load_state(&mut self, reader: &mut dyn Read) -> io::Result<()> {
+        self.d3_shift = reader.read_u8()?;
+        self.d4_shift = reader.read_u8()?;
+        self.strobe = reader.read_u8()? != 0;
+        Ok(())
+    }
+}
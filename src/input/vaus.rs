@@ -0,0 +1,84 @@
+use std::io::{self, Read, Write};
+
+use byteorder::{ReadBytesExt, WriteBytesExt};
+
+use crate::ppu::PPU;
+
+use super::{ExpansionDevice, InputDevice};
+
+// Arkanoid paddle. The knob position is latched on strobe and shifted out MSB first, inverted. The NES version
+// answers on $4017 D3/D4, the Famicom one on D1 of $4016/$4017 through the expansion port.
+pub struct ArkanoidVaus {
+    pub position: u8,
+    pub button: bool,
+    shift: u8,
+}
+
+impl ArkanoidVaus {
+    pub fn new() -> Self {
+        Self {
+            position: 0x80,
+            button: false,
+            shift: 0,
+        }
+    }
+
+    fn next_bit(&mut self) -> u8 {
+        let data = self.shift >> 7;
+        self.shift <<= 1;
+        data
+    }
+
+    fn latch(&mut self, data: u8) {
+        if data & 1 == 1 {
+            self.shift = !self.position;
+        }
+    }
+}
+
+impl Default for ArkanoidVaus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InputDevice for ArkanoidVaus {
+    fn read(&mut self, _: &PPU) -> u8 {
+        ((self.button as u8) << 3) | (self.next_bit() << 4)
+    }
+
+    fn strobe(&mut self, data: u8) {
+        self.latch(data);
+    }
+
+    fn save_state(&self, writer: &mut dyn Write) -> io::Result<()> {
+        writer.write_u8(self.shift)
+    }
+
+    fn load_state(&mut self, reader: &mut dyn Read) -> io::Result<()> {
+        self.shift = reader.read_u8()?;
+        Ok(())
+    }
+}
+
+impl ExpansionDevice for ArkanoidVaus {
+    fn read(&mut self, address: u16, _: &PPU) -> u8 {
+        match address {
+            0x4016 => (self.button as u8) << 1,
+            _ => self.next_bit() << 1,
+        }
+    }
+
+    fn write(&mut self, data: u8) {
+        self.latch(data);
+    }
+
+    fn save_state(&self, writer: &mut dyn Write) -> io::Result<()> {
+        writer.write_u8(self.shift)
+    }
+
+    fn load_state(&mut self, reader: &mut dyn Read) -> io::Result<()> {
+        self.shift = reader.read_u8()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,93 @@
+use crate::ppu::{PPU, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+use super::InputDevice;
+
+const LIGHT_NOT_DETECTED: u8 = 0b0000_1000;
+const TRIGGER_PULLED: u8 = 0b0001_0000;
+
+// Brightness (0-255) the photodiode has to see before it reports light
+const LIGHT_THRESHOLD: u8 = 85;
+// The sensor output stays asserted for roughly this many scanlines after the beam passes the aim point
+const LIGHT_WINDOW: u16 = 20;
+
+// Composite luma levels of the 2C02 relative to black, low and high half of each colour row
+const LUMA_LOW: [f32; 4] = [-0.117, 0.000, 0.308, 0.715];
+const LUMA_HIGH: [f32; 4] = [0.397, 0.681, 1.000, 1.000];
+
+pub struct Zapper {
+    // Screen coordinate the gun is pointed at, None when aimed off-screen
+    pub position: Option<(u16, u16)>,
+    pub trigger: bool,
+    // How many pixels around the aim point the sensor can see
+    pub radius: u16,
+}
+
+impl Zapper {
+    pub fn new() -> Self {
+        Self {
+            position: None,
+            trigger: false,
+            radius: 3,
+        }
+    }
+
+    pub fn aim(&mut self, x: u16, y: u16) {
+        self.position = Some((x, y));
+    }
+
+    fn detects_light(&self, ppu: &PPU) -> bool {
+        let (x, y) = match self.position {
+            Some((x, y)) if (x as usize) < SCREEN_WIDTH && (y as usize) < SCREEN_HEIGHT => (x, y),
+            _ => return false,
+        };
+
+        // Light is only seen shortly after the beam has drawn the area under the gun. Pixel x
+        // comes out of the PPU on dot x + 1.
+        if ppu.scanline < y || ppu.scanline - y > LIGHT_WINDOW || (ppu.scanline == y && ppu.dot <= x + 1) {
+            return false;
+        }
+
+        let top = y.saturating_sub(self.radius) as usize;
+        let bottom = (y + self.radius).min(ppu.scanline).min(SCREEN_HEIGHT as u16 - 1) as usize;
+        let left = x.saturating_sub(self.radius) as usize;
+        let right = (x + self.radius).min(SCREEN_WIDTH as u16 - 1) as usize;
+
+        (top..=bottom).any(|row| (left..=right).any(|column| brightness(ppu.pixel(column, row)) >= LIGHT_THRESHOLD))
+    }
+}
+
+impl Default for Zapper {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InputDevice for Zapper {
+    fn read(&mut self, ppu: &PPU) -> u8 {
+        let mut data = 0;
+        if !self.detects_light(ppu) {
+            data |= LIGHT_NOT_DETECTED;
+        }
+
+        if self.trigger {
+            data |= TRIGGER_PULLED;
+        }
+
+        data
+    }
+
+    fn strobe(&mut self, _: u8) {}
+}
+
+fn brightness(color: u16) -> u8 {
+    let hue = (color & 0x0f) as usize;
+    let level = ((color >> 4) & 0x03) as usize;
+    let luma = match hue {
+        0x00 => LUMA_HIGH[level],
+        0x0d => LUMA_LOW[level],
+        0x0e | 0x0f => 0.0,
+        _ => (LUMA_LOW[level] + LUMA_HIGH[level]) / 2.0,
+    };
+
+    (luma.clamp(0.0, 1.0) * 255.0) as u8
+}
@@ -1,9 +1,22 @@
 #![feature(mixed_integer_ops)]
 pub mod apu;
+pub mod audio;
 pub mod address;
+pub mod battery;
+pub mod bindings;
 pub mod bus;
+pub mod cdl;
+pub mod cheats;
 pub mod cpu;
+pub mod debugger;
+pub mod disasm;
+pub mod input;
 pub mod ppu;
 pub mod memory;
+pub mod movie;
+pub mod ntsc;
+pub mod palette;
 pub mod roms;
-pub mod system;
\ No newline at end of file
+pub mod state;
+pub mod system;
+pub mod testing;
\ No newline at end of file
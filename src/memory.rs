@@ -1,4 +1,7 @@
+use std::io::{self, Read, Write};
+
 use crate::bus::BusDevice;
+use crate::state::SaveState;
 
 trait MemoryDevice {
     fn normalize_address(&self, address: u16) -> u16;
@@ -26,7 +29,7 @@ impl <const SIZE: usize> MemoryDevice for RAM<SIZE> {
 }
 
 impl<const SIZE: usize> BusDevice for RAM<SIZE> {
-    fn read(&self, address: u16) -> u8 {
+    fn read(&mut self, address: u16) -> u8 {
         
         (*self.bank)[self.normalize_address(address) as usize]
     }
@@ -34,6 +37,24 @@ impl<const SIZE: usize> BusDevice for RAM<SIZE> {
     fn write(self: &mut Self, address: u16, data: u8) {
         (*self.bank)[self.normalize_address(address) as usize] = data;
     }
+
+    fn peek(&self, address: u16) -> u8 {
+        (*self.bank)[self.normalize_address(address) as usize]
+    }
+
+    fn poke(&mut self, address: u16, data: u8) {
+        self.write(address, data);
+    }
+}
+
+impl<const SIZE: usize> SaveState for RAM<SIZE> {
+    fn save_state(&self, writer: &mut dyn Write) -> io::Result<()> {
+        writer.write_all(self.bank.as_slice())
+    }
+
+    fn load_state(&mut self, reader: &mut dyn Read) -> io::Result<()> {
+        reader.read_exact(self.bank.as_mut_slice())
+    }
 }
 
 pub struct ROM<const SIZE: usize> {
@@ -57,11 +78,20 @@ impl <const SIZE: usize> MemoryDevice for ROM<SIZE> {
 }
 
 impl<const SIZE: usize> BusDevice for ROM<SIZE> {
-    fn read(&self, address: u16) -> u8 {
+    fn read(&mut self, address: u16) -> u8 {
         (*self.bank)[self.normalize_address(address) as usize]
     }
 
     fn write(self: &mut Self, address: u16, data: u8) {
         // Can't write to ROM, so just ignore
      }
+
+    fn peek(&self, address: u16) -> u8 {
+        (*self.bank)[self.normalize_address(address) as usize]
+    }
+
+    fn poke(&mut self, address: u16, data: u8) {
+        let index = self.normalize_address(address) as usize;
+        self.bank[index] = data;
+    }
 }
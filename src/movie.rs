@@ -0,0 +1,157 @@
+mod bk2;
+mod fm2;
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io;
+use std::path::Path;
+
+use crate::input::Buttons;
+
+// Frames between RAM hashes while recording
+pub const DEFAULT_HASH_INTERVAL: usize = 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MovieFrame {
+    pub ports: [Buttons; 2],
+    pub reset: bool,
+    // There's no power cycle without the ROM image to hand, so playing one back resets instead
+    pub power: bool,
+}
+
+impl MovieFrame {
+    pub fn new() -> Self {
+        Self {
+            ports: [Buttons::empty(); 2],
+            reset: false,
+            power: false,
+        }
+    }
+}
+
+impl Default for MovieFrame {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Controller input for every frame from power on or from a save state, one entry per frame
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Movie {
+    pub frames: Vec<MovieFrame>,
+    // None starts from power on
+    pub start_state: Option<Vec<u8>>,
+    pub rerecords: u32,
+    pub rom_filename: String,
+    pub pal: bool,
+    pub comments: Vec<String>,
+    // FNV-1a of internal RAM at the start of some frames, by frame index, for spotting where a playback desyncs
+    pub ram_hashes: BTreeMap<usize, u64>,
+}
+
+impl Movie {
+    pub fn new() -> Self {
+        Self {
+            frames: Vec::new(),
+            start_state: None,
+            rerecords: 0,
+            rom_filename: String::new(),
+            pal: false,
+            comments: Vec::new(),
+            ram_hashes: BTreeMap::new(),
+        }
+    }
+
+    pub fn from_state(state: Vec<u8>) -> Self {
+        Self { start_state: Some(state), ..Self::new() }
+    }
+
+    // FCEUX .fm2 or BizHawk .bk2, going by the extension
+    pub fn load(path: &Path) -> io::Result<Self> {
+        match extension(path).as_deref() {
+            Some("fm2") => Self::parse_fm2(&fs::read_to_string(path)?),
+            Some("bk2") => Self::read_bk2(File::open(path)?),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidInput, "movies need to be .fm2 or .bk2")),
+        }
+    }
+
+    // Always as .fm2, there's no writing .bk2
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        match extension(path).as_deref() {
+            Some("fm2") => fs::write(path, self.to_fm2()),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidInput, "movies can only be saved as .fm2")),
+        }
+    }
+}
+
+impl Default for Movie {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn extension(path: &Path) -> Option<String> {
+    path.extension().map(|extension| extension.to_string_lossy().to_ascii_lowercase())
+}
+
+fn invalid_movie(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MovieMode {
+    Recording,
+    Playing,
+    // Played to the end in read-only mode, the controllers are the player's again
+    Finished,
+}
+
+// A frame whose RAM didn't hash to what the movie recorded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Desync {
+    pub frame: usize,
+    pub expected: u64,
+    pub actual: u64,
+}
+
+// A movie attached to a ConsoleSystem. It steps at the first CPU cycle of every frame, after the frontend has set the
+// controllers for it, recording them or overwriting them with the movie's. Frames are counted from the PPU's frame
+// number, so loading a state or rewinding moves the movie with it: read-only keeps playing from there, read-write
+// takes over recording from there and counts a rerecord.
+pub struct MovieSession {
+    pub movie: Movie,
+    pub mode: MovieMode,
+    pub read_only: bool,
+    pub hash_interval: usize,
+    pub desyncs: Vec<Desync>,
+    // PPU frame number of the movie's first frame
+    pub(crate) start_frame: u64,
+    // PPU frame number last stepped for
+    pub(crate) last_frame: Option<u64>,
+    pub(crate) reset_requested: bool,
+}
+
+impl MovieSession {
+    pub(crate) fn new(movie: Movie, mode: MovieMode, start_frame: u64) -> Self {
+        Self {
+            movie,
+            mode,
+            read_only: mode == MovieMode::Playing,
+            hash_interval: DEFAULT_HASH_INTERVAL,
+            desyncs: Vec::new(),
+            start_frame,
+            last_frame: None,
+            reset_requested: false,
+        }
+    }
+
+    // Resets at the start of the next frame and records that it did
+    pub fn request_reset(&mut self) {
+        self.reset_requested = true;
+    }
+
+    // Index of the frame about to run
+    pub fn frame(&self) -> usize {
+        self.last_frame.map_or(0, |frame| frame.saturating_sub(self.start_frame) as usize)
+    }
+}
@@ -0,0 +1,116 @@
+use std::io::{self, Read, Seek};
+
+use zip::ZipArchive;
+
+use crate::input::Buttons;
+
+use super::{invalid_movie, Movie, MovieFrame};
+
+// What BizHawk's NES core logs when a movie doesn't say
+const DEFAULT_LOG_KEY: &str = "#Reset|Power|#P1 Up|P1 Down|P1 Left|P1 Right|P1 Start|P1 Select|P1 B|P1 A|\
+                               #P2 Up|P2 Down|P2 Left|P2 Right|P2 Start|P2 Select|P2 B|P2 A|";
+
+fn button(name: &str) -> Option<Buttons> {
+    Some(match name {
+        "Up" => Buttons::UP,
+        "Down" => Buttons::DOWN,
+        "Left" => Buttons::LEFT,
+        "Right" => Buttons::RIGHT,
+        "Start" => Buttons::START,
+        "Select" => Buttons::SELECT,
+        "B" => Buttons::B,
+        "A" => Buttons::A,
+        _ => return None,
+    })
+}
+
+// Each group of the key is a field of the input lines, one character per button in the same order
+fn parse_log_key(key: &str) -> Vec<Vec<String>> {
+    key.split('#')
+        .filter(|group| !group.is_empty())
+        .map(|group| group.split('|').filter(|name| !name.is_empty()).map(str::to_string).collect())
+        .collect()
+}
+
+fn read_file(archive: &mut ZipArchive<impl Read + Seek>, name: &str) -> io::Result<Option<String>> {
+    let mut file = match archive.by_name(name) {
+        Ok(file) => file,
+        Err(zip::result::ZipError::FileNotFound) => return Ok(None),
+        Err(e) => return Err(invalid_movie(e.to_string())),
+    };
+    let mut text = String::new();
+    file.read_to_string(&mut text)?;
+    Ok(Some(text))
+}
+
+impl Movie {
+    // A .bk2 is a zip, the input is in "Input Log.txt" and the rerecord count and game name in "Header.txt". Movies
+    // starting from one of BizHawk's save states can't be played here.
+    pub fn read_bk2(reader: impl Read + Seek) -> io::Result<Self> {
+        let mut archive = ZipArchive::new(reader).map_err(|e| invalid_movie(e.to_string()))?;
+        let log = read_file(&mut archive, "Input Log.txt")?.ok_or_else(|| invalid_movie("no Input Log.txt in the .bk2"))?;
+        let mut movie = Movie::parse_bk2_input_log(&log)?;
+
+        for line in read_file(&mut archive, "Header.txt")?.unwrap_or_default().lines() {
+            let (key, value) = line.trim_end_matches('\r').split_once(' ').unwrap_or((line, ""));
+            match key {
+                "rerecordCount" => movie.rerecords = value.parse().unwrap_or(0),
+                "GameName" => movie.rom_filename = value.to_string(),
+                "StartsFromSavestate" if value.eq_ignore_ascii_case("true") => {
+                    return Err(invalid_movie("the .bk2 starts from a BizHawk save state"));
+                }
+                _ => {}
+            }
+        }
+        Ok(movie)
+    }
+
+    pub fn parse_bk2_input_log(text: &str) -> io::Result<Self> {
+        let mut key = parse_log_key(DEFAULT_LOG_KEY);
+        let mut movie = Movie::new();
+
+        for line in text.lines() {
+            let line = line.trim_end_matches('\r');
+            if let Some(log_key) = line.strip_prefix("LogKey:") {
+                key = parse_log_key(log_key);
+            } else if line.starts_with('|') {
+                movie.frames.push(parse_frame(&key, line)?);
+            }
+        }
+        Ok(movie)
+    }
+}
+
+fn parse_frame(key: &[Vec<String>], line: &str) -> io::Result<MovieFrame> {
+    let fields: Vec<&str> = line.trim_start_matches('|').trim_end_matches('|').split('|').collect();
+    if fields.len() != key.len() {
+        return Err(invalid_movie(format!("input line {} doesn't match the log key", line)));
+    }
+
+    let mut frame = MovieFrame::new();
+    for (names, field) in key.iter().zip(fields) {
+        for (name, state) in names.iter().zip(field.chars()) {
+            if state == '.' || state == ' ' {
+                continue;
+            }
+
+            match name.as_str() {
+                "Reset" => frame.reset = true,
+                "Power" => frame.power = true,
+                _ => {
+                    // "P1 Up" and so on, anything past the two standard controllers is left out
+                    let (player, name) = name.split_once(' ').unwrap_or(("", name));
+                    let port = match player {
+                        "P1" => 0,
+                        "P2" => 1,
+                        _ => continue,
+                    };
+                    if let Some(button) = button(name) {
+                        frame.ports[port] |= button;
+                    }
+                }
+            }
+        }
+    }
+    Ok(frame)
+}
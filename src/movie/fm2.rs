@@ -0,0 +1,117 @@
+use std::fmt::Write;
+use std::io;
+
+use crate::input::Buttons;
+
+use super::{invalid_movie, Movie, MovieFrame};
+
+// Button order in an input field, right to left is bit 0 up
+const BUTTONS: &[u8; 8] = b"RLDUTSBA";
+const SOFT_RESET: u8 = 1;
+const HARD_RESET: u8 = 2;
+
+fn write_port(line: &mut String, buttons: Buttons) {
+    for (i, name) in BUTTONS.iter().enumerate() {
+        line.push(if buttons.bits() & (0x80 >> i) != 0 { *name as char } else { '.' });
+    }
+}
+
+// Anything other than '.' or ' ' is held
+fn parse_port(field: &str) -> Buttons {
+    let bits = field.bytes().take(8).enumerate().fold(0, |bits, (i, byte)| match byte {
+        b'.' | b' ' => bits,
+        _ => bits | (0x80 >> i),
+    });
+    Buttons::from_bits_truncate(bits)
+}
+
+impl Movie {
+    // FCEUX's text format with a gamepad in each port. RAM hashes go in ramHash lines, which FCEUX skips over.
+    pub fn to_fm2(&self) -> String {
+        let mut text = String::new();
+        let _ = writeln!(text, "version 3");
+        let _ = writeln!(text, "emuVersion 0");
+        let _ = writeln!(text, "rerecordCount {}", self.rerecords);
+        let _ = writeln!(text, "palFlag {}", self.pal as u8);
+        let _ = writeln!(text, "romFilename {}", self.rom_filename);
+        let _ = writeln!(text, "romChecksum base64:{}", base64::encode([0u8; 16]));
+        let _ = writeln!(text, "guid 00000000-0000-0000-0000-000000000000");
+        let _ = writeln!(text, "fourscore 0");
+        let _ = writeln!(text, "microphone 0");
+        let _ = writeln!(text, "port0 1");
+        let _ = writeln!(text, "port1 1");
+        let _ = writeln!(text, "port2 0");
+        let _ = writeln!(text, "FDS 0");
+        let _ = writeln!(text, "NewPPU 0");
+        if let Some(state) = &self.start_state {
+            let _ = writeln!(text, "savestate base64:{}", base64::encode(state));
+        }
+        for comment in self.comments.iter() {
+            let _ = writeln!(text, "comment {}", comment);
+        }
+        for (frame, hash) in self.ram_hashes.iter() {
+            let _ = writeln!(text, "ramHash {} {:016x}", frame, hash);
+        }
+
+        for frame in self.frames.iter() {
+            let commands = (frame.reset as u8 * SOFT_RESET) | (frame.power as u8 * HARD_RESET);
+            let mut line = format!("|{}|", commands);
+            write_port(&mut line, frame.ports[0]);
+            line.push('|');
+            write_port(&mut line, frame.ports[1]);
+            line.push_str("||");
+            let _ = writeln!(text, "{}", line);
+        }
+        text
+    }
+
+    pub fn parse_fm2(text: &str) -> io::Result<Self> {
+        let mut movie = Movie::new();
+        for line in text.lines() {
+            let line = line.trim_end_matches('\r');
+            if line.starts_with('|') {
+                movie.frames.push(parse_frame(line)?);
+                continue;
+            }
+
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            match key {
+                "binary" if value != "0" => return Err(invalid_movie("binary .fm2 input isn't supported")),
+                "rerecordCount" => movie.rerecords = value.parse().map_err(|_| invalid_movie("bad rerecordCount"))?,
+                "palFlag" => movie.pal = value == "1",
+                "romFilename" => movie.rom_filename = value.to_string(),
+                "comment" => movie.comments.push(value.to_string()),
+                "savestate" => {
+                    let encoded = value.strip_prefix("base64:").ok_or_else(|| invalid_movie("savestate isn't base64"))?;
+                    movie.start_state = Some(base64::decode(encoded).map_err(|e| invalid_movie(e.to_string()))?);
+                }
+                "ramHash" => {
+                    let (frame, hash) = value.split_once(' ').ok_or_else(|| invalid_movie("ramHash needs a frame and a hash"))?;
+                    let frame = frame.parse().map_err(|_| invalid_movie("bad ramHash frame"))?;
+                    let hash = u64::from_str_radix(hash, 16).map_err(|_| invalid_movie("bad ramHash hash"))?;
+                    movie.ram_hashes.insert(frame, hash);
+                }
+                _ => {}
+            }
+        }
+        Ok(movie)
+    }
+}
+
+// |commands|port0|port1|port2|
+fn parse_frame(line: &str) -> io::Result<MovieFrame> {
+    let fields: Vec<&str> = line.split('|').collect();
+    if fields.len() < 4 {
+        return Err(invalid_movie(format!("bad input line {}", line)));
+    }
+
+    let commands: u8 = match fields[1].trim() {
+        "" => 0,
+        commands => commands.parse().map_err(|_| invalid_movie(format!("bad commands in {}", line)))?,
+    };
+    Ok(MovieFrame {
+        ports: [parse_port(fields[2]), parse_port(fields[3])],
+        reset: commands & SOFT_RESET != 0,
+        power: commands & HARD_RESET != 0,
+    })
+}
@@ -0,0 +1,172 @@
+use crate::palette::{self, NtscParameters};
+use crate::ppu::{SCREEN_HEIGHT, SCREEN_WIDTH};
+
+// Composite samples per PPU dot, the colour subcarrier takes 12 of them per cycle
+const SAMPLES_PER_DOT: usize = 8;
+const SAMPLES_PER_CYCLE: usize = 12;
+// Output pixels per PPU dot, enough to show the colour fringes
+pub const PIXELS_PER_DOT: usize = 2;
+pub const NTSC_WIDTH: usize = SCREEN_WIDTH * PIXELS_PER_DOT;
+const SAMPLES_PER_PIXEL: usize = SAMPLES_PER_DOT / PIXELS_PER_DOT;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NtscFilterSettings {
+    // 0 blurs luma over a whole subcarrier cycle, 1 keeps all of the detail the signal has
+    pub sharpness: f32,
+    // Share of luma detail the decoder mistakes for colour, the rainbows on fine patterns. 0 keeps luma and chroma as
+    // apart as S-Video does.
+    pub artifacts: f32,
+    // Share of chroma the luma filter lets through at colour edges, where it shows up as crawling dots
+    pub fringing: f32,
+    pub colour: NtscParameters,
+}
+
+impl NtscFilterSettings {
+    pub fn new() -> Self {
+        Self {
+            sharpness: 0.5,
+            artifacts: 1.0,
+            fringing: 1.0,
+            colour: NtscParameters::new(),
+        }
+    }
+}
+
+impl Default for NtscFilterSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Re-encodes PPU output as the 2C02's composite signal and decodes it again like a TV would, on the CPU so it works
+// anywhere the framebuffer goes
+pub struct NtscFilter {
+    pub settings: NtscFilterSettings,
+    // Composite level of every pixel value at every subcarrier phase
+    levels: Vec<[f32; SAMPLES_PER_CYCLE]>,
+}
+
+fn cycle_average(levels: &[f32; SAMPLES_PER_CYCLE]) -> f32 {
+    levels.iter().sum::<f32>() / SAMPLES_PER_CYCLE as f32
+}
+
+// One scanline of composite signal with a cycle of black either side, so no window ever runs off the end
+struct Scanline {
+    samples: Vec<f32>,
+    // The luma each sample's dot was encoded with, what a separate luma signal would have carried
+    luma: Vec<f32>,
+    // Running totals for quick window averages, sums[i] covers samples[..i]
+    sums: Vec<f32>,
+    // Subcarrier phase of the first sample
+    start: usize,
+    // Cosine and sine of the subcarrier at each phase
+    carrier: [(f32, f32); SAMPLES_PER_CYCLE],
+}
+
+impl Scanline {
+    fn average(&self, centre: usize, width: usize) -> f32 {
+        let first = centre - width / 2;
+        (self.sums[first + width] - self.sums[first]) / width as f32
+    }
+
+    fn carrier(&self, index: usize) -> (f32, f32) {
+        self.carrier[(self.start + index) % SAMPLES_PER_CYCLE]
+    }
+}
+
+impl NtscFilter {
+    pub fn new(settings: NtscFilterSettings) -> Self {
+        let levels = (0..palette::PALETTE_ENTRIES as u16)
+            .map(|pixel| std::array::from_fn(|phase| palette::signal(pixel, phase)))
+            .collect();
+        Self { settings, levels }
+    }
+
+    // NTSC_WIDTH x SCREEN_HEIGHT RGB. Each frame starts a third of a subcarrier cycle on from the last, which is what
+    // makes the dots crawl.
+    pub fn apply(&self, framebuffer: &[u16], frame: u64) -> Vec<[u8; 3]> {
+        let mut output = Vec::with_capacity(NTSC_WIDTH * SCREEN_HEIGHT);
+        let carrier = std::array::from_fn(|phase| {
+            let angle = palette::subcarrier_angle(phase as f32, self.settings.colour.hue);
+            (angle.cos(), angle.sin())
+        });
+        for (y, line) in framebuffer.chunks_exact(SCREEN_WIDTH).take(SCREEN_HEIGHT).enumerate() {
+            // 341 dots of 8 samples puts every scanline 4 phases on from the one before, and 262 of them a frame 4 on
+            let start = (frame as usize + y) * 4 % SAMPLES_PER_CYCLE;
+            let scanline = self.encode(line, start, carrier);
+            for x in 0..NTSC_WIDTH {
+                output.push(self.decode(&scanline, x));
+            }
+        }
+        output
+    }
+
+    fn encode(&self, line: &[u16], start: usize, carrier: [(f32, f32); SAMPLES_PER_CYCLE]) -> Scanline {
+        // The padding is a whole number of cycles so it doesn't move the phase
+        let mut samples = vec![0.0; (line.len() * SAMPLES_PER_DOT) + SAMPLES_PER_CYCLE * 2];
+        let mut luma = samples.clone();
+        for (dot, pixel) in line.iter().enumerate() {
+            let levels = &self.levels[*pixel as usize & 0x1ff];
+            for sample in 0..SAMPLES_PER_DOT {
+                let index = dot * SAMPLES_PER_DOT + sample;
+                samples[SAMPLES_PER_CYCLE + index] = levels[(start + index) % SAMPLES_PER_CYCLE];
+                luma[SAMPLES_PER_CYCLE + index] = cycle_average(levels);
+            }
+        }
+
+        let mut sums = Vec::with_capacity(samples.len() + 1);
+        sums.push(0.0);
+        for sample in samples.iter() {
+            sums.push(sums.last().unwrap() + sample);
+        }
+        Scanline { samples, luma, sums, start, carrier }
+    }
+
+    // I and Q over `width` samples round `centre`, after taking away as much of the luma as artifacts leaves out
+    fn demodulate(&self, scanline: &Scanline, centre: usize, width: usize) -> (f32, f32) {
+        let keep_luma = self.settings.artifacts.clamp(0.0, 1.0);
+        let (mut i, mut q) = (0.0, 0.0);
+        for index in centre - width / 2..centre - width / 2 + width {
+            let chroma = scanline.samples[index] - scanline.luma[index] * (1.0 - keep_luma);
+            let (cos, sin) = scanline.carrier(index);
+            i += chroma * cos;
+            q += chroma * sin;
+        }
+        (i * 2.0 / width as f32, q * 2.0 / width as f32)
+    }
+
+    // The chroma wave at `centre` as worked out from `width` samples round it
+    fn chroma(&self, scanline: &Scanline, centre: usize, width: usize) -> f32 {
+        let (i, q) = self.demodulate(scanline, centre, width);
+        let (cos, sin) = scanline.carrier(centre);
+        i * cos + q * sin
+    }
+
+    fn decode(&self, scanline: &Scanline, x: usize) -> [u8; 3] {
+        let settings = &self.settings;
+        let colour = &settings.colour;
+        let centre = SAMPLES_PER_CYCLE + x * SAMPLES_PER_PIXEL + SAMPLES_PER_PIXEL / 2;
+
+        // Taking the chroma away sample by sample keeps the detail a whole cycle's average loses. Estimating it from a
+        // wider window gets it wrong at colour edges and what's left over is the fringing.
+        let raw = scanline.samples[centre];
+        let exact = raw - self.chroma(scanline, centre, SAMPLES_PER_CYCLE);
+        let smeared = raw - self.chroma(scanline, centre, SAMPLES_PER_CYCLE * 2);
+        let sharp = exact + (smeared - exact) * settings.fringing.clamp(0.0, 1.0);
+        let soft = scanline.average(centre, SAMPLES_PER_CYCLE);
+        let luma = soft + (sharp - soft) * settings.sharpness.clamp(0.0, 1.0);
+
+        let (i, q) = self.demodulate(scanline, centre, SAMPLES_PER_CYCLE);
+        let y = luma * colour.contrast + colour.brightness;
+        palette::yiq_to_rgb(y, i * colour.saturation, q * colour.saturation).map(|channel| {
+            let corrected = channel.max(0.0).powf(2.2 / colour.gamma);
+            (corrected.min(1.0) * 255.0).round() as u8
+        })
+    }
+}
+
+impl Default for NtscFilter {
+    fn default() -> Self {
+        Self::new(NtscFilterSettings::new())
+    }
+}
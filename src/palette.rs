@@ -0,0 +1,133 @@
+mod generator;
+mod rgb_ppu;
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+pub use self::generator::NtscParameters;
+pub(crate) use self::generator::{signal, subcarrier_angle, yiq_to_rgb};
+pub use self::rgb_ppu::{RGB_PPU_PALETTE, RP2C04_ORDERS, RP2C04_PALETTE};
+
+// Every colour index with every combination of the three emphasis bits
+pub const PALETTE_ENTRIES: usize = 64 * 8;
+// What the 2C02 leaves of the other channels' share of the signal when one is emphasised
+const EMPHASIS_ATTENUATION: f32 = 0.816;
+
+// 2C02 colours as RGB, indexed by the 6-bit colour in the framebuffer
+pub const DEFAULT_PALETTE: [[u8; 3]; 64] = [
+    [0x62, 0x62, 0x62], [0x00, 0x1f, 0xb2], [0x24, 0x04, 0xc8], [0x52, 0x00, 0xb2],
+    [0x73, 0x00, 0x76], [0x80, 0x00, 0x24], [0x73, 0x0b, 0x00], [0x52, 0x28, 0x00],
+    [0x24, 0x44, 0x00], [0x00, 0x57, 0x00], [0x00, 0x5c, 0x00], [0x00, 0x53, 0x24],
+    [0x00, 0x3c, 0x76], [0x00, 0x00, 0x00], [0x00, 0x00, 0x00], [0x00, 0x00, 0x00],
+    [0xab, 0xab, 0xab], [0x0d, 0x57, 0xff], [0x4b, 0x30, 0xff], [0x8a, 0x13, 0xff],
+    [0xbc, 0x08, 0xd6], [0xd2, 0x12, 0x69], [0xc7, 0x2e, 0x00], [0x9d, 0x54, 0x00],
+    [0x60, 0x7b, 0x00], [0x20, 0x98, 0x00], [0x00, 0xa3, 0x00], [0x00, 0x99, 0x42],
+    [0x00, 0x7d, 0xb4], [0x00, 0x00, 0x00], [0x00, 0x00, 0x00], [0x00, 0x00, 0x00],
+    [0xff, 0xff, 0xff], [0x53, 0xae, 0xff], [0x90, 0x85, 0xff], [0xd3, 0x65, 0xff],
+    [0xff, 0x57, 0xff], [0xff, 0x5d, 0xcf], [0xff, 0x77, 0x57], [0xfa, 0x9e, 0x00],
+    [0xbd, 0xc7, 0x00], [0x7a, 0xe7, 0x00], [0x43, 0xf6, 0x11], [0x26, 0xef, 0x7e],
+    [0x2c, 0xd5, 0xf6], [0x4e, 0x4e, 0x4e], [0x00, 0x00, 0x00], [0x00, 0x00, 0x00],
+    [0xff, 0xff, 0xff], [0xb6, 0xe1, 0xff], [0xce, 0xd1, 0xff], [0xe9, 0xc3, 0xff],
+    [0xff, 0xbc, 0xff], [0xff, 0xbd, 0xf4], [0xff, 0xc6, 0xc3], [0xff, 0xd5, 0x9a],
+    [0xe9, 0xe6, 0x81], [0xce, 0xf4, 0x81], [0xb6, 0xfb, 0x9a], [0xa9, 0xfa, 0xc3],
+    [0xa9, 0xf0, 0xf4], [0xb8, 0xb8, 0xb8], [0x00, 0x00, 0x00], [0x00, 0x00, 0x00],
+];
+
+// Turns framebuffer pixels (6-bit colour index, emphasis bits above it with red at bit 6) into RGB
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Palette {
+    colours: Vec<[u8; 3]>,
+    // Shows everything in the grey column, like PPUMASK bit 0 does but for the whole display
+    pub greyscale: bool,
+}
+
+impl Palette {
+    // The built in 2C02 colours with emphasis worked out from them
+    pub fn new() -> Self {
+        Self::with_emphasis(&DEFAULT_PALETTE)
+    }
+
+    // 64 entries as 2C02 colours, the emphasised ones get dimmed from them
+    pub fn with_emphasis(colours: &[[u8; 3]; 64]) -> Self {
+        let colours = (0..PALETTE_ENTRIES)
+            .map(|pixel| {
+                let colour = colours[pixel & 0x3f];
+                let emphasis = pixel >> 6;
+                // $xE/$xF are blanked to black and don't get any dimmer
+                if emphasis == 0 || pixel & 0x0e == 0x0e {
+                    return colour;
+                }
+                let mut rgb = [0; 3];
+                for (channel, value) in rgb.iter_mut().enumerate() {
+                    let attenuated = emphasis & !(1 << channel) != 0;
+                    *value = match attenuated {
+                        true => (colour[channel] as f32 * EMPHASIS_ATTENUATION).round() as u8,
+                        false => colour[channel],
+                    };
+                }
+                rgb
+            })
+            .collect();
+        Self { colours, greyscale: false }
+    }
+
+    // A .pal file: 64 or 512 RGB triplets, the latter with the emphasised colours after the plain ones
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+
+    pub fn from_bytes(data: &[u8]) -> io::Result<Self> {
+        let triplets: Vec<[u8; 3]> = data.chunks_exact(3).map(|rgb| [rgb[0], rgb[1], rgb[2]]).collect();
+        match data.len() {
+            192 => Ok(Self::with_emphasis(triplets.as_slice().try_into().expect("Length checked above"))),
+            1536 => Ok(Self { colours: triplets, greyscale: false }),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidData, "Palette files have 64 or 512 colours")),
+        }
+    }
+
+    // Worked out from a model of the 2C02's composite output
+    pub fn generate(parameters: &NtscParameters) -> Self {
+        Self { colours: generator::generate(parameters), greyscale: false }
+    }
+
+    // The 2C03 and 2C05 RGB PPUs in Vs. System and PlayChoice hardware, which turn emphasis bits into full intensity on that
+    // channel instead of dimming the others
+    pub fn rgb_ppu() -> Self {
+        Self { colours: rgb_ppu::colours(&RGB_PPU_PALETTE, &std::array::from_fn(|index| index as u8)), greyscale: false }
+    }
+
+    // The RP2C04-0001 to -0004 used by Vs. System games, None for any other revision
+    pub fn rp2c04(revision: usize) -> Option<Self> {
+        let order = RP2C04_ORDERS.get(revision.checked_sub(1)?)?;
+        Some(Self::rgb_ppu_reordered(order))
+    }
+
+    // The 2C04 colours in any order, `order[i]` being the RP2C04_PALETTE colour that shows up at index i
+    pub fn rgb_ppu_reordered(order: &[u8; 64]) -> Self {
+        Self { colours: rgb_ppu::colours(&RP2C04_PALETTE, order), greyscale: false }
+    }
+
+    pub fn rgb(&self, pixel: u16) -> [u8; 3] {
+        let pixel = match self.greyscale {
+            true => pixel & 0x1f0,
+            false => pixel & 0x1ff,
+        };
+        self.colours[pixel as usize]
+    }
+
+    pub fn colours(&self) -> &[[u8; 3]] {
+        &self.colours
+    }
+
+    // Always the 512 entry form
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.colours.iter().flatten().copied().collect()
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,109 @@
+use std::f32::consts::PI;
+
+use super::PALETTE_ENTRIES;
+
+// 2C02 output voltages for the four luma levels, low and high half of the colour wave, relative to sync
+const LOW_LEVELS: [f32; 4] = [0.350, 0.518, 0.962, 1.550];
+const HIGH_LEVELS: [f32; 4] = [1.094, 1.506, 1.962, 1.962];
+const BLACK: f32 = 0.518;
+const WHITE: f32 = 1.962;
+// Emphasis pulls the signal down to this during its colour's part of the wave
+const EMPHASIS_ATTENUATION: f32 = 0.746;
+// Where colour 1's wave sits against the colour burst, in twelfths of a cycle
+const PHASE_OFFSET: f32 = 3.9;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NtscParameters {
+    // Rotates every colour, in degrees
+    pub hue: f32,
+    pub saturation: f32,
+    pub contrast: f32,
+    pub brightness: f32,
+    // Of the display the palette is meant for, 2.2 leaves the decoded colours as they are
+    pub gamma: f32,
+}
+
+impl NtscParameters {
+    pub fn new() -> Self {
+        Self {
+            hue: 0.0,
+            saturation: 1.0,
+            contrast: 1.0,
+            brightness: 0.0,
+            gamma: 2.2,
+        }
+    }
+}
+
+impl Default for NtscParameters {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// True during the half of the 12 step colour cycle that `colour` is high
+fn in_colour_phase(colour: u16, phase: usize) -> bool {
+    (colour as usize + phase) % 12 < 6
+}
+
+// The composite level of one pixel at one of the 12 phases of the colour subcarrier, 0 black and 1 white
+pub(crate) fn signal(pixel: u16, phase: usize) -> f32 {
+    let colour = pixel & 0x0f;
+    let emphasis = (pixel >> 6) & 7;
+    // $xE/$xF are black whatever the level bits say
+    let level = match colour {
+        0x0e | 0x0f => 1,
+        _ => (pixel >> 4) as usize & 3,
+    };
+
+    let low = if colour == 0x00 { HIGH_LEVELS[level] } else { LOW_LEVELS[level] };
+    let high = if colour < 0x0d { HIGH_LEVELS[level] } else { LOW_LEVELS[level] };
+    let mut signal = match in_colour_phase(colour, phase) {
+        true => high,
+        false => low,
+    };
+
+    // Red, green and blue emphasis each dim a different half of the wave, a third of a cycle apart
+    let emphasised = [0, 4, 8].iter().enumerate().any(|(bit, colour)| emphasis & (1 << bit) != 0 && in_colour_phase(*colour, phase));
+    if emphasised && colour < 0x0e {
+        signal *= EMPHASIS_ATTENUATION;
+    }
+
+    (signal - BLACK) / (WHITE - BLACK)
+}
+
+// YIQ to RGB with the FCC matrix
+pub(crate) fn yiq_to_rgb(y: f32, i: f32, q: f32) -> [f32; 3] {
+    [
+        y + 0.946_882 * i + 0.623_557 * q,
+        y - 0.274_788 * i - 0.635_691 * q,
+        y - 1.108_545 * i + 1.709_007 * q,
+    ]
+}
+
+pub(crate) fn subcarrier_angle(phase: f32, hue: f32) -> f32 {
+    PI / 6.0 * (phase + PHASE_OFFSET) + hue.to_radians()
+}
+
+pub(super) fn generate(parameters: &NtscParameters) -> Vec<[u8; 3]> {
+    (0..PALETTE_ENTRIES as u16)
+        .map(|pixel| {
+            // Averaging a whole cycle of the wave separates luma from the two chroma components
+            let (mut y, mut i, mut q) = (0.0, 0.0, 0.0);
+            for phase in 0..12 {
+                let level = signal(pixel, phase) / 12.0;
+                let angle = subcarrier_angle(phase as f32, parameters.hue);
+                y += level;
+                i += level * angle.cos();
+                q += level * angle.sin();
+            }
+
+            let y = y * parameters.contrast + parameters.brightness;
+            let (i, q) = (i * parameters.saturation * 2.0, q * parameters.saturation * 2.0);
+            yiq_to_rgb(y, i, q).map(|channel| {
+                let corrected = channel.max(0.0).powf(2.2 / parameters.gamma);
+                (corrected.min(1.0) * 255.0).round() as u8
+            })
+        })
+        .collect()
+}
@@ -1,8 +1,20 @@
-use std::borrow::Borrow;
+use std::io::{self, Read, Write};
 
-use crate::bus::BusDevice;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use crate::bus::{AccessKind, BusAccess, BusDevice};
+use crate::state::SaveState;
+use crate::system::Region;
 use bitflags::bitflags;
 
+pub const SCREEN_WIDTH: usize = 256;
+pub const SCREEN_HEIGHT: usize = 240;
+pub const DOTS_PER_SCANLINE: u16 = 341;
+// NTSC, see RegionProfile for the others
+pub const SCANLINES_PER_FRAME: u16 = 262;
+
+const CHARACTER_RAM_SIZE: usize = 0x2000;
+
 bitflags! {
     pub struct Status: u8 {
         const SPRITE_OVERFLOW = 0b0010_0000;
@@ -10,41 +22,268 @@ bitflags! {
         const VBLANK = 0b1000_0000;
     }
 }
+// How the four logical nametables map onto the console's 2K of VRAM, decided by the cartridge
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mirroring {
+    Horizontal,
+    Vertical,
+    // The cartridge brings another 2K so every nametable is its own
+    FourScreen,
+}
+
 pub struct PPU {
     data: u8,
-    scanline_counter_or_something: u16,
+    ctrl: u8,
+    // PPUMASK, everything but sprites is looked at so far
+    pub mask: u8,
+    // Where $2007 reads and writes go, loaded through $2006 a byte at a time
+    pub vram_address: u16,
+    write_latch: bool,
+    // Set by a $2002 read on the dot before vblank starts, which then never gets flagged that frame
+    suppress_vblank: bool,
+    // The last PPU address space access made through $2007
+    pub last_access: Option<BusAccess>,
+    // $2007 reads come back one access late, except for the palette
+    read_buffer: u8,
+    pub mirroring: Mirroring,
+    // Pattern tables, handed over by the mapper. CHR-RAM when the cartridge has no CHR-ROM.
+    character: Vec<u8>,
+    character_ram: bool,
+    nametables: Box<[u8; 0x1000]>,
+    palette: [u8; 0x20],
     pub status: Status,
+    pub region: Region,
+    pub dot: u16,
+    pub scanline: u16,
+    pub frame: u64,
+    // One entry per visible pixel: 6-bit colour index with the emphasis bits above it
+    pub framebuffer: Box<[u16; SCREEN_WIDTH * SCREEN_HEIGHT]>,
 }
 
 impl PPU {
     pub fn new() -> Self {
         Self { 
             data: 0,
-            scanline_counter_or_something: 0,
-            status: Status::VBLANK | Status::SPRITE_OVERFLOW
+            ctrl: 0,
+            mask: 0,
+            vram_address: 0,
+            write_latch: false,
+            suppress_vblank: false,
+            last_access: None,
+            read_buffer: 0,
+            mirroring: Mirroring::Horizontal,
+            character: vec![0; CHARACTER_RAM_SIZE],
+            character_ram: true,
+            nametables: Box::new([0; 0x1000]),
+            palette: [0; 0x20],
+            status: Status::VBLANK | Status::SPRITE_OVERFLOW,
+            region: Region::Ntsc,
+            dot: 0,
+            scanline: 0,
+            frame: 0,
+            framebuffer: Box::new([0x0f; SCREEN_WIDTH * SCREEN_HEIGHT]),
         }
     }
 
+    pub fn with_region(region: Region) -> Self {
+        Self { region, ..Self::new() }
+    }
+
     pub fn reset(self: &mut Self) {
     }
 
     pub fn cycle(&mut self) {
-        self.scanline_counter_or_something = self.scanline_counter_or_something.wrapping_add(1);
+        let profile = self.region.profile();
+        self.dot += 1;
+        if self.dot == DOTS_PER_SCANLINE {
+            self.dot = 0;
+            self.scanline += 1;
+            if self.scanline == profile.scanlines_per_frame {
+                self.scanline = 0;
+                self.frame += 1;
+            }
+        }
+
+        if self.dot == 1 {
+            if self.scanline == profile.vblank_scanline() {
+                if !self.suppress_vblank {
+                    self.status.insert(Status::VBLANK);
+                }
+                self.suppress_vblank = false;
+            } else if self.scanline == profile.pre_render_scanline() {
+                self.status.remove(Status::all());
+            }
+        }
 
-        if self.scanline_counter_or_something % 256 == 0 {
-            self.status.toggle(Status::VBLANK);
+        // Pixel x comes out on dot x + 1
+        if (1..=SCREEN_WIDTH as u16).contains(&self.dot) && (self.scanline as usize) < SCREEN_HEIGHT {
+            let (x, y) = (self.dot as usize - 1, self.scanline as usize);
+            self.framebuffer[y * SCREEN_WIDTH + x] = self.output_pixel(self.colour_at(x, y));
         }
     }
 
-    fn read_status(&self) -> u8 {
-        self.status.bits
+    // Palette RAM colour the PPU puts out at a visible position. Only the background is drawn so far, from the
+    // nametable PPUCTRL picks and without $2005 scrolling, sprites aren't.
+    fn colour_at(&self, x: usize, y: usize) -> u8 {
+        if self.mask & 0x18 == 0 {
+            // With rendering off the backdrop shows, unless the VRAM address points into the palette
+            return match self.vram_address & 0x3fff {
+                address @ 0x3f00..=0x3fff => self.palette[palette_index(address)],
+                _ => self.palette[0],
+            };
+        }
+
+        let background = self.mask & 0x08 != 0 && (x >= 8 || self.mask & 0x02 != 0);
+        match background.then(|| self.background_pixel(x, y)) {
+            Some(pixel) if pixel & 0x03 != 0 => self.palette[pixel as usize],
+            _ => self.palette[0],
+        }
+    }
+
+    // Palette number in bits 2-3 and the pattern's two bits below them
+    fn background_pixel(&self, x: usize, y: usize) -> u8 {
+        let nametable = 0x2000 + (self.ctrl as u16 & 0x03) * 0x400;
+        let tile = self.peek_vram(nametable + (y / 8 * 32 + x / 8) as u16) as u16;
+        let attribute = self.peek_vram(nametable + 0x3c0 + (y / 32 * 8 + x / 32) as u16);
+        let palette = (attribute >> (((y / 16) & 1) * 4 + ((x / 16) & 1) * 2)) & 0x03;
+
+        let pattern = (self.ctrl as u16 & 0x10) << 8 | tile << 4 | (y & 7) as u16;
+        let bit = 7 - (x & 7);
+        let low = (self.peek_vram(pattern) >> bit) & 1;
+        let high = (self.peek_vram(pattern + 8) >> bit) & 1;
+        palette << 2 | high << 1 | low
+    }
+
+    // Level of the /NMI output, active high
+    pub fn nmi(&self) -> bool {
+        self.ctrl & 0x80 != 0 && self.status.contains(Status::VBLANK)
+    }
+
+    // What goes in the framebuffer for a palette RAM colour under the current PPUMASK. Emphasis ends up above the colour
+    // with red at bit 6, which on the PAL and Dendy PPUs means swapping the red and green bits round.
+    pub fn output_pixel(&self, colour: u8) -> u16 {
+        let colour = match self.mask & 0x01 {
+            0 => colour & 0x3f,
+            _ => colour & 0x30,
+        };
+        let emphasis = match self.region {
+            Region::Ntsc => self.mask >> 5,
+            Region::Pal | Region::Dendy => ((self.mask >> 6) & 1) | ((self.mask >> 4) & 2) | ((self.mask >> 5) & 4),
+        };
+        colour as u16 | (emphasis as u16) << 6
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> u16 {
+        self.framebuffer[y * SCREEN_WIDTH + x]
+    }
+
+    // FNV-1a over the framebuffer, for comparing frames against known good output
+    pub fn framebuffer_hash(&self) -> u64 {
+        self.framebuffer.iter().fold(0xcbf2_9ce4_8422_2325, |hash, pixel| {
+            pixel.to_le_bytes().iter().fold(hash, |hash, byte| (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3))
+        })
+    }
+
+    pub fn load_character(&mut self, data: &[u8]) {
+        self.character_ram = data.is_empty();
+        self.character = match self.character_ram {
+            true => vec![0; CHARACTER_RAM_SIZE],
+            false => data.to_vec(),
+        };
+    }
+
+    // Reads the PPU address space ($0000-$3FFF) without going through $2006/$2007
+    pub fn peek_vram(&self, address: u16) -> u8 {
+        match address & 0x3fff {
+            address @ 0x0000..=0x1fff => self.character.get(address as usize).copied().unwrap_or(0),
+            address @ 0x2000..=0x3eff => self.nametables[self.nametable_index(address)],
+            address => self.palette[palette_index(address)],
+        }
+    }
+
+    // Like a $2007 write but also changes CHR-ROM
+    pub fn poke_vram(&mut self, address: u16, data: u8) {
+        match address & 0x3fff {
+            address @ 0x0000..=0x1fff => {
+                if let Some(byte) = self.character.get_mut(address as usize) {
+                    *byte = data;
+                }
+            }
+            address @ 0x2000..=0x3eff => self.nametables[self.nametable_index(address)] = data,
+            address => self.palette[palette_index(address)] = data,
+        }
+    }
+
+    fn nametable_index(&self, address: u16) -> usize {
+        let (table, offset) = ((address as usize >> 10) & 3, address as usize & 0x3ff);
+        let table = match self.mirroring {
+            Mirroring::Horizontal => table >> 1,
+            Mirroring::Vertical => table & 1,
+            Mirroring::FourScreen => table,
+        };
+        table * 0x400 + offset
+    }
+
+    fn read_status(&mut self) -> u8 {
+        let status = self.status.bits;
+        self.write_latch = false;
+        self.status.remove(Status::VBLANK);
+        if (self.scanline, self.dot) == (self.region.profile().vblank_scanline(), 0) {
+            self.suppress_vblank = true;
+        }
+        status
+    }
+
+    // What a $2007 read returns, palette reads skip the buffer
+    fn data_port(&self) -> u8 {
+        match self.vram_address & 0x3fff {
+            0x3f00..=0x3fff => self.peek_vram(self.vram_address),
+            _ => self.read_buffer,
+        }
+    }
+
+    fn read_data(&mut self) -> u8 {
+        let data = self.data_port();
+        // Palette reads still fill the buffer, with the nametable byte underneath
+        self.read_buffer = match self.vram_address & 0x3fff {
+            address @ 0x3f00..=0x3fff => self.peek_vram(address - 0x1000),
+            address => self.peek_vram(address),
+        };
+        self.access_vram(AccessKind::Read, data);
+        data
+    }
+
+    fn write_data(&mut self, data: u8) {
+        let address = self.vram_address & 0x3fff;
+        if address >= 0x2000 || self.character_ram {
+            self.poke_vram(address, data);
+        }
+        self.access_vram(AccessKind::Write, data);
+    }
+
+    fn access_vram(&mut self, kind: AccessKind, data: u8) {
+        let address = self.vram_address & 0x3fff;
+        self.last_access = Some(BusAccess { kind, address, data });
+        self.vram_address = self.vram_address.wrapping_add(match self.ctrl & 0x04 {
+            0 => 1,
+            _ => 32,
+        }) & 0x7fff;
+    }
+
+    fn write_address(&mut self, data: u8) {
+        self.vram_address = match self.write_latch {
+            false => (self.vram_address & 0x00ff) | ((data as u16 & 0x3f) << 8),
+            true => (self.vram_address & 0xff00) | data as u16,
+        };
+        self.write_latch = !self.write_latch;
     }
 }
 
 impl BusDevice for PPU {
-    fn read(&self, address: u16) -> u8 {
-        match address {
+    fn read(&mut self, address: u16) -> u8 {
+        match address & 0x2007 {
             0x2002 => self.read_status(),
+            0x2007 => self.read_data(),
             _ => 0,
         }
         //println!("PPU READ!! ${:04X}", address);
@@ -52,6 +291,86 @@ impl BusDevice for PPU {
     }
     fn write(&mut self, address: u16, data: u8) {
         self.data = data;
+        match address & 0x2007 {
+            0x2000 => self.ctrl = data,
+            0x2001 => self.mask = data,
+            0x2006 => self.write_address(data),
+            0x2007 => self.write_data(data),
+            _ => {}
+        }
         //println!("PPU WRITE!! ${:04X}", address);
     }
+
+    fn peek(&self, address: u16) -> u8 {
+        match address & 0x2007 {
+            0x2002 => self.status.bits,
+            0x2007 => self.data_port(),
+            _ => 0,
+        }
+    }
+
+    // $2007 pokes land at the current VRAM address without moving it, $2006 pokes aren't possible without the latch
+    fn poke(&mut self, address: u16, data: u8) {
+        match address & 0x2007 {
+            0x2000 => self.ctrl = data,
+            0x2001 => self.mask = data,
+            0x2002 => self.status = Status::from_bits_truncate(data),
+            0x2007 => self.poke_vram(self.vram_address, data),
+            _ => {}
+        }
+    }
+}
+
+// $3F10/$3F14/$3F18/$3F1C are the same bytes as $3F00/$3F04/$3F08/$3F0C
+fn palette_index(address: u16) -> usize {
+    match address as usize & 0x1f {
+        index if index & 0x13 == 0x10 => index & 0x0f,
+        index => index,
+    }
+}
+
+impl SaveState for PPU {
+    fn save_state(&self, writer: &mut dyn Write) -> io::Result<()> {
+        writer.write_u8(self.data)?;
+        writer.write_u8(self.ctrl)?;
+        writer.write_u8(self.mask)?;
+        writer.write_u16::<LittleEndian>(self.vram_address)?;
+        writer.write_u8(self.write_latch as u8)?;
+        writer.write_u8(self.suppress_vblank as u8)?;
+        writer.write_u8(self.read_buffer)?;
+        writer.write_all(self.nametables.as_slice())?;
+        writer.write_all(&self.palette)?;
+        if self.character_ram {
+            writer.write_all(&self.character)?;
+        }
+        writer.write_u8(self.status.bits)?;
+        writer.write_u16::<LittleEndian>(self.dot)?;
+        writer.write_u16::<LittleEndian>(self.scanline)?;
+        writer.write_u64::<LittleEndian>(self.frame)?;
+        for pixel in self.framebuffer.iter() {
+            writer.write_u16::<LittleEndian>(*pixel)?;
+        }
+
+        Ok(())
+    }
+
+    fn load_state(&mut self, reader: &mut dyn Read) -> io::Result<()> {
+        self.data = reader.read_u8()?;
+        self.ctrl = reader.read_u8()?;
+        self.mask = reader.read_u8()?;
+        self.vram_address = reader.read_u16::<LittleEndian>()?;
+        self.write_latch = reader.read_u8()? != 0;
+        self.suppress_vblank = reader.read_u8()? != 0;
+        self.read_buffer = reader.read_u8()?;
+        reader.read_exact(self.nametables.as_mut_slice())?;
+        reader.read_exact(&mut self.palette)?;
+        if self.character_ram {
+            reader.read_exact(&mut self.character)?;
+        }
+        self.status = Status::from_bits_truncate(reader.read_u8()?);
+        self.dot = reader.read_u16::<LittleEndian>()?;
+        self.scanline = reader.read_u16::<LittleEndian>()?;
+        self.frame = reader.read_u64::<LittleEndian>()?;
+        reader.read_u16_into::<LittleEndian>(self.framebuffer.as_mut_slice())
+    }
 }
@@ -1,14 +1,17 @@
 //#![feature(const_ops)]
+use crate::apu::Alu2A03;
 use crate::bus::BusDevice;
+use crate::input::{ControllerPorts, OPEN_BUS};
 use crate::memory::{RAM, ROM};
-use crate::ppu::PPU;
+use crate::ppu::{Mirroring, PPU};
+use crate::state::SaveState;
 use crate::system::ConsoleDevices;
 use bitflags::bitflags;
 use byteorder::ReadBytesExt;
 use num_enum::TryFromPrimitive;
 use std::convert::TryFrom;
 use std::io::Read;
-use std::io::{self, Seek, SeekFrom};
+use std::io::{self, Seek, SeekFrom, Write};
 
 bitflags! {
     pub struct RomFlags: u8 {
@@ -19,11 +22,12 @@ bitflags! {
     }
 }
 
-#[derive(Debug, TryFromPrimitive)]
-#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TVSystem {
-    NTSC = 0x0,
-    PAL = 0x1,
+    NTSC,
+    PAL,
+    // Only NES 2.0 headers can say so
+    Dendy,
 }
 
 #[derive(Debug, TryFromPrimitive)]
@@ -43,6 +47,8 @@ pub struct RomImageHeader {
     pub console_type: ConsoleType,
     pub program_ram_size: u8,
     pub tv_system: TVSystem,
+    // NES 2.0 only
+    pub default_expansion_device: Option<u8>,
 }
 
 pub struct RomImage {
@@ -90,35 +96,54 @@ impl RomImage {
         let console_type_flags = reader.read_u8()?;
         let console_type = ConsoleType::try_from(console_type_flags & 0x3)
             .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, "Unknown console type"))?;
+        let mut mapper: u16 = ((rom_flags >> 4u8) | console_type_flags & 0xf0).into();
 
-        if (console_type_flags & 0xc) == 0x8 {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                "NES 2.0 format not supported",
-            ));
-        }
+        let (program_ram_size, tv_system, default_expansion_device) = if (console_type_flags & 0xc) == 0x8 {
+            let mut nes2: [u8; 8] = [0; 8];
+            reader.read_exact(&mut nes2)?;
+            mapper |= ((nes2[0] & 0x0f) as u16) << 8;
 
-        let program_ram_size = reader.read_u8()?;
-        let tv_system = TVSystem::try_from(reader.read_u8()?)
-            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, "Unknown TV system type"))?;
-        if reader.read_u8()? != 0 {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                "I don't know why but I don't support non-zero values here",
-            ));
-        }
+            // PRG-RAM is stored as a shift count, 64 << n bytes, with battery backed PRG-NVRAM in the high nibble. There's
+            // only the one program RAM so it's sized for the larger of the two.
+            let program_ram_size = match (nes2[2] & 0x0f).max(nes2[2] >> 4) {
+                0 => 0,
+                shift => ((64usize << shift) / 0x2000).clamp(1, 0xff) as u8,
+            };
+            let tv_system = match nes2[4] & 0x3 {
+                1 => TVSystem::PAL,
+                3 => TVSystem::Dendy,
+                _ => TVSystem::NTSC,
+            };
 
-        // Unused padding
-        reader.seek(SeekFrom::Current(5))?;
+            (program_ram_size, tv_system, Some(nes2[7] & 0x3f))
+        } else {
+            let program_ram_size = reader.read_u8()?;
+            let tv_system = match reader.read_u8()? {
+                0 => TVSystem::NTSC,
+                1 => TVSystem::PAL,
+                _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "Unknown TV system type")),
+            };
+            if reader.read_u8()? != 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "I don't know why but I don't support non-zero values here",
+                ));
+            }
+
+            // Unused padding
+            reader.seek(SeekFrom::Current(5))?;
+            (program_ram_size, tv_system, None)
+        };
 
         Ok(RomImageHeader {
             program_rom_size: program_rom_size,
             character_rom_size,
             rom_flags: RomFlags::from_bits_truncate(rom_flags),
-            mapper: ((rom_flags >> 4u8) | console_type_flags & 0xf0).into(),
+            mapper,
             console_type: console_type,
             program_ram_size: program_ram_size,
             tv_system: tv_system,
+            default_expansion_device,
         })
     }
 }
@@ -134,6 +159,12 @@ impl RomError {
     }
 }
 
+impl std::fmt::Display for RomError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.message)
+    }
+}
+
 // impl Error for RomError {}
 
 // impl From<std::io::Error> for RomError {
@@ -146,36 +177,91 @@ pub struct Mappers;
 
 impl Mappers {
     pub fn from(image: RomImage, devices: ConsoleDevices) -> Result<Box<dyn Mapper>, RomError> {
+        Self::check(&image.header)?;
         match image.header.mapper {
             0 => Ok(Box::new(NROM::new(image, devices))),
             _ => Err(RomError::new("Unsupported mapper")),
         }
     }
+
+    // Whether `from` can build a mapper for the header, so callers can turn a ROM down before building a system
+    pub fn check(header: &RomImageHeader) -> Result<(), RomError> {
+        match header.mapper {
+            0 if header.program_rom_size == 1 || header.program_rom_size == 2 => Ok(()),
+            0 => Err(RomError::new("NROM needs 16K or 32K of PRG-ROM")),
+            _ => Err(RomError::new("Unsupported mapper")),
+        }
+    }
 }
 
-pub trait Mapper {
-    fn read(self: &Self, address: u16) -> u8;
+pub trait Mapper: SaveState {
+    fn read(self: &mut Self, address: u16) -> u8;
     fn write(self: &mut Self, address: u16, data: u8) -> ();
     fn get_ppu(&mut self) -> &mut PPU;
+    fn get_alu(&mut self) -> &mut Alu2A03;
+    fn get_input(&mut self) -> &mut ControllerPorts;
+
+    // Debugger access to the CPU address space, see BusDevice::peek and BusDevice::poke
+    fn peek(&self, address: u16) -> u8;
+    fn poke(&mut self, address: u16, data: u8);
+
+    // The same for the PPU address space: pattern tables, nametables and palette
+    fn peek_ppu(&self, address: u16) -> u8;
+    fn poke_ppu(&mut self, address: u16, data: u8);
+
+    // Everything the cartridge keeps across power cycles (battery PRG-RAM, EEPROM, ...), in .sav layout
+    fn battery_ram(&self) -> Option<Vec<u8>> {
+        None
+    }
+
+    fn load_battery_ram(&mut self, _: &[u8]) -> io::Result<()> {
+        Ok(())
+    }
+
+    // Level of the cartridge's /IRQ line, active high
+    fn irq(&self) -> bool {
+        false
+    }
+
+    // Where a CPU address currently lands in the PRG-ROM data, through whatever banking the board does
+    fn program_rom_offset(&self, _: u16) -> Option<usize> {
+        None
+    }
+
+    // The same for a PPU address and the CHR-ROM data, None for CHR-RAM
+    fn character_rom_offset(&self, _: u16) -> Option<usize> {
+        None
+    }
 }
 
 pub struct NROM {
     //image: RomImage,
     devices: ConsoleDevices,
+    battery: bool,
+    program_rom_banks: u8,
+    character_rom: bool,
     program_ram: RAM::<0x2000>,
     program_rom_bank0: ROM::<0x4000>,
     program_rom_bank1: ROM::<0x4000>,
 }
 
 impl NROM {
-    fn new(image: RomImage, devices: ConsoleDevices) -> Self {
+    fn new(image: RomImage, mut devices: ConsoleDevices) -> Self {
+        devices.ppu.load_character(&image.character_rom_data);
+        devices.ppu.mirroring = match image.header.rom_flags {
+            flags if flags.contains(RomFlags::FOUR_SCREEN) => Mirroring::FourScreen,
+            flags if flags.contains(RomFlags::VERTICAL) => Mirroring::Vertical,
+            _ => Mirroring::Horizontal,
+        };
+
         NROM {
             //image,
             devices,
-            program_ram: match image.header.program_ram_size {
-                0 => RAM::<0x2000>::new(0x1fff),
-                _ => RAM::<0x2000>::new((image.header.program_ram_size as u16 * 0x2000) - 1),
-            },
+            battery: image.header.rom_flags.contains(RomFlags::BATTERY),
+            program_rom_banks: image.header.program_rom_size,
+            character_rom: !image.character_rom_data.is_empty(),
+            // NROM only has the 8K window at $6000, so a header asking for more still gets 8K
+            program_ram: RAM::<0x2000>::new(0x1fff),
             program_rom_bank0: ROM::<0x4000>::new(&image.program_rom_data[0..0x4000], 0x3fff),
             program_rom_bank1: match image.header.program_rom_size {
                 1 => ROM::<0x4000>::new(&image.program_rom_data[0..0x4000], 0x3fff),
@@ -191,11 +277,52 @@ impl Mapper for NROM {
         &mut self.devices.ppu
     }
 
-    fn read(self: &Self, address: u16) -> u8 {
+    fn get_alu(&mut self) -> &mut Alu2A03 {
+        &mut self.devices.alu
+    }
+
+    fn get_input(&mut self) -> &mut ControllerPorts {
+        &mut self.devices.input
+    }
+
+    fn battery_ram(&self) -> Option<Vec<u8>> {
+        self.battery.then(|| self.program_ram.bank.to_vec())
+    }
+
+    fn load_battery_ram(&mut self, data: &[u8]) -> io::Result<()> {
+        if !self.battery {
+            return Ok(());
+        }
+
+        if data.len() != self.program_ram.bank.len() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Battery RAM size mismatch"));
+        }
+
+        self.program_ram.bank.copy_from_slice(data);
+        Ok(())
+    }
+
+    fn program_rom_offset(&self, address: u16) -> Option<usize> {
+        match address {
+            0x8000..=0xbfff => Some(address as usize & 0x3fff),
+            // NROM-128 mirrors its only bank into both halves
+            0xc000..=0xffff => Some((self.program_rom_banks as usize - 1) * 0x4000 + (address as usize & 0x3fff)),
+            _ => None,
+        }
+    }
+
+    fn character_rom_offset(&self, address: u16) -> Option<usize> {
+        (self.character_rom && address & 0x3fff < 0x2000).then_some(address as usize & 0x1fff)
+    }
+
+    fn read(self: &mut Self, address: u16) -> u8 {
         let data = match address >> 13 {
             0 => self.devices.ram.read(address),
             1 => self.devices.ppu.read(address),
-            2 => self.devices.alu.read(address),
+            2 => match address {
+                0x4016 | 0x4017 => self.devices.input.read(address, &self.devices.ppu),
+                _ => self.devices.alu.read(address),
+            },
             3 => self.program_ram.read(address),
             // look at https://github.com/Cryowatt/NES/blob/master/NES.CPU/Mappers/Mapper0.cs#L21
             4 | 5 => self.program_rom_bank0.read(address),
@@ -210,7 +337,10 @@ impl Mapper for NROM {
         match address >> 13 {
             0 => self.devices.ram.write(address, data),
             1 => self.devices.ppu.write(address, data),
-            2 => self.devices.alu.write(address, data),
+            2 => match address {
+                0x4016 => self.devices.input.write(data),
+                _ => self.devices.alu.write(address, data),
+            },
             3 => self.program_ram.write(address, data),
             // look at https://github.com/Cryowatt/NES/blob/master/NES.CPU/Mappers/Mapper0.cs#L21
             4 | 5 => self.program_rom_bank0.write(address, data),
@@ -219,4 +349,66 @@ impl Mapper for NROM {
 
         //println!("${:04X} <- {:02x}", address, data);
     }
-}
\ No newline at end of file
+
+    fn peek(&self, address: u16) -> u8 {
+        match address >> 13 {
+            0 => self.devices.ram.peek(address),
+            1 => self.devices.ppu.peek(address),
+            2 => match address {
+                // Controllers are shift registers, there's nothing to see without clocking them
+                0x4016 | 0x4017 => OPEN_BUS,
+                _ => self.devices.alu.peek(address),
+            },
+            3 => self.program_ram.peek(address),
+            4 | 5 => self.program_rom_bank0.peek(address),
+            _ => self.program_rom_bank1.peek(address),
+        }
+    }
+
+    fn poke(&mut self, address: u16, data: u8) {
+        match address >> 13 {
+            0 => self.devices.ram.poke(address, data),
+            1 => self.devices.ppu.poke(address, data),
+            2 => match address {
+                0x4016 | 0x4017 => {}
+                _ => self.devices.alu.poke(address, data),
+            },
+            3 => self.program_ram.poke(address, data),
+            // NROM-128 keeps a copy of its bank in each half, both have to change
+            _ => {
+                if address < 0xc000 || self.program_rom_banks == 1 {
+                    self.program_rom_bank0.poke(address, data);
+                }
+                if address >= 0xc000 || self.program_rom_banks == 1 {
+                    self.program_rom_bank1.poke(address, data);
+                }
+            }
+        }
+    }
+
+    fn peek_ppu(&self, address: u16) -> u8 {
+        self.devices.ppu.peek_vram(address)
+    }
+
+    fn poke_ppu(&mut self, address: u16, data: u8) {
+        self.devices.ppu.poke_vram(address, data)
+    }
+}
+
+impl SaveState for NROM {
+    fn save_state(&self, writer: &mut dyn Write) -> io::Result<()> {
+        self.devices.ram.save_state(writer)?;
+        self.devices.ppu.save_state(writer)?;
+        self.devices.alu.save_state(writer)?;
+        self.devices.input.save_state(writer)?;
+        self.program_ram.save_state(writer)
+    }
+
+    fn load_state(&mut self, reader: &mut dyn Read) -> io::Result<()> {
+        self.devices.ram.load_state(reader)?;
+        self.devices.ppu.load_state(reader)?;
+        self.devices.alu.load_state(reader)?;
+        self.devices.input.load_state(reader)?;
+        self.program_ram.load_state(reader)
+    }
+}
@@ -1,30 +1,36 @@
-struct VOutput{   
-    @location(0) v_color: vec4<f32>,
-    @builtin(position) position: vec4<f32>,
+struct Screen {
+    size: vec2<f32>,
 };
 
+// 6-bit colour index with the emphasis bits above it, straight from the PPU
+@group(0) @binding(0) var frame: texture_2d<u32>;
+// 64 colours across, one row per emphasis combination
+@group(0) @binding(1) var palette: texture_2d<f32>;
+@group(0) @binding(2) var<uniform> screen: Screen;
+
 @vertex
-fn vs_main(@builtin(vertex_index) in_vertex_index: u32) -> VOutput {    
+fn vs_main(@builtin(vertex_index) in_vertex_index: u32) -> @builtin(position) vec4<f32> {
     var pos = array<vec2<f32>,4>(
         vec2<f32>(-1.0, 1.0),
         vec2<f32>(1.0, 1.0),
         vec2<f32>(-1.0, -1.0),
         vec2<f32>(1.0, -1.0)
     );
-    var color = array<vec3<f32>,4>(
-        vec3<f32>(1.0, 0.0, 0.0),
-        vec3<f32>(0.0, 1.0, 0.0),
-        vec3<f32>(0.0, 0.0, 1.0),
-        vec3<f32>(0.0, 0.0, 0.0)
-    );
-
-    var out: VOutput;
-    out.position = vec4<f32>(pos[in_vertex_index], 0.0, 1.0);
-    out.v_color = vec4<f32>(color[in_vertex_index], 1.0);
-    return out;
+    return vec4<f32>(pos[in_vertex_index], 0.0, 1.0);
 }
 
 @fragment
-fn fs_main(in: VOutput) -> @location(0) vec4<f32> {
-    return in.v_color;
-}
\ No newline at end of file
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    // NES pixels are 8:7, scaled up by the biggest whole number that still fits and centred
+    let picture = vec2<f32>(256.0 * 8.0 / 7.0, 240.0);
+    let scale = max(1.0, floor(min(screen.size.x / picture.x, screen.size.y / picture.y)));
+    let size = picture * scale;
+    let origin = floor((screen.size - size) / 2.0);
+    let uv = (position.xy - origin) / size;
+    if (any(uv < vec2<f32>(0.0, 0.0)) || any(uv >= vec2<f32>(1.0, 1.0))) {
+        return vec4<f32>(0.05, 0.062, 0.08, 1.0);
+    }
+
+    let pixel = textureLoad(frame, vec2<i32>(uv * vec2<f32>(256.0, 240.0)), 0).r;
+    return textureLoad(palette, vec2<i32>(i32(pixel & 63u), i32(pixel >> 6u)), 0);
+}
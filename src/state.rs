@@ -0,0 +1,34 @@
+use std::io::{self, Read, Write};
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+pub const STATE_MAGIC: &[u8; 4] = b"NESS";
+// Bump whenever anything below changes what it writes
+pub const STATE_VERSION: u16 = 9;
+
+pub trait SaveState {
+    fn save_state(&self, writer: &mut dyn Write) -> io::Result<()>;
+    fn load_state(&mut self, reader: &mut dyn Read) -> io::Result<()>;
+}
+
+pub fn write_header(writer: &mut dyn Write) -> io::Result<()> {
+    writer.write_all(STATE_MAGIC)?;
+    writer.write_u16::<LittleEndian>(STATE_VERSION)
+}
+
+pub fn read_header(reader: &mut dyn Read) -> io::Result<()> {
+    let mut magic: [u8; 4] = [0; 4];
+    reader.read_exact(&mut magic)?;
+    if &magic != STATE_MAGIC {
+        return Err(invalid_state("Not a save state"));
+    }
+
+    match reader.read_u16::<LittleEndian>()? {
+        STATE_VERSION => Ok(()),
+        _ => Err(invalid_state("Unsupported save state version")),
+    }
+}
+
+pub fn invalid_state(message: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
@@ -1,13 +1,34 @@
+mod clock;
+mod frame;
+mod movie;
+mod region;
+mod rewind;
+
+use std::io;
+
 use crate::cpu::Mos6502;
 use crate::cpu::RP2A03;
+use crate::movie::MovieSession;
 use crate::roms::Mappers;
 use crate::roms::RomImage;
+use crate::state::{self, SaveState};
 
 use crate::apu::Alu2A03;
+use crate::input::{ControllerPorts, InputLayout};
 use crate::{memory::RAM, ppu::PPU};
 
+pub use self::clock::{ClockTick, MasterClock, NTSC_MASTER_CLOCK, PAL_MASTER_CLOCK};
+pub use self::frame::{EventHook, FrameResult, RunUntil, SystemEvent, RUN_UNTIL_FRAMES};
+pub use self::region::{Region, RegionProfile, DENDY, NTSC, PAL};
+pub use self::rewind::{RewindBuffer, RewindSettings};
+
 pub struct ConsoleSystem {
     pub cpu: Mos6502,
+    pub clock: MasterClock,
+    pub region: Region,
+    hooks: Vec<Box<dyn EventHook>>,
+    rewind: Option<RewindBuffer>,
+    movie: Option<MovieSession>,
     //pub mapper: Box<dyn Mapper>,
 }
 
@@ -15,16 +36,25 @@ pub struct ConsoleDevices {
     pub ram: RAM<2048>,
     pub ppu: PPU,
     pub alu: Alu2A03,
+    pub input: ControllerPorts,
 }
 
 impl ConsoleSystem {
+    // Runs at whatever region the header asks for
     pub fn new(image: RomImage) -> Self {
+        let region = Region::from_tv_system(image.header.tv_system);
+        Self::with_region(image, region)
+    }
+
+    pub fn with_region(image: RomImage, region: Region) -> Self {
         let devices = ConsoleDevices {
             ram: RAM::<0x800>::new(0x7FF),
-            ppu: PPU::new(),
-            alu: Alu2A03 { fake_status: 0x00 },
+            ppu: PPU::with_region(region),
+            alu: Alu2A03::with_region(region),
+            input: ControllerPorts::with_layout(InputLayout::from_header(&image.header)),
         };
-        
+        let clock = MasterClock::new(region);
+
         let mapper = Mappers::from(image, devices).expect("failed to create mapper");
 
         // let memoryMap: MemoryMapper = |a: u16, devices: &mut ConsoleDevices| match a >> 13 {
@@ -42,16 +72,89 @@ impl ConsoleSystem {
         // let mut ram = Box::new(RAM::<0x800>::new());
         // let fk: &mut dyn BusDevice = ram.as_mut();
 
-        ConsoleSystem { cpu: Mos6502::new(mapper) }
+        ConsoleSystem { cpu: Mos6502::new(mapper), clock, region, hooks: Vec::new(), rewind: None, movie: None }
     }
 
     pub fn reset(&mut self) {
         self.cpu.reset();
         self.cpu.mapper.get_ppu().reset();
+        self.forget_rewind();
+    }
+
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        self.write_state(&mut data).expect("Writing to memory can't fail");
+        data
+    }
+
+    // All or nothing: the parts load one after another, so a state that turns out truncated or corrupt part way
+    // through gets the system put back the way it was
+    pub fn load_state(&mut self, data: &[u8]) -> io::Result<()> {
+        let previous = self.save_state();
+        self.read_state(data).inspect_err(|_| {
+            self.read_state(&previous).expect("Reloading the state from before can't fail");
+        })?;
+        self.forget_rewind();
+        Ok(())
+    }
+
+    fn read_state(&mut self, mut data: &[u8]) -> io::Result<()> {
+        state::read_header(&mut data)?;
+        self.clock.load_state(&mut data)?;
+        self.cpu.load_state(&mut data)?;
+        self.cpu.mapper.load_state(&mut data)
     }
 
+    fn write_state(&self, data: &mut Vec<u8>) -> io::Result<()> {
+        state::write_header(data)?;
+        self.clock.save_state(data)?;
+        self.cpu.save_state(data)?;
+        self.cpu.mapper.save_state(data)
+    }
+
+    pub fn battery_ram(&self) -> Option<Vec<u8>> {
+        self.cpu.mapper.battery_ram()
+    }
+
+    pub fn load_battery_ram(&mut self, data: &[u8]) -> io::Result<()> {
+        self.cpu.mapper.load_battery_ram(data)
+    }
+
+    pub fn input(&mut self) -> &mut ControllerPorts {
+        self.cpu.mapper.get_input()
+    }
+
+    // One CPU cycle's worth of master clock ticks
     pub fn cycle(&mut self) {
-        self.cpu.cycle();
-        self.cpu.mapper.get_ppu().cycle();
+        if self.movie.is_some() {
+            self.advance_movie();
+        }
+        loop {
+            self.tick();
+            if self.clock.at_cpu_cycle() {
+                break;
+            }
+        }
+    }
+
+    // One master clock tick. On a tick shared by both the CPU goes first, so a $2002 read sees the PPU as it was
+    // before that dot.
+    pub fn tick(&mut self) {
+        let tick = self.clock.tick();
+        if tick.cpu {
+            self.cpu.cycle();
+            self.cpu.mapper.get_alu().cycle();
+
+            // Sampled after the cycle's own access so a $2002 read that clears vblank also keeps the NMI from firing
+            let nmi = self.cpu.mapper.get_ppu().nmi();
+            let irq = self.cpu.mapper.irq();
+            self.cpu.set_interrupt_lines(nmi, irq);
+        }
+        if tick.ppu {
+            self.cpu.mapper.get_ppu().cycle();
+            if !self.hooks.is_empty() {
+                self.emit_events();
+            }
+        }
     }
 }
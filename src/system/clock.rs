@@ -0,0 +1,74 @@
+use std::io::{self, Read, Write};
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use crate::state::SaveState;
+
+use super::Region;
+
+// Master clock rates in Hz, everything else in the console is divided down from these
+pub const NTSC_MASTER_CLOCK: u64 = 21_477_272;
+pub const PAL_MASTER_CLOCK: u64 = 26_601_712;
+
+// Which components get clocked on a master clock tick
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClockTick {
+    pub cpu: bool,
+    pub ppu: bool,
+}
+
+pub struct MasterClock {
+    pub rate: u64,
+    pub cpu_divider: u64,
+    pub ppu_divider: u64,
+    // Master clock ticks since power on
+    pub cycle: u64,
+}
+
+impl MasterClock {
+    pub fn new(region: Region) -> Self {
+        let profile = region.profile();
+        Self {
+            rate: profile.master_clock,
+            cpu_divider: profile.cpu_divider,
+            ppu_divider: profile.ppu_divider,
+            cycle: 0,
+        }
+    }
+
+    // What runs on the current tick, then moves on to the next one
+    pub fn tick(&mut self) -> ClockTick {
+        let tick = ClockTick {
+            cpu: self.cycle.is_multiple_of(self.cpu_divider),
+            ppu: self.cycle.is_multiple_of(self.ppu_divider),
+        };
+        self.cycle += 1;
+        tick
+    }
+
+    // True when the next tick starts a CPU cycle
+    pub fn at_cpu_cycle(&self) -> bool {
+        self.cycle.is_multiple_of(self.cpu_divider)
+    }
+
+    pub fn cpu_rate(&self) -> f64 {
+        self.rate as f64 / self.cpu_divider as f64
+    }
+}
+
+impl Default for MasterClock {
+    fn default() -> Self {
+        Self::new(Region::Ntsc)
+    }
+}
+
+impl SaveState for MasterClock {
+    fn save_state(&self, writer: &mut dyn Write) -> io::Result<()> {
+        writer.write_u64::<LittleEndian>(self.cycle)
+    }
+
+    fn load_state(&mut self, reader: &mut dyn Read) -> io::Result<()> {
+        self.cycle = reader.read_u64::<LittleEndian>()?;
+        Ok(())
+    }
+}
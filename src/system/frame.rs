@@ -0,0 +1,164 @@
+use std::io;
+use std::mem;
+
+use crate::ppu::{SCREEN_HEIGHT, SCREEN_WIDTH};
+
+use super::ConsoleSystem;
+
+// What a run produced, with the framebuffer as it stood when the run stopped
+pub struct FrameResult {
+    pub framebuffer: Box<[u16; SCREEN_WIDTH * SCREEN_HEIGHT]>,
+    pub samples: Vec<f32>,
+    // Nothing read the controllers, the game most likely didn't get its frame's work done
+    pub lag: bool,
+    // CPU cycles run
+    pub cycles: u64,
+}
+
+// How long run_until waits for a scanline or PC before giving up, ten seconds of NTSC
+pub const RUN_UNTIL_FRAMES: u64 = 600;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunUntil {
+    // CPU cycles since power on, see ConsoleSystem::cpu_cycles
+    Cycle(u64),
+    // The PPU moving onto this scanline
+    Scanline(u16),
+    // An instruction boundary with PC here
+    Pc(u16),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SystemEvent {
+    // Dot 0 of scanline 0, with the number of the frame that's starting
+    FrameStart(u64),
+    // Dot 1 of the region's first vblank scanline, whether or not a $2002 read suppressed the flag
+    Vblank(u64),
+    // Dot 0 of every scanline
+    Scanline(u16),
+}
+
+// Called from inside the master clock loop, so hooks should be quick about it
+pub trait EventHook {
+    fn event(&mut self, system: &mut ConsoleSystem, event: SystemEvent);
+}
+
+impl<F: FnMut(&mut ConsoleSystem, SystemEvent)> EventHook for F {
+    fn event(&mut self, system: &mut ConsoleSystem, event: SystemEvent) {
+        self(system, event)
+    }
+}
+
+impl ConsoleSystem {
+    pub fn add_hook(&mut self, hook: Box<dyn EventHook>) {
+        self.hooks.push(hook);
+    }
+
+    pub fn clear_hooks(&mut self) {
+        self.hooks.clear();
+    }
+
+    pub fn cpu_cycles(&self) -> u64 {
+        self.clock.cycle / self.clock.cpu_divider
+    }
+
+    // Runs to the start of the next frame
+    pub fn run_frame(&mut self) -> FrameResult {
+        // The movie goes first so rewind records what it set the controllers to
+        self.advance_movie();
+        self.record_rewind();
+        let frame = self.cpu.mapper.get_ppu().frame;
+        self.run(|system| system.cpu.mapper.get_ppu().frame != frame)
+    }
+
+    // Cycles always come round, a scanline or PC that hasn't after RUN_UNTIL_FRAMES frames is a TimedOut error
+    pub fn run_until(&mut self, until: RunUntil) -> io::Result<FrameResult> {
+        if let RunUntil::Scanline(wanted) = until {
+            if wanted >= self.region.profile().scanlines_per_frame {
+                let message = format!("{:?} has no scanline {}", self.region, wanted);
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, message));
+            }
+        }
+
+        let ppu = self.cpu.mapper.get_ppu();
+        let (mut scanline, last_frame) = (ppu.scanline, ppu.frame + RUN_UNTIL_FRAMES);
+        let mut reached = false;
+        let result = self.run(|system| {
+            reached = match until {
+                RunUntil::Cycle(cycle) => return system.cpu_cycles() >= cycle,
+                RunUntil::Scanline(wanted) => {
+                    let (before, after) = (scanline, system.cpu.mapper.get_ppu().scanline);
+                    scanline = after;
+                    after == wanted && before != wanted
+                }
+                RunUntil::Pc(pc) => system.cpu.at_instruction_boundary() && system.cpu.pc == pc,
+            };
+            reached || system.cpu.mapper.get_ppu().frame >= last_frame
+        });
+
+        match reached || matches!(until, RunUntil::Cycle(_)) || self.cpu.jammed {
+            true => Ok(result),
+            false => Err(io::Error::new(io::ErrorKind::TimedOut, format!("No {:?} within {} frames", until, RUN_UNTIL_FRAMES))),
+        }
+    }
+
+    // Runs to the next instruction boundary, returning how many CPU cycles that took
+    pub fn step_instruction(&mut self) -> u64 {
+        let start = self.cpu_cycles();
+        loop {
+            self.cycle();
+            if self.cpu.at_instruction_boundary() {
+                return self.cpu_cycles() - start;
+            }
+        }
+    }
+
+    // A CPU cycle at a time until `done`, or until the CPU jams since nothing would ever change after that
+    fn run(&mut self, mut done: impl FnMut(&mut ConsoleSystem) -> bool) -> FrameResult {
+        let start = self.cpu_cycles();
+        let mut samples = Vec::new();
+        self.input().polled = false;
+
+        loop {
+            self.cycle();
+            samples.append(&mut self.cpu.mapper.get_alu().samples);
+            if done(self) || (self.cpu.jammed && self.cpu.at_instruction_boundary()) {
+                break;
+            }
+        }
+
+        let lag = !self.input().polled;
+        FrameResult {
+            framebuffer: self.cpu.mapper.get_ppu().framebuffer.clone(),
+            samples,
+            lag,
+            cycles: self.cpu_cycles() - start,
+        }
+    }
+
+    // Called after every PPU dot when any hooks are registered
+    pub(super) fn emit_events(&mut self) {
+        let ppu = self.cpu.mapper.get_ppu();
+        let (scanline, dot, frame) = (ppu.scanline, ppu.dot, ppu.frame);
+        let vblank_scanline = ppu.region.profile().vblank_scanline();
+
+        if dot == 0 {
+            if scanline == 0 {
+                self.emit(SystemEvent::FrameStart(frame));
+            }
+            self.emit(SystemEvent::Scanline(scanline));
+        } else if dot == 1 && scanline == vblank_scanline {
+            self.emit(SystemEvent::Vblank(frame));
+        }
+    }
+
+    fn emit(&mut self, event: SystemEvent) {
+        // Out of the way while they run so they can have the whole system, anything they add themselves goes on the end
+        let mut hooks = mem::take(&mut self.hooks);
+        for hook in hooks.iter_mut() {
+            hook.event(self, event);
+        }
+        hooks.append(&mut self.hooks);
+        self.hooks = hooks;
+    }
+}
@@ -0,0 +1,122 @@
+use std::io;
+
+use crate::input::{Port, StandardController};
+use crate::movie::{Desync, Movie, MovieFrame, MovieMode, MovieSession};
+
+use super::ConsoleSystem;
+
+impl ConsoleSystem {
+    // Records from here, with the current state as the movie's start. Call it straight after power on and reset to
+    // record from power on instead.
+    pub fn record_movie(&mut self, from_state: bool) {
+        let movie = match from_state {
+            true => Movie::from_state(self.save_state()),
+            false => Movie::new(),
+        };
+        let start = self.cpu.mapper.get_ppu().frame;
+        self.movie = Some(MovieSession::new(movie, MovieMode::Recording, start));
+    }
+
+    // Loads the movie's start state if it has one, otherwise it should be a freshly powered on and reset system.
+    // Plays read-only.
+    pub fn play_movie(&mut self, movie: Movie) -> io::Result<()> {
+        if let Some(state) = &movie.start_state {
+            self.load_state(state)?;
+        }
+        let start = self.cpu.mapper.get_ppu().frame;
+        self.movie = Some(MovieSession::new(movie, MovieMode::Playing, start));
+        Ok(())
+    }
+
+    pub fn stop_movie(&mut self) -> Option<MovieSession> {
+        self.movie.take()
+    }
+
+    pub fn movie(&self) -> Option<&MovieSession> {
+        self.movie.as_ref()
+    }
+
+    pub fn movie_mut(&mut self) -> Option<&mut MovieSession> {
+        self.movie.as_mut()
+    }
+
+    // FNV-1a over the 2KB of internal RAM
+    pub fn ram_hash(&self) -> u64 {
+        (0..0x800).fold(0xcbf2_9ce4_8422_2325, |hash, address| {
+            (hash ^ self.cpu.mapper.peek(address) as u64).wrapping_mul(0x0100_0000_01b3)
+        })
+    }
+
+    // Called before every CPU cycle while there's a movie, only does anything on the first one of a frame
+    pub(super) fn advance_movie(&mut self) {
+        let frame = self.cpu.mapper.get_ppu().frame;
+        let Some(mut session) = self.movie.take() else {
+            return;
+        };
+        if session.last_frame == Some(frame) || frame < session.start_frame {
+            self.movie = Some(session);
+            return;
+        }
+
+        let index = (frame - session.start_frame) as usize;
+        let jumped = session.last_frame.is_some() && session.frame() + 1 != index;
+        session.last_frame = Some(frame);
+        if jumped && (session.mode == MovieMode::Recording || !session.read_only) {
+            session.mode = MovieMode::Recording;
+            session.movie.rerecords += 1;
+        }
+        if session.mode == MovieMode::Playing && index >= session.movie.frames.len() {
+            session.mode = if session.read_only { MovieMode::Finished } else { MovieMode::Recording };
+        }
+
+        match session.mode {
+            MovieMode::Playing => {
+                let input = session.movie.frames[index];
+                if input.reset || input.power {
+                    self.reset();
+                }
+                self.set_movie_buttons(input);
+
+                if let Some(expected) = session.movie.ram_hashes.get(&index).copied() {
+                    let actual = self.ram_hash();
+                    if actual != expected {
+                        session.desyncs.push(Desync { frame: index, expected, actual });
+                    }
+                }
+            }
+            MovieMode::Recording => {
+                let movie = &mut session.movie;
+                movie.frames.truncate(index);
+                movie.frames.resize(index, MovieFrame::new());
+                movie.ram_hashes.retain(|hashed, _| *hashed < index);
+
+                let mut input = MovieFrame::new();
+                input.reset = session.reset_requested;
+                session.reset_requested = false;
+                if input.reset {
+                    self.reset();
+                }
+                for (port, buttons) in [Port::One, Port::Two].into_iter().zip(input.ports.iter_mut()) {
+                    if let Some(controller) = self.input().device_mut::<StandardController>(port) {
+                        *buttons = controller.buttons;
+                    }
+                }
+                movie.frames.push(input);
+
+                if index.is_multiple_of(session.hash_interval.max(1)) {
+                    movie.ram_hashes.insert(index, self.ram_hash());
+                }
+            }
+            MovieMode::Finished => {}
+        }
+        self.movie = Some(session);
+    }
+
+    fn set_movie_buttons(&mut self, input: MovieFrame) {
+        for (port, buttons) in [Port::One, Port::Two].into_iter().zip(input.ports) {
+            if let Some(controller) = self.input().device_mut::<StandardController>(port) {
+                controller.buttons = buttons;
+            }
+        }
+    }
+}
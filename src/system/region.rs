@@ -0,0 +1,109 @@
+use crate::ppu::DOTS_PER_SCANLINE;
+use crate::roms::TVSystem;
+
+use super::clock::{NTSC_MASTER_CLOCK, PAL_MASTER_CLOCK};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Region {
+    #[default]
+    Ntsc,
+    Pal,
+    // The famiclone: PAL clocks and frame length with NTSC-like CPU and APU behaviour
+    Dendy,
+}
+
+impl Region {
+    pub fn from_tv_system(tv_system: TVSystem) -> Self {
+        match tv_system {
+            TVSystem::NTSC => Region::Ntsc,
+            TVSystem::PAL => Region::Pal,
+            TVSystem::Dendy => Region::Dendy,
+        }
+    }
+
+    pub fn profile(self) -> &'static RegionProfile {
+        match self {
+            Region::Ntsc => &NTSC,
+            Region::Pal => &PAL,
+            Region::Dendy => &DENDY,
+        }
+    }
+}
+
+// Everything that differs between console regions. Periods are in CPU cycles.
+#[derive(Debug, PartialEq, Eq)]
+pub struct RegionProfile {
+    pub master_clock: u64,
+    pub cpu_divider: u64,
+    pub ppu_divider: u64,
+    pub scanlines_per_frame: u16,
+    // Idle scanlines between the picture and vblank, Dendy sits out 50 more than the others
+    pub post_render_scanlines: u16,
+    pub vblank_scanlines: u16,
+    // The APU's timings, here for its noise, DMC and frame counter to pick up
+    pub noise_periods: [u16; 16],
+    pub dmc_periods: [u16; 16],
+    // When each quarter frame step of the frame counter lands, the fourth ends a 4-step sequence and the fifth a 5-step one
+    pub frame_counter_steps: [u32; 5],
+}
+
+impl RegionProfile {
+    // The scanline vblank gets flagged on, at dot 1
+    pub fn vblank_scanline(&self) -> u16 {
+        240 + self.post_render_scanlines
+    }
+
+    pub fn pre_render_scanline(&self) -> u16 {
+        self.vblank_scanline() + self.vblank_scanlines
+    }
+
+    pub fn cpu_clock(&self) -> f64 {
+        self.master_clock as f64 / self.cpu_divider as f64
+    }
+
+    // Frames per second, ignoring the dot NTSC skips on odd frames
+    pub fn frame_rate(&self) -> f64 {
+        let dots = DOTS_PER_SCANLINE as u64 * self.scanlines_per_frame as u64;
+        self.master_clock as f64 / (self.ppu_divider * dots) as f64
+    }
+}
+
+const NTSC_NOISE_PERIODS: [u16; 16] = [4, 8, 16, 32, 64, 96, 128, 160, 202, 254, 380, 508, 762, 1016, 2034, 4068];
+const NTSC_DMC_PERIODS: [u16; 16] = [428, 380, 340, 320, 286, 254, 226, 214, 190, 160, 142, 128, 106, 84, 72, 54];
+const NTSC_FRAME_COUNTER_STEPS: [u32; 5] = [7457, 14913, 22371, 29829, 37281];
+
+pub const NTSC: RegionProfile = RegionProfile {
+    master_clock: NTSC_MASTER_CLOCK,
+    cpu_divider: 12,
+    ppu_divider: 4,
+    scanlines_per_frame: 262,
+    post_render_scanlines: 1,
+    vblank_scanlines: 20,
+    noise_periods: NTSC_NOISE_PERIODS,
+    dmc_periods: NTSC_DMC_PERIODS,
+    frame_counter_steps: NTSC_FRAME_COUNTER_STEPS,
+};
+
+pub const PAL: RegionProfile = RegionProfile {
+    master_clock: PAL_MASTER_CLOCK,
+    cpu_divider: 16,
+    ppu_divider: 5,
+    scanlines_per_frame: 312,
+    post_render_scanlines: 1,
+    vblank_scanlines: 70,
+    noise_periods: [4, 8, 14, 30, 60, 88, 118, 148, 188, 236, 354, 472, 708, 944, 1890, 3778],
+    dmc_periods: [398, 354, 316, 298, 276, 236, 210, 198, 176, 148, 132, 118, 98, 78, 66, 50],
+    frame_counter_steps: [8313, 16627, 24939, 33253, 41565],
+};
+
+pub const DENDY: RegionProfile = RegionProfile {
+    master_clock: PAL_MASTER_CLOCK,
+    cpu_divider: 15,
+    ppu_divider: 5,
+    scanlines_per_frame: 312,
+    post_render_scanlines: 51,
+    vblank_scanlines: 20,
+    noise_periods: NTSC_NOISE_PERIODS,
+    dmc_periods: NTSC_DMC_PERIODS,
+    frame_counter_steps: NTSC_FRAME_COUNTER_STEPS,
+};
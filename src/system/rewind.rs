@@ -0,0 +1,255 @@
+use std::collections::VecDeque;
+use std::mem;
+
+use crate::input::{Buttons, Port, StandardController};
+
+use super::ConsoleSystem;
+
+// Zero runs shorter than this stay inside a literal run, a new run costs more than the bytes it skips
+const MIN_SKIP: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RewindSettings {
+    // Frames between snapshots, going back to a frame in between replays up to this many
+    pub interval: u64,
+    // Bytes the snapshots and recorded input can take before the oldest are dropped
+    pub budget: usize,
+}
+
+impl RewindSettings {
+    pub fn new() -> Self {
+        Self {
+            interval: 10,
+            budget: 64 * 1024 * 1024,
+        }
+    }
+}
+
+impl Default for RewindSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Snapshots taken at the start of every interval'th frame, plus what the controllers held each frame so the frames in
+// between can be replayed. Only the newest snapshot is kept whole, each older one is stored as the bytes that differ
+// from the one after it, so dropping the oldest never leaves a delta with nothing to apply to.
+pub struct RewindBuffer {
+    pub settings: RewindSettings,
+    // Frame number and save state
+    newest: Option<(u64, Vec<u8>)>,
+    // Oldest first
+    older: VecDeque<(u64, Vec<u8>)>,
+    // Standard controller buttons on both ports, one entry per frame run since the oldest snapshot
+    inputs: VecDeque<(u64, [u8; 2])>,
+}
+
+impl RewindBuffer {
+    pub fn new(settings: RewindSettings) -> Self {
+        Self {
+            settings,
+            newest: None,
+            older: VecDeque::new(),
+            inputs: VecDeque::new(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.newest = None;
+        self.older.clear();
+        self.inputs.clear();
+    }
+
+    pub fn snapshots(&self) -> usize {
+        self.older.len() + self.newest.is_some() as usize
+    }
+
+    // The furthest back a rewind can go
+    pub fn oldest_frame(&self) -> Option<u64> {
+        self.older.front().or(self.newest.as_ref()).map(|(frame, _)| *frame)
+    }
+
+    pub fn memory_used(&self) -> usize {
+        let newest = self.newest.as_ref().map_or(0, |(_, state)| state.len());
+        let older: usize = self.older.iter().map(|(_, delta)| delta.len()).sum();
+        newest + older + self.inputs.len() * mem::size_of::<(u64, [u8; 2])>()
+    }
+
+    fn record(&mut self, frame: u64, buttons: [u8; 2]) {
+        self.inputs.push_back((frame, buttons));
+    }
+
+    fn capture(&mut self, frame: u64, state: Vec<u8>) {
+        if let Some((newest_frame, newest)) = self.newest.take() {
+            self.older.push_back((newest_frame, encode_delta(&newest, &state)));
+        }
+        self.newest = Some((frame, state));
+    }
+
+    // Drops the oldest snapshots until it's back in budget, the newest always stays
+    fn trim(&mut self) {
+        while self.memory_used() > self.settings.budget && !self.older.is_empty() {
+            self.older.pop_front();
+            let oldest = self.oldest_frame().unwrap();
+            while self.inputs.front().is_some_and(|(frame, _)| *frame < oldest) {
+                self.inputs.pop_front();
+            }
+        }
+    }
+
+    // Winds the snapshots back to the newest one at or before `frame`, dropping everything after it
+    fn restore(&mut self, frame: u64) -> Option<(u64, Vec<u8>)> {
+        if self.oldest_frame()? > frame {
+            return None;
+        }
+
+        while self.newest.as_ref().is_some_and(|(newest_frame, _)| *newest_frame > frame) {
+            let (_, newer) = self.newest.take().unwrap();
+            let (older_frame, delta) = self.older.pop_back().unwrap();
+            self.newest = Some((older_frame, decode_delta(&delta, &newer)));
+        }
+        self.newest.clone()
+    }
+
+    // What was recorded from `from` up to but not including `to`
+    fn inputs(&self, from: u64, to: u64) -> Vec<[u8; 2]> {
+        self.inputs.iter().filter(|(frame, _)| (from..to).contains(frame)).map(|(_, buttons)| *buttons).collect()
+    }
+
+    fn forget_inputs_from(&mut self, from: u64) {
+        let start = self.inputs.partition_point(|(frame, _)| *frame < from);
+        self.inputs.truncate(start);
+    }
+}
+
+// Older XOR newer as runs of (bytes to skip, byte count, bytes), after the older state's length
+fn encode_delta(older: &[u8], newer: &[u8]) -> Vec<u8> {
+    let difference: Vec<u8> = older.iter().enumerate().map(|(i, byte)| byte ^ newer.get(i).copied().unwrap_or(0)).collect();
+    let mut delta = (older.len() as u32).to_le_bytes().to_vec();
+
+    let mut position = 0;
+    while position < difference.len() {
+        let start = match difference[position..].iter().position(|byte| *byte != 0) {
+            Some(offset) => position + offset,
+            None => break,
+        };
+        // Runs end at the first stretch of MIN_SKIP zeroes, or the end
+        let mut end = start;
+        let mut zeroes = 0;
+        while end + zeroes < difference.len() && zeroes < MIN_SKIP {
+            match difference[end + zeroes] {
+                0 => zeroes += 1,
+                _ => {
+                    end += zeroes + 1;
+                    zeroes = 0;
+                }
+            }
+        }
+
+        delta.extend(((start - position) as u32).to_le_bytes());
+        delta.extend(((end - start) as u32).to_le_bytes());
+        delta.extend(&difference[start..end]);
+        position = end;
+    }
+    delta
+}
+
+fn decode_delta(delta: &[u8], newer: &[u8]) -> Vec<u8> {
+    let read = |at: usize| u32::from_le_bytes(delta[at..at + 4].try_into().unwrap()) as usize;
+    let mut older = newer.to_vec();
+    older.resize(read(0), 0);
+
+    let (mut at, mut position) = (4, 0);
+    while at < delta.len() {
+        position += read(at);
+        let count = read(at + 4);
+        at += 8;
+        for (byte, difference) in older[position..position + count].iter_mut().zip(&delta[at..at + count]) {
+            *byte ^= difference;
+        }
+        at += count;
+        position += count;
+    }
+    older
+}
+
+impl ConsoleSystem {
+    pub fn enable_rewind(&mut self, settings: RewindSettings) {
+        self.rewind = Some(RewindBuffer::new(settings));
+    }
+
+    pub fn disable_rewind(&mut self) {
+        self.rewind = None;
+    }
+
+    pub fn rewind_buffer(&self) -> Option<&RewindBuffer> {
+        self.rewind.as_ref()
+    }
+
+    // The snapshots are another timeline's once a state is loaded or the console reset. rewind_frame holds the buffer
+    // while it loads its own snapshots, so those don't come through here.
+    pub(super) fn forget_rewind(&mut self) {
+        if let Some(buffer) = self.rewind.as_mut() {
+            buffer.clear();
+        }
+    }
+
+    // Called by run_frame before it runs anything, with the frame's input already set
+    pub(super) fn record_rewind(&mut self) {
+        let Some(mut buffer) = self.rewind.take() else {
+            return;
+        };
+
+        let frame = self.cpu.mapper.get_ppu().frame;
+        // Anything recorded past here is a future that a rewind threw away
+        buffer.forget_inputs_from(frame);
+        buffer.record(frame, [self.controller_buttons(Port::One), self.controller_buttons(Port::Two)]);
+        let captured = buffer.newest.as_ref().is_some_and(|(newest, _)| *newest >= frame);
+        if frame.is_multiple_of(buffer.settings.interval.max(1)) && !captured {
+            buffer.capture(frame, self.save_state());
+        }
+        buffer.trim();
+        self.rewind = Some(buffer);
+    }
+
+    // Goes back to the start of the previous frame, false when that's further back than the buffer goes. The frames
+    // since the snapshot are replayed with the input they had the first time, without hooks or sound.
+    pub fn rewind_frame(&mut self) -> bool {
+        let Some(mut buffer) = self.rewind.take() else {
+            return false;
+        };
+
+        let target = self.cpu.mapper.get_ppu().frame.checked_sub(1);
+        let restored = target.and_then(|target| buffer.restore(target).map(|snapshot| (target, snapshot)));
+        let Some((target, (frame, state))) = restored else {
+            self.rewind = Some(buffer);
+            return false;
+        };
+
+        self.load_state(&state).expect("Rewind snapshots come from save_state");
+        let inputs = buffer.inputs(frame, target);
+        buffer.forget_inputs_from(target);
+        // The movie notices the jump the next time it steps, and doesn't see the replay
+        let hooks = mem::take(&mut self.hooks);
+        let movie = self.movie.take();
+        for [port1, port2] in inputs {
+            self.set_controller_buttons(Port::One, port1);
+            self.set_controller_buttons(Port::Two, port2);
+            self.run_frame();
+        }
+        self.hooks = hooks;
+        self.movie = movie;
+        self.rewind = Some(buffer);
+        true
+    }
+
+    fn controller_buttons(&mut self, port: Port) -> u8 {
+        self.input().device_mut::<StandardController>(port).map_or(0, |controller| controller.buttons.bits())
+    }
+
+    fn set_controller_buttons(&mut self, port: Port, buttons: u8) {
+        if let Some(controller) = self.input().device_mut::<StandardController>(port) {
+            controller.buttons = Buttons::from_bits_truncate(buttons);
+        }
+    }
+}
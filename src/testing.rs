@@ -0,0 +1,7 @@
+mod blargg;
+mod manifest;
+mod nestest;
+
+pub use self::blargg::{BlarggHarness, BlarggResult, RESET_DELAY_CYCLES, STATUS_RESET_REQUEST, STATUS_RUNNING};
+pub use self::manifest::{EntryResult, Manifest, ManifestEntry, Outcome, PassCriteria, Status, SuiteReport};
+pub use self::nestest::{TraceComparison, TraceDivergence, TraceLine};
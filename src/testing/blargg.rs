@@ -0,0 +1,128 @@
+use std::io;
+
+use crate::system::ConsoleSystem;
+
+// blargg's test ROMs report through cartridge RAM: $6000 holds the status, $6001-$6003 a signature that says the rest is
+// valid, and $6004 onwards a NUL-terminated text message
+const STATUS_ADDRESS: u16 = 0x6000;
+const SIGNATURE_ADDRESS: u16 = 0x6001;
+const SIGNATURE: [u8; 3] = [0xde, 0xb0, 0x61];
+const MESSAGE_ADDRESS: u16 = 0x6004;
+const MESSAGE_END: u16 = 0x7fff;
+
+pub const STATUS_RUNNING: u8 = 0x80;
+pub const STATUS_RESET_REQUEST: u8 = 0x81;
+
+// The ROMs ask for reset to be held off for at least 100ms after they request it
+pub const RESET_DELAY_CYCLES: u64 = 1_789_773 / 10;
+
+const DEFAULT_TIMEOUT_CYCLES: u64 = 1_789_773 * 60;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlarggResult {
+    // 0 is a pass, anything else is the ROM's own failure code
+    pub code: u8,
+    pub message: String,
+    pub cycles: u64,
+    pub resets: u32,
+}
+
+impl BlarggResult {
+    pub fn passed(&self) -> bool {
+        self.code == 0
+    }
+}
+
+pub struct BlarggHarness {
+    pub timeout_cycles: u64,
+    pub reset_delay_cycles: u64,
+}
+
+impl BlarggHarness {
+    pub fn new() -> Self {
+        Self {
+            timeout_cycles: DEFAULT_TIMEOUT_CYCLES,
+            reset_delay_cycles: RESET_DELAY_CYCLES,
+        }
+    }
+
+    // Runs an already reset system until the ROM reports a result
+    pub fn run(&self, system: &mut ConsoleSystem) -> io::Result<BlarggResult> {
+        let mut cycles = 0u64;
+        let mut resets = 0;
+        let mut last_status = None;
+        let mut started = false;
+        let mut reset_at = None;
+
+        while cycles < self.timeout_cycles {
+            system.cycle();
+            cycles += 1;
+
+            // Results are only looked at between instructions so a half finished store isn't mistaken for one
+            if !system.cpu.at_instruction_boundary() {
+                continue;
+            }
+
+            if reset_at.is_some_and(|at| cycles >= at) {
+                system.reset();
+                resets += 1;
+                reset_at = None;
+                continue;
+            }
+
+            if !has_signature(system) {
+                continue;
+            }
+
+            let status = system.cpu.mapper.peek(STATUS_ADDRESS);
+            match status {
+                STATUS_RUNNING => started = true,
+                // Only act on the request once, $6000 keeps holding it until the ROM starts over
+                STATUS_RESET_REQUEST if last_status != Some(STATUS_RESET_REQUEST) => {
+                    started = true;
+                    reset_at = Some(cycles + self.reset_delay_cycles);
+                }
+                STATUS_RESET_REQUEST => {}
+                // The signature can go in before the ROM has marked itself as running, so wait for that first
+                code if started && code < STATUS_RUNNING => {
+                    return Ok(BlarggResult {
+                        code,
+                        message: read_message(system),
+                        cycles,
+                        resets,
+                    });
+                }
+                _ => {}
+            }
+            last_status = Some(status);
+        }
+
+        let message = match last_status {
+            Some(status) => format!("Test ROM timed out with status ${:02X}", status),
+            None => "Test ROM never wrote the $6000 signature".to_string(),
+        };
+        Err(io::Error::new(io::ErrorKind::TimedOut, message))
+    }
+}
+
+impl Default for BlarggHarness {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn has_signature(system: &mut ConsoleSystem) -> bool {
+    SIGNATURE
+        .iter()
+        .enumerate()
+        .all(|(i, byte)| system.cpu.mapper.peek(SIGNATURE_ADDRESS + i as u16) == *byte)
+}
+
+fn read_message(system: &mut ConsoleSystem) -> String {
+    let bytes: Vec<u8> = (MESSAGE_ADDRESS..=MESSAGE_END)
+        .map(|address| system.cpu.mapper.peek(address))
+        .take_while(|byte| *byte != 0)
+        .collect();
+
+    String::from_utf8_lossy(&bytes).trim_end().to_string()
+}
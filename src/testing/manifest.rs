@@ -0,0 +1,267 @@
+use std::any::Any;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs::{self, File};
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::roms::{Mappers, RomImage};
+use crate::system::ConsoleSystem;
+
+use super::blargg::BlarggHarness;
+
+// Close enough to an NTSC frame of CPU time for turning frame timeouts into cycles
+const CPU_CYCLES_PER_FRAME: u64 = 29_781;
+const DEFAULT_TIMEOUT_FRAMES: u64 = 600;
+
+#[derive(Debug, Deserialize)]
+pub struct Manifest {
+    #[serde(rename = "test", default)]
+    pub tests: Vec<ManifestEntry>,
+    // Directory ROM paths are relative to, set from the manifest's own location when loaded from a file
+    #[serde(skip)]
+    pub root: PathBuf,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ManifestEntry {
+    pub rom: PathBuf,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default = "default_subsystem")]
+    pub subsystem: String,
+    pub pass: PassCriteria,
+    #[serde(default = "default_timeout_frames")]
+    pub frames: u64,
+    // Known to fail today; it's reported but doesn't count against the run
+    #[serde(default)]
+    pub expected_failure: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PassCriteria {
+    // $6000 status protocol, pass on result code 0
+    Blargg,
+    // FNV-1a of the framebuffer once the frame timeout is reached, as printed by the headless runner
+    FramebufferHash(String),
+    // Pass as soon as the byte at `address` holds `value`
+    Ram { address: u16, value: u8 },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Passed,
+    Failed(String),
+    // The ROM file isn't there, the suite ROMs aren't checked in
+    Missing,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Status {
+    Pass,
+    Fail,
+    ExpectedFailure,
+    UnexpectedPass,
+    Missing,
+}
+
+#[derive(Debug, Clone)]
+pub struct EntryResult {
+    pub name: String,
+    pub subsystem: String,
+    pub outcome: Outcome,
+    pub expected_failure: bool,
+}
+
+impl EntryResult {
+    pub fn status(&self) -> Status {
+        match (&self.outcome, self.expected_failure) {
+            (Outcome::Missing, _) => Status::Missing,
+            (Outcome::Passed, false) => Status::Pass,
+            (Outcome::Passed, true) => Status::UnexpectedPass,
+            (Outcome::Failed(_), false) => Status::Fail,
+            (Outcome::Failed(_), true) => Status::ExpectedFailure,
+        }
+    }
+}
+
+pub struct SuiteReport {
+    pub results: Vec<EntryResult>,
+}
+
+impl Manifest {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let mut manifest = Self::parse(&fs::read_to_string(path)?)?;
+        manifest.root = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        Ok(manifest)
+    }
+
+    pub fn parse(text: &str) -> io::Result<Self> {
+        toml::from_str(text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    pub fn run(&self) -> SuiteReport {
+        SuiteReport {
+            results: self.tests.iter().map(|entry| entry.run(&self.root)).collect(),
+        }
+    }
+}
+
+impl ManifestEntry {
+    pub fn display_name(&self) -> String {
+        self.name.clone().unwrap_or_else(|| self.rom.display().to_string())
+    }
+
+    pub fn run(&self, root: &Path) -> EntryResult {
+        let outcome = match File::open(root.join(&self.rom)) {
+            // A ROM that crashes the emulator fails its own entry rather than taking the rest of the suite down with it
+            Ok(mut file) => match panic::catch_unwind(AssertUnwindSafe(|| self.run_rom(&mut file))) {
+                Ok(result) => result.unwrap_or_else(|e| Outcome::Failed(e.to_string())),
+                Err(panic) => Outcome::Failed(format!("crashed: {}", panic_message(panic.as_ref()))),
+            },
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Outcome::Missing,
+            Err(e) => Outcome::Failed(e.to_string()),
+        };
+
+        EntryResult {
+            name: self.display_name(),
+            subsystem: self.subsystem.clone(),
+            outcome,
+            expected_failure: self.expected_failure,
+        }
+    }
+
+    fn run_rom(&self, file: &mut File) -> io::Result<Outcome> {
+        let image = RomImage::from(file)?;
+        if let Err(e) = Mappers::check(&image.header) {
+            return Ok(Outcome::Failed(e.to_string()));
+        }
+        let mut system = ConsoleSystem::new(image);
+        system.reset();
+
+        Ok(match &self.pass {
+            PassCriteria::Blargg => {
+                let harness = BlarggHarness {
+                    timeout_cycles: self.frames * CPU_CYCLES_PER_FRAME,
+                    ..BlarggHarness::new()
+                };
+                let result = harness.run(&mut system)?;
+                match result.passed() {
+                    true => Outcome::Passed,
+                    false => Outcome::Failed(format!("${:02X}: {}", result.code, result.message)),
+                }
+            }
+            PassCriteria::FramebufferHash(expected) => {
+                run_frames(&mut system, self.frames, |_| false);
+                let hash = format!("{:016x}", system.cpu.mapper.get_ppu().framebuffer_hash());
+                match hash.eq_ignore_ascii_case(expected) {
+                    true => Outcome::Passed,
+                    false => Outcome::Failed(format!("framebuffer hash {}, expected {}", hash, expected)),
+                }
+            }
+            PassCriteria::Ram { address, value } => {
+                match run_frames(&mut system, self.frames, |system| system.cpu.mapper.peek(*address) == *value) {
+                    true => Outcome::Passed,
+                    false => Outcome::Failed(format!(
+                        "${:04X} is ${:02X} after {} frames, expected ${:02X}",
+                        address,
+                        system.cpu.mapper.peek(*address),
+                        self.frames,
+                        value
+                    )),
+                }
+            }
+        })
+    }
+}
+
+// Runs up to `frames` frames, checking `done` between instructions. Returns whether it stopped because of `done`.
+fn run_frames(system: &mut ConsoleSystem, frames: u64, mut done: impl FnMut(&mut ConsoleSystem) -> bool) -> bool {
+    let end = system.cpu.mapper.get_ppu().frame + frames;
+    while system.cpu.mapper.get_ppu().frame < end {
+        system.cycle();
+        if system.cpu.at_instruction_boundary() && done(system) {
+            return true;
+        }
+    }
+    false
+}
+
+fn panic_message(panic: &(dyn Any + Send)) -> &str {
+    match (panic.downcast_ref::<&str>(), panic.downcast_ref::<String>()) {
+        (Some(message), _) => message,
+        (_, Some(message)) => message,
+        _ => "unknown panic",
+    }
+}
+
+impl SuiteReport {
+    // Any result that should make a CI run go red
+    pub fn unexpected(&self) -> impl Iterator<Item = &EntryResult> {
+        self.results
+            .iter()
+            .filter(|result| matches!(result.status(), Status::Fail | Status::UnexpectedPass))
+    }
+
+    // Per-subsystem counts followed by every individual result
+    pub fn matrix(&self) -> String {
+        let mut subsystems: BTreeMap<&str, BTreeMap<Status, usize>> = BTreeMap::new();
+        for result in &self.results {
+            *subsystems.entry(&result.subsystem).or_default().entry(result.status()).or_default() += 1;
+        }
+
+        let columns = [
+            (Status::Pass, "pass"),
+            (Status::Fail, "fail"),
+            (Status::ExpectedFailure, "xfail"),
+            (Status::UnexpectedPass, "xpass"),
+            (Status::Missing, "missing"),
+        ];
+
+        let mut report = String::new();
+        let _ = write!(report, "| subsystem |");
+        for (_, name) in columns {
+            let _ = write!(report, " {} |", name);
+        }
+        let _ = writeln!(report, " passing |");
+        let _ = writeln!(report, "|---|{}---|", "---|".repeat(columns.len()));
+
+        for (subsystem, counts) in &subsystems {
+            let count = |status| counts.get(&status).copied().unwrap_or(0);
+            let _ = write!(report, "| {} |", subsystem);
+            for (status, _) in columns {
+                let _ = write!(report, " {} |", count(status));
+            }
+            let run: usize = counts.iter().filter(|(status, _)| **status != Status::Missing).map(|(_, n)| n).sum();
+            let passing = count(Status::Pass) + count(Status::UnexpectedPass);
+            let _ = writeln!(report, " {}/{} |", passing, run);
+        }
+
+        let _ = writeln!(report);
+        for result in &self.results {
+            let label = columns.iter().find(|(status, _)| *status == result.status()).map_or("", |(_, name)| name);
+            match &result.outcome {
+                Outcome::Failed(reason) => {
+                    let _ = writeln!(report, "{:8} {} ({}): {}", label, result.name, result.subsystem, reason);
+                }
+                _ => {
+                    let _ = writeln!(report, "{:8} {} ({})", label, result.name, result.subsystem);
+                }
+            }
+        }
+
+        report
+    }
+}
+
+fn default_subsystem() -> String {
+    "misc".to_string()
+}
+
+fn default_timeout_frames() -> u64 {
+    DEFAULT_TIMEOUT_FRAMES
+}
@@ -0,0 +1,246 @@
+use std::fmt;
+use std::io;
+
+use crate::ppu::{DOTS_PER_SCANLINE, SCANLINES_PER_FRAME};
+use crate::system::ConsoleSystem;
+
+// Stop instead of spinning forever if an instruction never retires
+const MAX_INSTRUCTION_CYCLES: u32 = 16;
+
+// One instruction of a reference trace, e.g. from nestest.log:
+// C000  4C F5 C5  JMP $C5F5                       A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 21 CYC:7
+// or the older layout where CYC is the PPU dot and SL the scanline, -1 being the pre-render line:
+// C000  4C F5 C5  JMP $C5F5                       A:00 X:00 Y:00 P:24 SP:FD CYC:  0 SL:241
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceLine {
+    pub pc: u16,
+    pub bytes: Vec<u8>,
+    pub a: u8,
+    pub x: u8,
+    pub y: u8,
+    pub p: u8,
+    pub s: u8,
+    // (scanline, dot), missing from the oldest logs
+    pub ppu: Option<(u16, u16)>,
+    // CPU cycles, missing from logs with the SL: layout
+    pub cycle: Option<u64>,
+}
+
+impl TraceLine {
+    pub fn parse(line: &str) -> Option<Self> {
+        let pc = u16::from_str_radix(line.get(0..4)?, 16).ok()?;
+        let bytes = line
+            .get(6..15)?
+            .split_whitespace()
+            .map(|byte| u8::from_str_radix(byte, 16).ok())
+            .collect::<Option<Vec<_>>>()?;
+
+        // The disassembly can be anything, so registers are found from the end of the line
+        let registers = &line[line.rfind(" A:")?..];
+        let hex = |key: &str| u8::from_str_radix(field(registers, key)?, 16).ok();
+
+        let (ppu, cycle) = match field(registers, "SL:") {
+            Some(scanline) => {
+                let scanline: i32 = scanline.parse().ok()?;
+                let scanline = scanline.rem_euclid(SCANLINES_PER_FRAME as i32) as u16;
+                (Some((scanline, field(registers, "CYC:")?.parse().ok()?)), None)
+            }
+            None => {
+                let ppu = registers.find("PPU:").and_then(|start| {
+                    let rest = &registers[start + 4..];
+                    let rest = &rest[..rest.find("CYC:").unwrap_or(rest.len())];
+                    let (scanline, dot) = rest.split_once(',')?;
+                    Some((scanline.trim().parse().ok()?, dot.trim().parse().ok()?))
+                });
+                (ppu, Some(field(registers, "CYC:")?.parse().ok()?))
+            }
+        };
+
+        Some(Self {
+            pc,
+            bytes,
+            a: hex(" A:")?,
+            x: hex("X:")?,
+            y: hex("Y:")?,
+            p: hex("P:")?,
+            s: hex("SP:")?,
+            ppu,
+            cycle,
+        })
+    }
+
+    // Captures the emulator at an instruction boundary, reading as many opcode bytes as `length`
+    pub fn capture(system: &mut ConsoleSystem, length: usize) -> Self {
+        let pc = system.cpu.pc;
+        let bytes = (0..length as u16).map(|i| system.cpu.mapper.peek(pc.wrapping_add(i))).collect();
+        let ppu = system.cpu.mapper.get_ppu();
+
+        Self {
+            pc,
+            bytes,
+            a: system.cpu.a,
+            x: system.cpu.x,
+            y: system.cpu.y,
+            p: system.cpu.p.bits(),
+            s: system.cpu.s,
+            ppu: Some((ppu.scanline, ppu.dot)),
+            cycle: Some(system.cpu.cycle as u64),
+        }
+    }
+}
+
+impl fmt::Display for TraceLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bytes: Vec<String> = self.bytes.iter().map(|byte| format!("{:02X}", byte)).collect();
+        write!(
+            f,
+            "{:04X}  {:8}  A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X}",
+            self.pc,
+            bytes.join(" "),
+            self.a,
+            self.x,
+            self.y,
+            self.p,
+            self.s
+        )?;
+        if let Some((scanline, dot)) = self.ppu {
+            write!(f, " PPU:{:3},{:3}", scanline, dot)?;
+        }
+        match self.cycle {
+            Some(cycle) => write!(f, " CYC:{}", cycle),
+            None => Ok(()),
+        }
+    }
+}
+
+fn field<'a>(text: &'a str, key: &str) -> Option<&'a str> {
+    let start = text.find(key)? + key.len();
+    text[start..].split_whitespace().next()
+}
+
+#[derive(Debug)]
+pub struct TraceDivergence {
+    // 1-based line in the reference log
+    pub line: usize,
+    pub field: &'static str,
+    pub expected: TraceLine,
+    pub actual: TraceLine,
+    // Reference lines leading up to the divergence, which all matched
+    pub context: Vec<String>,
+}
+
+impl fmt::Display for TraceDivergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Trace diverges at line {} ({}):", self.line, self.field)?;
+        for line in &self.context {
+            writeln!(f, "           {}", line)?;
+        }
+        writeln!(f, "  expected {}", self.expected)?;
+        write!(f, "  actual   {}", self.actual)
+    }
+}
+
+pub struct TraceComparison {
+    pub context: usize,
+    pub check_ppu: bool,
+    pub check_cycles: bool,
+}
+
+impl TraceComparison {
+    pub fn new() -> Self {
+        Self {
+            context: 5,
+            check_ppu: true,
+            check_cycles: true,
+        }
+    }
+
+    // Steps `system` one instruction per reference line, starting from wherever it is now. Cycle and PPU counts are
+    // compared relative to the first line so the reference can start partway into a run. Returns the number of
+    // matching lines.
+    pub fn run(&self, system: &mut ConsoleSystem, log: &str) -> io::Result<Result<usize, TraceDivergence>> {
+        let mut context: Vec<String> = Vec::with_capacity(self.context + 1);
+        let mut base: Option<(TraceLine, TraceLine)> = None;
+        let mut matched = 0;
+
+        for (number, text) in log.lines().enumerate() {
+            if text.trim().is_empty() {
+                continue;
+            }
+
+            let expected = TraceLine::parse(text).ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidData, format!("Couldn't parse trace line {}", number + 1))
+            })?;
+
+            if matched > 0 && !step_instruction(system) {
+                return Err(io::Error::other("Instruction never finished"));
+            }
+
+            let actual = TraceLine::capture(system, expected.bytes.len());
+            let (expected_base, actual_base) = base.get_or_insert_with(|| (expected.clone(), actual.clone()));
+
+            if let Some(field) = self.first_difference(&expected, &actual, expected_base, actual_base) {
+                return Ok(Err(TraceDivergence {
+                    line: number + 1,
+                    field,
+                    expected,
+                    actual,
+                    context,
+                }));
+            }
+
+            if self.context > 0 {
+                if context.len() == self.context {
+                    context.remove(0);
+                }
+                context.push(text.to_string());
+            }
+            matched += 1;
+        }
+
+        Ok(Ok(matched))
+    }
+
+    fn first_difference(&self, expected: &TraceLine, actual: &TraceLine, expected_base: &TraceLine, actual_base: &TraceLine) -> Option<&'static str> {
+        let checks = [
+            ("PC", expected.pc == actual.pc),
+            ("opcode bytes", expected.bytes == actual.bytes),
+            ("A", expected.a == actual.a),
+            ("X", expected.x == actual.x),
+            ("Y", expected.y == actual.y),
+            ("P", expected.p == actual.p),
+            ("SP", expected.s == actual.s),
+            ("PPU dot", !self.check_ppu || expected.ppu.is_none() || dots(expected, expected_base) == dots(actual, actual_base)),
+            ("CYC", !self.check_cycles || expected.cycle.is_none() || cycles(expected, expected_base) == cycles(actual, actual_base)),
+        ];
+
+        checks.iter().find(|(_, same)| !same).map(|(field, _)| *field)
+    }
+}
+
+impl Default for TraceComparison {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// PPU dots since the first line, within a frame since the logs don't say which frame they're on
+fn dots(line: &TraceLine, base: &TraceLine) -> Option<i64> {
+    let position = |(scanline, dot): (u16, u16)| scanline as i64 * DOTS_PER_SCANLINE as i64 + dot as i64;
+    let frame = DOTS_PER_SCANLINE as i64 * SCANLINES_PER_FRAME as i64;
+    Some((position(line.ppu?) - position(base.ppu?)).rem_euclid(frame))
+}
+
+fn cycles(line: &TraceLine, base: &TraceLine) -> Option<i64> {
+    Some(line.cycle? as i64 - base.cycle? as i64)
+}
+
+fn step_instruction(system: &mut ConsoleSystem) -> bool {
+    for _ in 0..MAX_INSTRUCTION_CYCLES {
+        system.cycle();
+        if system.cpu.at_instruction_boundary() {
+            return true;
+        }
+    }
+    false
+}
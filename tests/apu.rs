@@ -0,0 +1,29 @@
+mod common;
+
+use nes::apu::{Alu2A03, MAX_SAMPLES, SAMPLE_RATE};
+
+#[test]
+fn sample_rate_test() {
+    let mut system = common::program_system(common::LOOP_PROGRAM);
+
+    // A tenth of a second of NTSC CPU time
+    for _ in 0..178_977 {
+        system.cycle();
+    }
+
+    let samples = system.cpu.mapper.get_alu().samples.len() as u32;
+    assert!(samples.abs_diff(SAMPLE_RATE / 10) <= 1, "got {} samples", samples);
+}
+
+#[test]
+fn undrained_samples_are_capped_test() {
+    let mut alu = Alu2A03::new();
+
+    // Three seconds of NTSC CPU time without anyone taking the samples
+    for _ in 0..5_369_318 {
+        alu.cycle();
+    }
+
+    assert!(alu.samples.len() <= MAX_SAMPLES, "got {} samples", alu.samples.len());
+    assert!(alu.samples.len() >= MAX_SAMPLES / 2);
+}
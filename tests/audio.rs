@@ -0,0 +1,112 @@
+mod common;
+
+use std::fs;
+
+use nes::{
+    apu::SAMPLE_RATE,
+    audio::{AudioOutput, AudioSink, DynamicRateControl, NullSink, WavSink, MAX_RATE_ADJUSTMENT},
+};
+
+const OUTPUT_RATE: u32 = 48_000;
+// A 60Hz frame's worth of APU samples, and what the sound card plays in that time
+const FRAME_SAMPLES: usize = 735;
+const PLAYED_PER_FRAME: usize = 800;
+
+#[test]
+fn resample_test() {
+    let mut rate_control = DynamicRateControl::new(SAMPLE_RATE, OUTPUT_RATE);
+    let input = vec![0.5; SAMPLE_RATE as usize];
+    let output = rate_control.resample(&input, None);
+    assert!(output.len().abs_diff(OUTPUT_RATE as usize) <= 1, "{} samples", output.len());
+    // Everything after the ramp up from silence is the input level
+    assert!(output[2..].iter().all(|sample| (sample - 0.5).abs() < 1e-6));
+
+    // A ramp stays a ramp
+    let mut rate_control = DynamicRateControl::new(100, 200);
+    let output = rate_control.resample(&[0.0, 1.0, 2.0, 3.0], None);
+    assert_eq!(output, [0.0, 0.0, 0.0, 0.5, 1.0, 1.5, 2.0, 2.5]);
+}
+
+#[test]
+fn ratio_test() {
+    let mut rate_control = DynamicRateControl::new(SAMPLE_RATE, OUTPUT_RATE);
+    let nominal = rate_control.nominal_ratio();
+
+    rate_control.resample(&[], Some(0.5));
+    assert!((rate_control.ratio - nominal).abs() < 1e-9);
+    // Emptying buffers get more samples, filling ones fewer, never by more than the maximum
+    rate_control.resample(&[], Some(0.0));
+    assert!((rate_control.ratio - nominal * (1.0 + MAX_RATE_ADJUSTMENT)).abs() < 1e-9);
+    rate_control.resample(&[], Some(2.0));
+    assert!((rate_control.ratio - nominal * (1.0 - MAX_RATE_ADJUSTMENT)).abs() < 1e-9);
+}
+
+// Frames at the emulator's pace against a sound card playing `played` samples per frame
+fn run_frames(output: &mut AudioOutput, frames: usize, played: usize) -> Vec<usize> {
+    let mut queued = Vec::new();
+    for _ in 0..frames {
+        output.push(&[0.0; FRAME_SAMPLES]);
+        let sink = output.sink_mut::<NullSink>().unwrap();
+        sink.play(played);
+        queued.push(sink.queued());
+    }
+    queued
+}
+
+#[test]
+fn rate_control_test() {
+    let capacity = OUTPUT_RATE as usize / 10;
+
+    // The display running a touch fast would overflow the buffer at a fixed ratio
+    let mut output = AudioOutput::new(Box::new(NullSink::with_capacity(OUTPUT_RATE, capacity)));
+    output.rate_control.max_adjustment = 0.0;
+    run_frames(&mut output, 3000, PLAYED_PER_FRAME - 2);
+    assert!(output.sink_mut::<NullSink>().unwrap().dropped > 0);
+
+    // With the ratio following the fill it settles part way up instead
+    let mut output = AudioOutput::new(Box::new(NullSink::with_capacity(OUTPUT_RATE, capacity)));
+    let queued = run_frames(&mut output, 3000, PLAYED_PER_FRAME - 2);
+    assert_eq!(output.sink_mut::<NullSink>().unwrap().dropped, 0);
+    let settled = &queued[2000..];
+    assert!(settled.iter().all(|queued| (capacity / 2..capacity).contains(queued)), "{:?}", &settled[..10]);
+
+    // And running slow it doesn't run dry
+    let mut output = AudioOutput::new(Box::new(NullSink::with_capacity(OUTPUT_RATE, capacity)));
+    let queued = run_frames(&mut output, 3000, PLAYED_PER_FRAME + 2);
+    assert!(queued[2000..].iter().all(|queued| (1..capacity / 2).contains(queued)));
+}
+
+#[test]
+fn wav_sink_test() {
+    let mut output = AudioOutput::new(Box::new(WavSink::new(SAMPLE_RATE)));
+    assert_eq!(output.fill(), None);
+    output.push(&[0.0, 1.0, -1.0, 0.5]);
+
+    let sink = output.sink_mut::<WavSink>().unwrap();
+    // Same rate and nothing to keep in step with, so samples go through one for one, a sample late from interpolation
+    assert_eq!(sink.samples, [0.0, 0.0, 1.0, -1.0]);
+
+    let path = std::env::temp_dir().join(format!("nes_audio_test_{}.wav", std::process::id()));
+    sink.save(&path).unwrap();
+    let wav = fs::read(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(&wav[0..4], b"RIFF");
+    assert_eq!(wav.len(), 44 + 8);
+    assert_eq!(u32::from_le_bytes(wav[24..28].try_into().unwrap()), SAMPLE_RATE);
+    assert_eq!(i16::from_le_bytes(wav[48..50].try_into().unwrap()), i16::MAX);
+}
+
+#[test]
+fn console_audio_test() {
+    let mut system = common::program_system(common::LOOP_PROGRAM);
+    let mut output = AudioOutput::new(Box::new(NullSink::new(OUTPUT_RATE)));
+    for _ in 0..60 {
+        output.push(&system.run_frame().samples);
+    }
+
+    let sink = output.sink_mut::<NullSink>().unwrap();
+    // A second of NTSC frames is a little over a second
+    let expected = OUTPUT_RATE as f64 * 60.0 / 60.0988;
+    assert!((sink.written as f64 - expected).abs() < 10.0, "{} samples", sink.written);
+    assert_eq!(sink.queued(), 0);
+}
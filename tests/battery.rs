@@ -0,0 +1,53 @@
+mod common;
+
+use std::{env, fs, process};
+
+use nes::{battery::BatterySave, system::ConsoleSystem};
+
+const SAVE_PROGRAM: &[u8] = &[
+    0xa9, 0x42,             // 8000 LDA #$42
+    0x8d, 0x00, 0x60,       // 8002 STA $6000
+    0x8d, 0xff, 0x7f,       // 8005 STA $7FFF
+    0x4c, 0x08, 0x80,       // 8008 JMP $8008
+];
+
+fn run(system: &mut ConsoleSystem) {
+    for _ in 0..100 {
+        system.cycle();
+    }
+}
+
+#[test]
+fn battery_ram_only_exported_with_battery() {
+    let mut system = common::program_system(SAVE_PROGRAM);
+    run(&mut system);
+    assert_eq!(system.battery_ram(), None);
+
+    let mut system = ConsoleSystem::new(common::program_image_with_flags(SAVE_PROGRAM, 0x02));
+    system.reset();
+    run(&mut system);
+    let ram = system.battery_ram().unwrap();
+    assert_eq!(ram.len(), 0x2000);
+    assert_eq!((ram[0], ram[0x1fff]), (0x42, 0x42));
+}
+
+#[test]
+fn battery_save_round_trips_through_file() {
+    let rom_path = env::temp_dir().join(format!("nes-battery-test-{}.nes", process::id()));
+    let mut battery = BatterySave::for_rom(&rom_path);
+    assert_eq!(battery.path, rom_path.with_extension("sav"));
+
+    let mut system = ConsoleSystem::new(common::program_image_with_flags(SAVE_PROGRAM, 0x02));
+    system.reset();
+    assert!(!battery.load(&mut system).unwrap());
+    run(&mut system);
+    assert!(battery.flush(&system).unwrap());
+    // Nothing changed since the last write
+    assert!(!battery.flush(&system).unwrap());
+
+    let mut restored = ConsoleSystem::new(common::program_image_with_flags(&[0x4c, 0x00, 0x80], 0x02));
+    assert!(BatterySave::for_rom(&rom_path).load(&mut restored).unwrap());
+    assert_eq!(restored.cpu.mapper.peek(0x6000), 0x42);
+
+    fs::remove_file(&battery.path).unwrap();
+}
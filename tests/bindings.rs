@@ -0,0 +1,89 @@
+mod common;
+
+use std::path::Path;
+
+use nes::{
+    bindings::{Binding, Hotkey, InputConfig, InputMapper},
+    input::{Buttons, Port, StandardController},
+};
+
+fn key(name: &str) -> Binding {
+    Binding::Key(name.to_string())
+}
+
+#[test]
+fn default_config_test() {
+    // The config shipped with the emulator is the built in one written out
+    assert_eq!(InputConfig::load(Path::new("input.toml")).unwrap(), InputConfig::new());
+    assert_eq!(InputConfig::parse("").unwrap(), InputConfig::new());
+}
+
+#[test]
+fn parse_test() {
+    assert_eq!("Return".parse(), Ok(key("Return")));
+    assert_eq!("Pad1:South".parse(), Ok(Binding::Gamepad(1, "South".to_string())));
+    assert!("PadX:South".parse::<Binding>().is_err());
+    assert!("Pad0".parse::<Binding>().is_err());
+
+    let config = InputConfig::parse("turbo_period = 3\n[hotkeys]\npause = [\"Space\", \"Pad0:Mode\"]\n").unwrap();
+    assert_eq!(config.turbo_period, 3);
+    assert_eq!(config.hotkeys.pause, [key("Space"), Binding::Gamepad(0, "Mode".to_string())]);
+    // The section replaces the default one, the others stay
+    assert!(config.hotkeys.reset.is_empty());
+    assert_eq!(config.port1, InputConfig::new().port1);
+
+    assert!(InputConfig::parse("[port1]\na = [\"Pad:East\"]\n").is_err());
+    assert!(InputConfig::parse("turbo_period = \"fast\"\n").is_err());
+}
+
+#[test]
+fn hotkey_test() {
+    let mut input = InputMapper::new(InputConfig::new());
+    assert_eq!(input.press(key("F5")), [Hotkey::SaveState]);
+    // Key repeat doesn't fire it again
+    assert!(input.press(key("F5")).is_empty());
+    input.release(&key("F5"));
+    assert_eq!(input.press(key("F5")), [Hotkey::SaveState]);
+
+    assert!(!input.is_held(Hotkey::FastForward));
+    input.press(key("Tab"));
+    assert!(input.is_held(Hotkey::FastForward));
+    input.release_all();
+    assert!(!input.is_held(Hotkey::FastForward));
+}
+
+#[test]
+fn buttons_test() {
+    let mut input = InputMapper::new(InputConfig::new());
+    input.press(key("X"));
+    input.press(key("Up"));
+    input.press(Binding::Gamepad(1, "Start".to_string()));
+    assert_eq!(input.buttons(Port::One, 0), Buttons::A | Buttons::UP);
+    assert_eq!(input.buttons(Port::Two, 0), Buttons::START);
+
+    input.release(&key("X"));
+    assert_eq!(input.buttons(Port::One, 0), Buttons::UP);
+}
+
+#[test]
+fn turbo_test() {
+    let mut input = InputMapper::new(InputConfig::new());
+    input.press(key("S"));
+    let pressed: Vec<bool> = (0..8).map(|frame| input.buttons(Port::One, frame).contains(Buttons::A)).collect();
+    assert_eq!(pressed, [true, true, false, false, true, true, false, false]);
+
+    // Holding A as well keeps it down through the turbo's off frames
+    input.press(key("X"));
+    assert!((0..8).all(|frame| input.buttons(Port::One, frame).contains(Buttons::A)));
+}
+
+#[test]
+fn apply_test() {
+    let mut system = common::program_system(common::LOOP_PROGRAM);
+    let mut input = InputMapper::new(InputConfig::new());
+    input.press(key("Return"));
+    input.apply(&mut system);
+
+    let controller = system.input().device_mut::<StandardController>(Port::One).unwrap();
+    assert_eq!(controller.buttons, Buttons::START);
+}
@@ -0,0 +1,53 @@
+mod common;
+
+use nes::testing::BlarggHarness;
+
+// Writes the signature, then a result code and message depending on whether $6010 was set before the last reset
+const RESET_PROGRAM: &[u8] = &[
+    0xad, 0x10, 0x60,       // 8000 LDA $6010
+    0xd0, 0x22,             // 8003 BNE $8027
+    0xa9, 0xde,             // 8005 LDA #$DE
+    0x8d, 0x01, 0x60,       // 8007 STA $6001
+    0xa9, 0xb0,             // 800A LDA #$B0
+    0x8d, 0x02, 0x60,       // 800C STA $6002
+    0xa9, 0x61,             // 800F LDA #$61
+    0x8d, 0x03, 0x60,       // 8011 STA $6003
+    0xa9, 0x80,             // 8014 LDA #$80
+    0x8d, 0x00, 0x60,       // 8016 STA $6000
+    0xa9, 0x01,             // 8019 LDA #$01
+    0x8d, 0x10, 0x60,       // 801B STA $6010
+    0xa9, 0x81,             // 801E LDA #$81
+    0x8d, 0x00, 0x60,       // 8020 STA $6000
+    0x4c, 0x23, 0x80,       // 8023 JMP $8023
+    0xea, 0xea,
+    0xa9, 0x6f,             // 8027 LDA #'o'
+    0x8d, 0x04, 0x60,       // 8029 STA $6004
+    0xa9, 0x6b,             // 802C LDA #'k'
+    0x8d, 0x05, 0x60,       // 802E STA $6005
+    0xa9, 0x00,             // 8031 LDA #$00
+    0x8d, 0x06, 0x60,       // 8033 STA $6006
+    0xa9, 0x03,             // 8036 LDA #$03
+    0x8d, 0x00, 0x60,       // 8038 STA $6000
+    0x4c, 0x3b, 0x80,       // 803B JMP $803B
+];
+
+#[test]
+fn reset_request_test() {
+    let mut system = common::program_system(RESET_PROGRAM);
+    let harness = BlarggHarness::new();
+
+    let result = harness.run(&mut system).unwrap();
+    assert_eq!(result.code, 3);
+    assert_eq!(result.message, "ok");
+    assert_eq!(result.resets, 1);
+    assert!(result.cycles >= harness.reset_delay_cycles);
+    assert!(!result.passed());
+}
+
+#[test]
+fn timeout_test() {
+    let mut system = common::program_system(common::LOOP_PROGRAM);
+    let harness = BlarggHarness { timeout_cycles: 10_000, ..BlarggHarness::new() };
+
+    assert!(harness.run(&mut system).is_err());
+}
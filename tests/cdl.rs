@@ -0,0 +1,83 @@
+mod common;
+
+use nes::{
+    cdl::{CharacterFlags, CodeDataLogger, ProgramFlags},
+    system::ConsoleSystem,
+};
+
+fn logged_system(program: &[u8]) -> ConsoleSystem {
+    let image = common::program_image(program);
+    let logger = CodeDataLogger::new(&image.header);
+    let mut system = ConsoleSystem::new(image);
+    system.cpu.code_data_logger = Some(logger);
+    system.cpu.pc = 0x8000;
+    system.cpu.s = 0xfd;
+    system
+}
+
+fn run_to(system: &mut ConsoleSystem, pc: u16) {
+    for _ in 0..1000 {
+        system.cycle();
+        if system.cpu.at_instruction_boundary() && system.cpu.pc == pc {
+            return;
+        }
+    }
+    panic!("Never got to ${:04X}", pc);
+}
+
+#[test]
+fn code_and_data_test() {
+    let program = [
+        0xad, 0x20, 0x80,       // 8000 LDA $8020
+        0xa9, 0x30,             // 8003 LDA #$30
+        0x85, 0x00,             // 8005 STA $00
+        0xa9, 0x80,             // 8007 LDA #$80
+        0x85, 0x01,             // 8009 STA $01
+        0xa0, 0x01,             // 800B LDY #$01
+        0xb1, 0x00,             // 800D LDA ($00),Y
+        0x6c, 0x22, 0x80,       // 800F JMP ($8022)
+    ];
+    let mut image = program.to_vec();
+    image.resize(0x20, 0xea);
+    image.extend([0x42, 0x00, 0x28, 0x80]); // 8020 data, 8022 pointer to $8028
+    let mut system = logged_system(&image);
+    run_to(&mut system, 0x8029);
+
+    let logger = system.cpu.code_data_logger.as_ref().unwrap();
+    assert_eq!(logger.program_flags(0x00), ProgramFlags::CODE | ProgramFlags::OPCODE);
+    assert_eq!(logger.program_flags(0x01), ProgramFlags::CODE);
+    assert_eq!(logger.program_flags(0x20), ProgramFlags::DATA);
+    assert_eq!(logger.program_flags(0x31), ProgramFlags::DATA | ProgramFlags::INDIRECT_DATA);
+    assert_eq!(logger.program_flags(0x22), ProgramFlags::DATA);
+    assert_eq!(logger.program_flags(0x28), ProgramFlags::CODE | ProgramFlags::OPCODE | ProgramFlags::INDIRECT_CODE);
+    assert_eq!(logger.program_flags(0x12), ProgramFlags::empty());
+
+    // NROM-128 mirrors at $C000, which FCEUX records as the $C000-$DFFF window
+    let mut system = logged_system(&[0xad, 0x00, 0xc0]);
+    run_to(&mut system, 0x8003);
+    assert_eq!(system.cpu.code_data_logger.as_ref().unwrap().program_flags(0), ProgramFlags::CODE | ProgramFlags::OPCODE | ProgramFlags::DATA | ProgramFlags::from_bits_truncate(0x08));
+}
+
+#[test]
+fn character_and_file_test() {
+    let program = [
+        0xa9, 0x00,             // 8000 LDA #$00
+        0x8d, 0x06, 0x20,       // 8002 STA $2006
+        0xa9, 0x10,             // 8005 LDA #$10
+        0x8d, 0x06, 0x20,       // 8007 STA $2006
+        0xad, 0x07, 0x20,       // 800A LDA $2007
+    ];
+    let mut system = logged_system(&program);
+    run_to(&mut system, 0x800d);
+
+    let logger = system.cpu.code_data_logger.as_ref().unwrap();
+    assert_eq!(logger.character_flags(0x10), CharacterFlags::READ);
+    assert_eq!(logger.coverage(), (13, 0));
+
+    let mut file = Vec::new();
+    logger.write(&mut file).unwrap();
+    assert_eq!(file.len(), 0x4000 + 0x2000);
+    // The opcode bit is ours, FCEUX never sees it
+    assert_eq!(file[0], 0x01);
+    assert_eq!(file[0x4000 + 0x10], 0x02);
+}
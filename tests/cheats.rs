@@ -0,0 +1,134 @@
+mod common;
+
+use nes::{
+    cheats::{decode_game_genie, encode_game_genie, Cheat, CheatKind, Cheats},
+    system::ConsoleSystem,
+};
+
+// Copies a PRG byte to $10, counts at $20 and copies $20 back through a mirror to $11
+fn cheat_system() -> ConsoleSystem {
+    let mut program = vec![
+        0xad, 0x00, 0x81,       // 8000 LDA $8100
+        0x85, 0x10,             // 8003 STA $10
+        0xe6, 0x20,             // 8005 INC $20
+        0xad, 0x20, 0x08,       // 8007 LDA $0820
+        0x85, 0x11,             // 800A STA $11
+        0x4c, 0x00, 0x80,       // 800C JMP $8000
+    ];
+    program.resize(0x101, 0xea);
+    program[0x100] = 0x05;
+    common::program_system(&program)
+}
+
+fn run_cycles(system: &mut ConsoleSystem, cycles: usize) {
+    for _ in 0..cycles {
+        system.cycle();
+    }
+}
+
+#[test]
+fn decodes_game_genie() {
+    assert_eq!(decode_game_genie("SXIOPO").unwrap(), (0x91d9, 0xad, None));
+    assert_eq!(decode_game_genie("sxiopo").unwrap(), (0x91d9, 0xad, None));
+    assert!(decode_game_genie("SXIOP").is_err());
+    assert!(decode_game_genie("SXIOPB").is_err());
+
+    for (address, value, compare) in [(0x8000, 0x00, None), (0xffff, 0xff, Some(0xff)), (0x91d9, 0xad, Some(0x5a)), (0xc123, 0x80, Some(0x08))] {
+        let code = encode_game_genie(address, value, compare);
+        assert_eq!(code.len(), if compare.is_some() { 8 } else { 6 });
+        assert_eq!(decode_game_genie(&code).unwrap(), (address, value, compare));
+    }
+}
+
+#[test]
+fn parses_codes() {
+    let cheat = Cheat::parse("SXIOPO").unwrap();
+    assert_eq!((cheat.kind, cheat.address, cheat.value), (CheatKind::Substitute, 0x91d9, 0xad));
+    assert_eq!(cheat.game_genie_code().as_deref(), Some("SXIOPO"));
+
+    let cheat = Cheat::parse("0075:09").unwrap();
+    assert_eq!((cheat.kind, cheat.address, cheat.value), (CheatKind::Freeze, 0x0075, 0x09));
+    assert_eq!(Cheat::parse("007509").unwrap().address, 0x0075);
+    assert_eq!(cheat.game_genie_code(), None);
+    assert!(Cheat::parse("8000:01").is_err());
+}
+
+#[test]
+fn cht_round_trip() {
+    let text = "0075:09:Infinite lives\n:0020:01:Disabled\nSC8100:07:05:Compared\nS:9000:ea:\n";
+    let cheats = Cheats::parse(text).unwrap();
+    let cheats = cheats.cheats();
+    assert_eq!(cheats.len(), 4);
+    assert_eq!(cheats[0], Cheat::new("Infinite lives", CheatKind::Freeze, 0x0075, 0x09, None));
+    assert!(!cheats[1].enabled);
+    assert_eq!((cheats[2].kind, cheats[2].compare, cheats[2].name.as_str()), (CheatKind::Substitute, Some(0x05), "Compared"));
+    assert!(!cheats[3].enabled && cheats[3].name.is_empty());
+
+    assert_eq!(Cheats::parse(text).unwrap().to_cht(), text);
+    assert!(Cheats::parse("C0075:09:No compare\n").is_err());
+}
+
+#[test]
+fn substitutes_program_reads() {
+    let mut system = cheat_system();
+    let mut cheats = Cheats::new();
+    cheats.add(Cheat::game_genie(&encode_game_genie(0x8100, 0x42, None)).unwrap());
+    cheats.add(Cheat::game_genie(&encode_game_genie(0x8100, 0x43, Some(0x99))).unwrap());
+    system.cpu.cheats = Some(cheats);
+    run_cycles(&mut system, 1000);
+    assert_eq!(system.cpu.mapper.peek(0x10), 0x42);
+    // The ROM itself is left alone
+    assert_eq!(system.cpu.mapper.peek(0x8100), 0x05);
+
+    // Only the code whose compare doesn't match is left
+    let cheats = system.cpu.cheats.as_mut().unwrap();
+    cheats.remove(0);
+    run_cycles(&mut system, 1000);
+    assert_eq!(system.cpu.mapper.peek(0x10), 0x05);
+
+    let cheats = system.cpu.cheats.as_mut().unwrap();
+    let matching = cheats.add(Cheat::game_genie(&encode_game_genie(0x8100, 0x43, Some(0x05))).unwrap());
+    run_cycles(&mut system, 1000);
+    assert_eq!(system.cpu.mapper.peek(0x10), 0x43);
+
+    system.cpu.cheats.as_mut().unwrap().set_enabled(matching, false);
+    run_cycles(&mut system, 1000);
+    assert_eq!(system.cpu.mapper.peek(0x10), 0x05);
+}
+
+#[test]
+fn freezes_ram() {
+    let mut system = cheat_system();
+    let mut cheats = Cheats::new();
+    cheats.add(Cheat::pro_action_replay("0020:42").unwrap());
+    system.cpu.cheats = Some(cheats);
+    run_cycles(&mut system, 1000);
+    assert_eq!(system.cpu.mapper.peek(0x20), 0x42);
+    // Read back through $0820
+    assert_eq!(system.cpu.mapper.peek(0x11), 0x42);
+
+    system.cpu.cheats.as_mut().unwrap().set_enabled(0, false);
+    run_cycles(&mut system, 1000);
+    assert_ne!(system.cpu.mapper.peek(0x20), 0x42);
+}
+
+#[test]
+fn timing_and_state_unchanged() {
+    let mut plain = cheat_system();
+    let mut cheated = cheat_system();
+    let mut cheats = Cheats::new();
+    cheats.add(Cheat::game_genie(&encode_game_genie(0x8100, 0x42, None)).unwrap());
+    cheats.add(Cheat::pro_action_replay("0020:42").unwrap());
+    cheated.cpu.cheats = Some(cheats);
+
+    run_cycles(&mut plain, 5000);
+    run_cycles(&mut cheated, 5000);
+    assert_eq!(cheated.cpu.pc, plain.cpu.pc);
+    assert_eq!(cheated.cpu.cycle, plain.cpu.cycle);
+    assert_eq!(cheated.cpu.mapper.get_ppu().dot, plain.cpu.mapper.get_ppu().dot);
+
+    let state = cheated.save_state();
+    assert_eq!(state.len(), plain.save_state().len());
+    plain.load_state(&state).unwrap();
+    assert_eq!(plain.cpu.mapper.peek(0x10), 0x42);
+}
@@ -0,0 +1,77 @@
+#![allow(dead_code)]
+use std::io::Cursor;
+
+use nes::{
+    input::{Buttons, Port, StandardController},
+    roms::RomImage,
+    system::ConsoleSystem,
+};
+
+// Builds a one bank NROM image with `program` at $8000 and the reset vector pointing at it
+pub fn program_image(program: &[u8]) -> RomImage {
+    program_image_with_flags(program, 0)
+}
+
+pub fn program_image_with_flags(program: &[u8], flags: u8) -> RomImage {
+    let mut data = b"NES\x1a\x01\x01\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00".to_vec();
+    data[6] = flags;
+    let mut prg = vec![0xea; 0x4000];
+    prg[..program.len()].copy_from_slice(program);
+    prg[0x3ffc] = 0x00;
+    prg[0x3ffd] = 0x80;
+    data.extend(prg);
+    data.extend(vec![0; 0x2000]);
+    RomImage::from(&mut Cursor::new(data)).expect("Test image load error")
+}
+
+pub fn program_system(program: &[u8]) -> ConsoleSystem {
+    let mut system = ConsoleSystem::new(program_image(program));
+    system.reset();
+    system
+}
+
+// A little loop that touches the stack, zero page, absolute and indexed addressing and a JSR
+pub const LOOP_PROGRAM: &[u8] = &[
+    0xa2, 0x00,             // 8000 LDX #$00
+    0xa9, 0x01,             // 8002 LDA #$01
+    0x85, 0x10,             // 8004 STA $10
+    0xe6, 0x10,             // 8006 INC $10
+    0x0a,                   // 8008 ASL A
+    0x8d, 0x00, 0x02,       // 8009 STA $0200
+    0x6d, 0x00, 0x02,       // 800C ADC $0200
+    0x48,                   // 800F PHA
+    0x68,                   // 8010 PLA
+    0x20, 0x20, 0x80,       // 8011 JSR $8020
+    0xe8,                   // 8014 INX
+    0xd0, 0xeb,             // 8015 BNE $8002
+    0x4c, 0x17, 0x80,       // 8017 JMP $8017
+    0xea, 0xea, 0xea, 0xea, 0xea, 0xea,
+    0x9d, 0x00, 0x03,       // 8020 STA $0300,X
+    0xbd, 0x00, 0x03,       // 8023 LDA $0300,X
+    0x60,                   // 8026 RTS
+];
+
+// Reads the first controller over and over, adding up the A presses at $10 and counting reads at $11
+pub const INPUT_PROGRAM: &[u8] = &[
+    0xa9, 0x01,             // 8000 LDA #$01
+    0x8d, 0x16, 0x40,       // 8002 STA $4016
+    0xa9, 0x00,             // 8005 LDA #$00
+    0x8d, 0x16, 0x40,       // 8007 STA $4016
+    0xa2, 0x08,             // 800A LDX #$08
+    0xad, 0x16, 0x40,       // 800C LDA $4016
+    0x29, 0x01,             // 800F AND #$01
+    0x65, 0x10,             // 8011 ADC $10
+    0x85, 0x10,             // 8013 STA $10
+    0xca,                   // 8015 DEX
+    0xd0, 0xf4,             // 8016 BNE $800C
+    0xe6, 0x11,             // 8018 INC $11
+    0x4c, 0x00, 0x80,       // 801A JMP $8000
+];
+
+pub fn press(system: &mut ConsoleSystem, buttons: Buttons) {
+    system.input().device_mut::<StandardController>(Port::One).unwrap().buttons = buttons;
+}
+
+pub fn pattern(frame: usize) -> Buttons {
+    if frame.is_multiple_of(3) { Buttons::A } else { Buttons::empty() }
+}
@@ -1,5 +1,36 @@
+mod common;
+
 #[cfg(test)]
 mod test {
+    use crate::common;
+    use nes::system::RunUntil;
+
+    // LDA ($00),Y with the pointer at $00 set to `pointer`, returns A once it's done
+    fn load_indirect_indexed_y(pointer: u16, y: u8, data: u8) -> u8 {
+        let program = [
+            0xa9, pointer as u8,                        // 8000 LDA #<pointer
+            0x85, 0x00,                                 // 8002 STA $00
+            0xa9, (pointer >> 8) as u8,                 // 8004 LDA #>pointer
+            0x85, 0x01,                                 // 8006 STA $01
+            0xa0, y,                                    // 8008 LDY #y
+            0xa9, data,                                 // 800A LDA #data
+            0x99, pointer as u8, (pointer >> 8) as u8,  // 800C STA pointer,Y
+            0xa9, 0x00,                                 // 800F LDA #$00
+            0xb1, 0x00,                                 // 8011 LDA ($00),Y
+            0x4c, 0x13, 0x80,                           // 8013 JMP $8013
+        ];
+        let mut system = common::program_system(&program);
+        system.run_until(RunUntil::Pc(0x8013)).unwrap();
+        system.cpu.a
+    }
+
+    #[test]
+    fn indirect_indexed_y() {
+        assert_eq!(load_indirect_indexed_y(0x0300, 0x01, 0x42), 0x42);
+        // Crossing into the next page
+        assert_eq!(load_indirect_indexed_y(0x03ff, 0x02, 0x24), 0x24);
+    }
+
     #[test]
     fn power_on_state() {
         //let cpu = crate::nes::cpu::RP2A03::new();
@@ -0,0 +1,100 @@
+mod common;
+
+use nes::{
+    debugger::{AddressSpace, Debugger, Expression, Operator, StopReason, Variable},
+    system::ConsoleSystem,
+};
+
+fn loop_debugger() -> Debugger {
+    let mut system = ConsoleSystem::new(common::program_image(common::LOOP_PROGRAM));
+    system.cpu.pc = 0x8000;
+    system.cpu.s = 0xfd;
+    Debugger::new(system)
+}
+
+#[test]
+fn expression_test() {
+    let expression = Expression::parse("a == $10 && [$0300] != 0").unwrap();
+    match expression {
+        Expression::Binary(left, Operator::And, right) => {
+            assert_eq!(*left, Expression::Binary(Box::new(Expression::Variable(Variable::A)), Operator::Equal, Box::new(Expression::Number(0x10))));
+            assert!(matches!(*right, Expression::Binary(_, Operator::NotEqual, _)));
+        }
+        other => panic!("Parsed as {:?}", other),
+    }
+
+    assert!(Expression::parse("x +").is_err());
+    assert!(Expression::parse("nowhere == 1").is_err());
+
+    let mut system = loop_debugger().system;
+    system.cpu.x = 3;
+    assert_eq!(Expression::parse("(x + 1) & 6").unwrap().evaluate(&mut system, 0), 4);
+    assert_eq!(Expression::parse("x - 1 - 1").unwrap().evaluate(&mut system, 0), 1);
+    assert_eq!(Expression::parse("0x10 | 1 == 1").unwrap().evaluate(&mut system, 0), 0x11);
+    assert!(Expression::parse("x >= 3 || value & $80").unwrap().is_true(&mut system, 0));
+    assert!(Expression::parse("value & $80").unwrap().is_true(&mut system, 0x80));
+    assert!(!Expression::parse("!pc").unwrap().is_true(&mut system, 0));
+}
+
+#[test]
+fn breakpoint_test() {
+    let mut debugger = loop_debugger();
+    debugger.add_breakpoint(0x8014, None, None);
+
+    assert_eq!(debugger.resume(), StopReason::Breakpoint(0));
+    assert_eq!((debugger.system.cpu.pc, debugger.system.cpu.x), (0x8014, 0));
+
+    // Resuming runs the instruction under the breakpoint and goes round the loop again
+    assert_eq!(debugger.resume(), StopReason::Breakpoint(0));
+    assert_eq!(debugger.system.cpu.x, 1);
+}
+
+#[test]
+fn conditional_breakpoint_test() {
+    let mut debugger = loop_debugger();
+    let output = debugger.execute("b $8014 if x == 3").unwrap();
+    assert_eq!(output, "Breakpoint 0 at $8014");
+
+    assert_eq!(debugger.resume(), StopReason::Breakpoint(0));
+    assert_eq!(debugger.system.cpu.x, 3);
+}
+
+#[test]
+fn watchpoint_test() {
+    let mut debugger = loop_debugger();
+    debugger.execute("w w $10").unwrap();
+
+    match debugger.resume() {
+        StopReason::Watchpoint(0, AddressSpace::Cpu, access) => assert_eq!((access.address, access.data), (0x10, 0x01)),
+        other => panic!("Stopped for {:?}", other),
+    }
+
+    // INC $10 reads then writes twice, only the final write is a change worth a condition
+    debugger.watchpoints[0].condition = Some(Expression::parse("value == 2").unwrap());
+    match debugger.resume() {
+        StopReason::Watchpoint(0, AddressSpace::Cpu, access) => assert_eq!(access.data, 0x02),
+        other => panic!("Stopped for {:?}", other),
+    }
+}
+
+#[test]
+fn step_test() {
+    let mut debugger = loop_debugger();
+    debugger.add_breakpoint(0x8011, None, None);
+    assert_eq!(debugger.resume(), StopReason::Breakpoint(0));
+
+    // Stepping over the JSR lands straight after it with the stack back where it was
+    let stack = debugger.system.cpu.s;
+    assert_eq!(debugger.step_over(), StopReason::Step);
+    assert_eq!((debugger.system.cpu.pc, debugger.system.cpu.s), (0x8014, stack));
+
+    debugger.breakpoints[0].enabled = false;
+    debugger.add_breakpoint(0x8020, None, None);
+    assert_eq!(debugger.resume(), StopReason::Breakpoint(1));
+    assert_eq!(debugger.step_instruction(), StopReason::Step);
+    assert_eq!(debugger.system.cpu.pc, 0x8023);
+
+    assert_eq!(debugger.step_out(), StopReason::Step);
+    assert_eq!(debugger.system.cpu.pc, 0x8014);
+    assert!(debugger.execute("d $8000 1").unwrap().contains("LDX #$00"));
+}
@@ -0,0 +1,63 @@
+mod common;
+
+use nes::disasm::{AddressingMode, Instruction, Labels, OPCODES};
+
+#[test]
+fn decode_test() {
+    let lda = Instruction::decode(0x8000, &[0xbd, 0x00, 0x03]);
+    assert_eq!(lda.text(), "LDA $0300,X");
+    assert_eq!(lda.mode(), AddressingMode::AbsoluteX);
+    assert_eq!(lda.length(), 3);
+    assert_eq!(lda.cycles(), (4, 5));
+    assert!(!lda.illegal());
+
+    let bne = Instruction::decode(0x8015, &[0xd0, 0xeb]);
+    assert_eq!(bne.text(), "BNE $8002");
+    assert_eq!(bne.target(), Some(0x8002));
+    assert_eq!(bne.cycles(), (2, 4));
+
+    assert_eq!(Instruction::decode(0x8000, &[0xb1, 0x10]).text(), "LDA ($10),Y");
+    assert_eq!(Instruction::decode(0x8000, &[0x6c, 0xfc, 0xff]).text(), "JMP ($FFFC)");
+    assert_eq!(Instruction::decode(0x8000, &[0x0a]).text(), "ASL A");
+    assert_eq!(Instruction::decode(0x8000, &[0x20, 0x20, 0x80]).target(), Some(0x8020));
+    assert_eq!(Instruction::decode(0x8000, &[0xa9, 0x01]).to_string(), "8000  A9 01     LDA #$01");
+
+    let lax = Instruction::decode(0x8000, &[0xb7, 0x44]);
+    assert_eq!(lax.text(), "LAX $44,Y");
+    assert!(lax.illegal());
+    assert!(Instruction::decode(0x8000, &[0xeb, 0x01]).illegal());
+}
+
+#[test]
+fn opcode_table_test() {
+    let official = OPCODES.iter().enumerate().filter(|(opcode, info)| !info.illegal(*opcode as u8)).count();
+    assert_eq!(official, 151);
+}
+
+#[test]
+fn label_formats_test() {
+    let dbg = "version\tmajor=2,minor=0\nsym\tid=0,name=\"reset\",addrsize=absolute,scope=0,def=1,val=0x8000,seg=0,type=lab\n\
+               sym\tid=1,name=\"counter\",addrsize=zeropage,scope=0,def=2,val=0x10,type=lab\n";
+    let labels = Labels::parse_ca65_dbg(dbg).unwrap();
+    assert_eq!(labels.address_of("reset"), Some(0x8000));
+    assert_eq!(labels.address_of("counter"), Some(0x10));
+
+    let nl = Labels::parse_fceux_nl("$8020#subroutine#stores X\n$0010#counter#\n", Some(0)).unwrap();
+    assert_eq!(nl.len(), 2);
+
+    let mlb = Labels::parse_mesen_mlb("P:0020:subroutine\nR:0010:counter:loop counter\nG:2002:PPUSTATUS\nP:0030-0032:table\n").unwrap();
+    assert_eq!(mlb.len(), 4);
+    assert_eq!(mlb.address_of("PPUSTATUS"), Some(0x2002));
+}
+
+#[test]
+fn annotate_test() {
+    let system = common::program_system(common::LOOP_PROGRAM);
+    let mapper = system.cpu.mapper.as_ref();
+    let labels = Labels::parse_mesen_mlb("P:0020:subroutine\nR:0010:counter\n").unwrap();
+
+    assert_eq!(Instruction::decode(0x8011, &[0x20, 0x20, 0x80]).annotate(&labels, mapper), "JSR subroutine");
+    // NROM-128 mirrors the bank, so the label shows up at $C020 as well
+    assert_eq!(Instruction::decode(0x8011, &[0x20, 0x20, 0xc0]).annotate(&labels, mapper), "JSR subroutine");
+    assert_eq!(Instruction::decode(0x8006, &[0xe6, 0x10]).annotate(&labels, mapper), "INC counter");
+}
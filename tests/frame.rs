@@ -0,0 +1,98 @@
+mod common;
+
+use std::cell::RefCell;
+use std::io::ErrorKind;
+use std::rc::Rc;
+
+use nes::system::{ConsoleSystem, RunUntil, SystemEvent, RUN_UNTIL_FRAMES};
+
+// Reads the first controller once per pass of a long delay loop
+const POLLING_PROGRAM: &[u8] = &[
+    0xad, 0x16, 0x40,       // 8000 LDA $4016
+    0xca,                   // 8003 DEX
+    0xd0, 0xfd,             // 8004 BNE $8003
+    0x88,                   // 8006 DEY
+    0xd0, 0xfa,             // 8007 BNE $8003
+    0x4c, 0x00, 0x80,       // 8009 JMP $8000
+];
+
+#[test]
+fn run_frame_test() {
+    let mut system = common::program_system(common::LOOP_PROGRAM);
+    system.run_frame();
+
+    let result = system.run_frame();
+    assert!((29_780..=29_781).contains(&result.cycles), "frame took {} cycles", result.cycles);
+    assert!(result.samples.len().abs_diff(735) <= 1);
+    assert!(result.lag);
+    assert_eq!(system.cpu.mapper.get_ppu().scanline, 0);
+}
+
+#[test]
+fn lag_frame_test() {
+    let mut system = common::program_system(POLLING_PROGRAM);
+    assert!(!system.run_frame().lag);
+}
+
+#[test]
+fn run_until_test() {
+    let mut system = common::program_system(common::LOOP_PROGRAM);
+
+    system.run_until(RunUntil::Pc(0x8020)).unwrap();
+    assert_eq!(system.cpu.pc, 0x8020);
+    assert!(system.cpu.at_instruction_boundary());
+
+    system.run_until(RunUntil::Cycle(1000)).unwrap();
+    assert_eq!(system.cpu_cycles(), 1000);
+
+    system.run_until(RunUntil::Scanline(100)).unwrap();
+    assert_eq!(system.cpu.mapper.get_ppu().scanline, 100);
+    assert!(system.cpu.mapper.get_ppu().dot < 3);
+}
+
+#[test]
+fn run_until_gives_up_test() {
+    let mut system = common::program_system(common::LOOP_PROGRAM);
+
+    // NTSC stops at 261
+    let error = system.run_until(RunUntil::Scanline(300)).err().unwrap();
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+    assert_eq!(system.cpu_cycles(), 0);
+
+    let start = system.cpu.mapper.get_ppu().frame;
+    let error = system.run_until(RunUntil::Pc(0x9000)).err().unwrap();
+    assert_eq!(error.kind(), ErrorKind::TimedOut);
+    assert_eq!(system.cpu.mapper.get_ppu().frame, start + RUN_UNTIL_FRAMES);
+}
+
+#[test]
+fn step_instruction_test() {
+    let mut system = ConsoleSystem::new(common::program_image(common::LOOP_PROGRAM));
+    system.cpu.pc = 0x8000;
+    system.cpu.s = 0xfd;
+
+    // LDX #$00, LDA #$01, STA $10, INC $10
+    let cycles: Vec<u64> = (0..4).map(|_| system.step_instruction()).collect();
+    assert_eq!(cycles, vec![2, 2, 3, 5]);
+    assert_eq!(system.cpu.pc, 0x8008);
+}
+
+#[test]
+fn event_hook_test() {
+    let mut system = common::program_system(common::LOOP_PROGRAM);
+    let events = Rc::new(RefCell::new(Vec::new()));
+    let seen = events.clone();
+    system.add_hook(Box::new(move |_: &mut ConsoleSystem, event| seen.borrow_mut().push(event)));
+
+    system.run_frame();
+    events.borrow_mut().clear();
+    let frame = system.cpu.mapper.get_ppu().frame;
+    system.run_frame();
+
+    let events = events.borrow();
+    let scanlines = events.iter().filter(|event| matches!(event, SystemEvent::Scanline(_))).count();
+    assert_eq!(scanlines, 262);
+    assert!(events.contains(&SystemEvent::Vblank(frame)));
+    assert_eq!(events.last(), Some(&SystemEvent::Scanline(0)));
+    assert_eq!(events[events.len() - 2], SystemEvent::FrameStart(frame + 1));
+}
@@ -0,0 +1,142 @@
+mod common;
+
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use nes::{
+    debugger::{Debugger, GdbServer},
+    system::ConsoleSystem,
+};
+
+// Sends each packet, checks the ack and collects the reply
+fn client(mut stream: TcpStream, packets: &[&str]) -> Vec<String> {
+    let mut replies = Vec::new();
+    for packet in packets {
+        let checksum = packet.bytes().fold(0u8, |sum, byte| sum.wrapping_add(byte));
+        write!(stream, "${}#{:02x}", packet, checksum).unwrap();
+
+        let mut ack = [0];
+        stream.read_exact(&mut ack).unwrap();
+        assert_eq!(ack[0], b'+', "no ack for {}", packet);
+        if *packet == "k" {
+            break;
+        }
+
+        let mut reply = Vec::new();
+        let mut byte = [0];
+        while !reply.ends_with(b"#") {
+            stream.read_exact(&mut byte).unwrap();
+            reply.push(byte[0]);
+        }
+        let mut checksum = [0; 2];
+        stream.read_exact(&mut checksum).unwrap();
+        stream.write_all(b"+").unwrap();
+
+        let text = String::from_utf8(reply).unwrap();
+        replies.push(text.trim_start_matches('$').trim_end_matches('#').to_string());
+    }
+    replies
+}
+
+fn loop_server() -> GdbServer {
+    let mut system = ConsoleSystem::new(common::program_image(common::LOOP_PROGRAM));
+    system.cpu.pc = 0x8000;
+    system.cpu.s = 0xfd;
+    GdbServer::new(Debugger::new(system))
+}
+
+fn run_script(packets: &'static [&'static str]) -> (Vec<String>, Debugger) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let script = thread::spawn(move || client(TcpStream::connect(address).unwrap(), packets));
+
+    let mut server = loop_server();
+    let (stream, _) = listener.accept().unwrap();
+    server.serve(stream).unwrap();
+
+    (script.join().unwrap(), server.debugger)
+}
+
+#[test]
+fn registers_and_memory_test() {
+    let (replies, debugger) = run_script(&["qSupported:swbreak+", "?", "g", "P0=5a", "p0", "M0300,2:beef", "m0300,2", "m8000,4", "k"]);
+
+    assert!(replies[0].contains("qXfer:features:read+"));
+    assert_eq!(replies[1], "S05");
+    assert_eq!(replies[2], format!("000000{:02x}fd0080", debugger.system.cpu.p.bits()));
+    assert_eq!(&replies[3..], ["OK", "5a", "OK", "beef", "a200a901"]);
+    assert_eq!(debugger.system.cpu.a, 0x5a);
+}
+
+#[test]
+fn memory_read_is_clamped_test() {
+    let (replies, _) = run_script(&["qSupported", "m0,ffffffff", "k"]);
+    assert!(replies[0].starts_with("PacketSize=1000;"));
+    assert_eq!(replies[1].len(), 0x1000);
+}
+
+#[test]
+fn hang_up_while_running_test() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut server = loop_server();
+        let (stream, _) = listener.accept().unwrap();
+        let _ = sender.send(server.serve(stream));
+    });
+
+    // Continue with nothing to stop at, then go away
+    let mut stream = TcpStream::connect(address).unwrap();
+    stream.write_all(b"$c#63").unwrap();
+    let mut ack = [0];
+    stream.read_exact(&mut ack).unwrap();
+    drop(stream);
+
+    let result = receiver.recv_timeout(Duration::from_secs(10)).expect("Server kept running after the client hung up");
+    assert!(result.is_err());
+}
+
+#[test]
+fn breakpoint_and_step_test() {
+    let (replies, debugger) = run_script(&["Z0,8011,1", "c", "p5", "s", "p5", "z0,8011,1", "Z2,0300,1", "c", "D"]);
+
+    assert_eq!(&replies[..5], ["OK", "S05", "1180", "S05", "2080"]);
+    assert_eq!(replies[5], "OK");
+    assert_eq!(replies[7], "T05watch:0300;");
+    assert_eq!(replies[8], "OK");
+    assert_eq!(debugger.system.cpu.last_access.unwrap().address, 0x0300);
+}
+
+#[test]
+fn target_description_test() {
+    let (replies, _) = run_script(&["qXfer:features:read:target.xml:0,20", "qXfer:features:read:target.xml:0,fff", "k"]);
+    assert!(replies[0].starts_with("m<?xml"));
+    assert!(replies[1].starts_with('l') && replies[1].contains(r#"<reg name="pc" bitsize="16""#));
+}
+
+#[test]
+fn non_ascii_command_test() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let script = thread::spawn(move || {
+        let mut stream = TcpStream::connect(address).unwrap();
+        stream.write_all(b"$\xff#ff").unwrap();
+        let mut reply = [0; 4];
+        stream.read_exact(&mut reply).unwrap();
+        assert_eq!(&reply, b"+$#0");
+        let mut checksum = [0];
+        stream.read_exact(&mut checksum).unwrap();
+        stream.write_all(b"+").unwrap();
+        client(stream, &["?", "k"])
+    });
+
+    let mut server = loop_server();
+    let (stream, _) = listener.accept().unwrap();
+    server.serve(stream).unwrap();
+
+    assert_eq!(script.join().unwrap(), ["S05"]);
+}
@@ -0,0 +1,136 @@
+mod common;
+
+use nes::input::{Buttons, ControllerPorts, ExpansionDeviceKind, FamilyKeyboard, FourScore, InputLayout, Port, PowerPad, StandardController, Zapper};
+use nes::input::InputDevice;
+use nes::ppu::PPU;
+
+#[test]
+fn standard_controller_shifts_buttons() {
+    let ppu = PPU::new();
+    let mut ports = ControllerPorts::new();
+    ports.device_mut::<StandardController>(Port::One).unwrap().buttons = Buttons::A | Buttons::START | Buttons::RIGHT;
+    ports.write(1);
+    ports.write(0);
+
+    let bits: Vec<u8> = (0..10).map(|_| ports.read(0x4016, &ppu) & 1).collect();
+    assert_eq!(bits, [1, 0, 0, 1, 0, 0, 0, 1, 1, 1]);
+}
+
+#[test]
+fn zapper_reports_trigger_and_light() {
+    let mut ppu = PPU::new();
+    let mut ports = ControllerPorts::new();
+    ports.attach(Port::Two, Box::new(Zapper::new()));
+    let zapper = ports.device_mut::<Zapper>(Port::Two).unwrap();
+    zapper.aim(100, 120);
+    zapper.trigger = true;
+
+    // White target drawn under the gun
+    ppu.framebuffer[120 * 256 + 101] = 0x30;
+
+    // Beam hasn't reached the target yet
+    ppu.scanline = 119;
+    ppu.dot = 200;
+    assert_eq!(ports.read(0x4017, &ppu), 0x40 | 0x10 | 0x08);
+
+    // Just after the beam drew it
+    ppu.scanline = 121;
+    ppu.dot = 10;
+    assert_eq!(ports.read(0x4017, &ppu), 0x40 | 0x10);
+
+    // Sensor has decayed
+    ppu.scanline = 150;
+    assert_eq!(ports.read(0x4017, &ppu), 0x40 | 0x10 | 0x08);
+}
+
+#[test]
+fn zapper_ignores_dark_pixels() {
+    let mut ppu = PPU::new();
+    let mut zapper = Zapper::new();
+    zapper.aim(10, 10);
+    ppu.framebuffer[10 * 256 + 10] = 0x06;
+    ppu.scanline = 12;
+
+    assert_eq!(zapper.read(&ppu), 0x08);
+}
+
+#[test]
+fn zapper_sees_rendered_background() {
+    let mut system = common::program_system(&[0x4c, 0x00, 0x80]);
+    let mapper = &mut system.cpu.mapper;
+    mapper.poke_ppu(0x3f00, 0x0f);
+    mapper.poke_ppu(0x3f01, 0x30);
+    // Tile 1 is solid colour 1, drawn over $60-$7F both ways
+    for row in 0..8 {
+        mapper.poke_ppu(0x0010 + row, 0xff);
+    }
+    for y in 12..16 {
+        for x in 12..16 {
+            mapper.poke_ppu(0x2000 + y * 32 + x, 0x01);
+        }
+    }
+    mapper.poke(0x2001, 0x0a);
+    system.run_frame();
+    system.run_frame();
+
+    let ppu = system.cpu.mapper.get_ppu();
+    assert_eq!(ppu.pixel(100, 100), 0x30);
+    assert_eq!(ppu.pixel(10, 10), 0x0f);
+
+    while system.cpu.mapper.get_ppu().scanline != 105 {
+        system.cycle();
+    }
+    let ppu = system.cpu.mapper.get_ppu();
+    let mut zapper = Zapper::new();
+    zapper.aim(100, 100);
+    assert_eq!(zapper.read(ppu), 0x00);
+    zapper.aim(10, 100);
+    assert_eq!(zapper.read(ppu), 0x08);
+}
+
+#[test]
+fn four_score_sends_signature_after_both_controllers() {
+    let ppu = PPU::new();
+    let mut ports = ControllerPorts::with_layout(InputLayout::from_expansion_device(0x02));
+    ports.device_mut::<FourScore>(Port::One).unwrap().controllers[1].buttons = Buttons::B;
+    ports.device_mut::<FourScore>(Port::Two).unwrap().controllers[0].buttons = Buttons::UP;
+    ports.write(1);
+    ports.write(0);
+
+    let port1: Vec<u8> = (0..25).map(|_| ports.read(0x4016, &ppu) & 1).collect();
+    let port2: Vec<u8> = (0..25).map(|_| ports.read(0x4017, &ppu) & 1).collect();
+    assert_eq!(port1[8..16], [0, 1, 0, 0, 0, 0, 0, 0]);
+    assert_eq!(port1[16..], [0, 0, 0, 1, 0, 0, 0, 0, 1]);
+    assert_eq!(port2[..8], [0, 0, 0, 0, 1, 0, 0, 0]);
+    assert_eq!(port2[16..], [0, 0, 1, 0, 0, 0, 0, 0, 1]);
+}
+
+#[test]
+fn power_pad_shifts_on_d3_and_d4() {
+    let ppu = PPU::new();
+    let mut ports = ControllerPorts::with_layout(InputLayout::from_expansion_device(0x0b));
+    let pad = ports.device_mut::<PowerPad>(Port::Two).unwrap();
+    pad.set_button(1, true);
+    pad.set_button(12, true);
+    ports.write(1);
+    ports.write(0);
+
+    let reads: Vec<u8> = (0..5).map(|_| ports.read(0x4017, &ppu) & 0x18).collect();
+    assert_eq!(reads, [0x00, 0x08, 0x10, 0x00, 0x10]);
+}
+
+#[test]
+fn family_keyboard_scans_rows() {
+    let ppu = PPU::new();
+    let mut ports = ControllerPorts::new();
+    ports.select_expansion(ExpansionDeviceKind::FamilyKeyboard);
+    ports.expansion_mut::<FamilyKeyboard>().unwrap().set_key(1, 5, true);
+
+    // Reset to row 0, then column 1, column 0 (row 1), column 1
+    ports.write(0b101);
+    ports.write(0b110);
+    ports.write(0b100);
+    assert_eq!(ports.read(0x4017, &ppu), 0x40 | 0x1e);
+    ports.write(0b110);
+    assert_eq!(ports.read(0x4017, &ppu), 0x40 | 0x1a);
+}
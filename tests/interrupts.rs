@@ -0,0 +1,196 @@
+mod common;
+
+use nes::{
+    cpu::RP2A03,
+    ppu::Status,
+    system::{ConsoleSystem, MasterClock, Region},
+};
+
+// Turns on the vblank NMI and spins, the handler counts NMIs in $10
+const NMI_PROGRAM: &[u8] = &[
+    0x2c, 0x02, 0x20,       // 8000 BIT $2002
+    0xa9, 0x80,             // 8003 LDA #$80
+    0x8d, 0x00, 0x20,       // 8005 STA $2000
+    0x4c, 0x08, 0x80,       // 8008 JMP $8008
+    0xea, 0xea, 0xea, 0xea, 0xea,
+    0xe6, 0x10,             // 8010 INC $10
+    0x40,                   // 8012 RTI
+];
+
+fn nmi_system() -> ConsoleSystem {
+    let mut system = common::program_system(NMI_PROGRAM);
+    system.cpu.mapper.poke(0xfffa, 0x10);
+    system.cpu.mapper.poke(0xfffb, 0x80);
+    system
+}
+
+fn run_frames(system: &mut ConsoleSystem, frames: u64) {
+    let end = system.cpu.mapper.get_ppu().frame + frames;
+    while system.cpu.mapper.get_ppu().frame < end {
+        system.cycle();
+    }
+}
+
+#[test]
+fn clock_divider_test() {
+    let mut ntsc = MasterClock::new(Region::Ntsc);
+    let ticks: Vec<_> = (0..12).map(|_| ntsc.tick()).collect();
+    assert_eq!(ticks.iter().filter(|tick| tick.cpu).count(), 1);
+    assert_eq!(ticks.iter().filter(|tick| tick.ppu).count(), 3);
+
+    let mut pal = MasterClock::new(Region::Pal);
+    let ticks: Vec<_> = (0..80).map(|_| pal.tick()).collect();
+    assert_eq!(ticks.iter().filter(|tick| tick.cpu).count(), 5);
+    assert_eq!(ticks.iter().filter(|tick| tick.ppu).count(), 16);
+}
+
+#[test]
+fn ppu_runs_three_dots_per_cpu_cycle_test() {
+    let mut system = common::program_system(common::LOOP_PROGRAM);
+    let start = system.cpu.mapper.get_ppu().dot;
+    for _ in 0..100 {
+        system.cycle();
+    }
+    assert_eq!(system.cpu.mapper.get_ppu().dot - start, 300);
+}
+
+#[test]
+fn nmi_once_per_frame_test() {
+    let mut system = nmi_system();
+    run_frames(&mut system, 3);
+    assert_eq!(system.cpu.mapper.peek(0x10), 3);
+    assert_eq!(system.cpu.s, 0xfd);
+}
+
+#[test]
+fn nmi_pushes_status_without_break_test() {
+    let mut system = nmi_system();
+    while system.cpu.pc != 0x8010 {
+        system.cycle();
+    }
+
+    // PCH, PCL, then status
+    assert_eq!(system.cpu.s, 0xfa);
+    assert_eq!(system.cpu.mapper.peek(0x01fd), 0x80);
+    assert_eq!(system.cpu.mapper.peek(0x01fc), 0x08);
+    assert_eq!(system.cpu.mapper.peek(0x01fb) & 0x30, 0x20);
+    assert!(system.cpu.p.contains(nes::cpu::Status::INTERRUPT_DISABLE));
+}
+
+#[test]
+fn status_read_clears_vblank_test() {
+    let mut system = common::program_system(common::LOOP_PROGRAM);
+    let ppu = system.cpu.mapper.get_ppu();
+    ppu.scanline = 241;
+    ppu.dot = 1;
+    ppu.status = Status::VBLANK;
+
+    assert_eq!(system.cpu.mapper.read(0x2002) & 0x80, 0x80);
+    assert_eq!(system.cpu.mapper.read(0x2002) & 0x80, 0x00);
+}
+
+#[test]
+fn status_read_racing_vblank_suppresses_it_test() {
+    let mut system = common::program_system(common::LOOP_PROGRAM);
+    let ppu = system.cpu.mapper.get_ppu();
+    ppu.scanline = 241;
+    ppu.dot = 0;
+    ppu.status = Status::empty();
+
+    // One dot early reads clear and the flag then never comes up this frame
+    assert_eq!(system.cpu.mapper.read(0x2002) & 0x80, 0x00);
+    let ppu = system.cpu.mapper.get_ppu();
+    for _ in 0..10 {
+        ppu.cycle();
+    }
+    assert!(!ppu.status.contains(Status::VBLANK));
+
+    // The next frame is back to normal
+    while ppu.scanline != 241 || ppu.dot != 1 {
+        ppu.cycle();
+    }
+    assert!(ppu.status.contains(Status::VBLANK));
+}
+
+// Runs the CPU on its own with the interrupt lines held where the test puts them
+fn step(system: &mut ConsoleSystem, nmi: bool, irq: bool) {
+    system.cpu.cycle();
+    system.cpu.set_interrupt_lines(nmi, irq);
+}
+
+fn step_to(system: &mut ConsoleSystem, pc: u16, nmi: bool, irq: bool) {
+    for _ in 0..1000 {
+        if system.cpu.pc == pc && system.cpu.at_instruction_boundary() {
+            return;
+        }
+        step(system, nmi, irq);
+    }
+    panic!("never got to ${:04X}", pc);
+}
+
+// `program` at $8000, both vectors pointing at a JMP to itself at $8010
+fn vector_system(program: &[u8]) -> ConsoleSystem {
+    let mut program = program.to_vec();
+    program.resize(0x10, 0xea);
+    program.extend([0x4c, 0x10, 0x80]);
+    let mut system = common::program_system(&program);
+    for vector in [0xfffa, 0xfffe] {
+        system.cpu.mapper.poke(vector, 0x10);
+        system.cpu.mapper.poke(vector + 1, 0x80);
+    }
+    step_to(&mut system, 0x8000, false, false);
+    system
+}
+
+fn return_address(system: &mut ConsoleSystem) -> u16 {
+    let s = system.cpu.s as u16;
+    u16::from_le_bytes([system.cpu.mapper.peek(0x0101 + s + 1), system.cpu.mapper.peek(0x0101 + s + 2)])
+}
+
+#[test]
+fn irq_waits_an_instruction_after_cli_test() {
+    // CLI, INX, INX with /IRQ held low the whole time
+    let mut system = vector_system(&[0x58, 0xe8, 0xe8]);
+    step_to(&mut system, 0x8010, false, true);
+    assert_eq!(system.cpu.x, 1);
+    assert_eq!(return_address(&mut system), 0x8002);
+
+    // CLI, SEI still lets one through, with I set in the pushed status
+    let mut system = vector_system(&[0x58, 0x78, 0xe8]);
+    step_to(&mut system, 0x8010, false, true);
+    assert_eq!(system.cpu.x, 0);
+    assert_eq!(return_address(&mut system), 0x8002);
+    let status = system.cpu.mapper.peek(0x0101 + system.cpu.s as u16);
+    assert_eq!(status & 0x04, 0x04);
+}
+
+#[test]
+fn nmi_on_last_cycle_waits_an_instruction_test() {
+    // INX, INX, INX
+    let program = [0xe8, 0xe8, 0xe8];
+
+    // An edge on the second to last cycle is taken straight after the instruction
+    let mut system = vector_system(&program);
+    step(&mut system, true, false);
+    step_to(&mut system, 0x8010, true, false);
+    assert_eq!(system.cpu.x, 1);
+
+    // One on the last cycle lets the next instruction run first
+    let mut system = vector_system(&program);
+    step(&mut system, false, false);
+    step(&mut system, true, false);
+    step_to(&mut system, 0x8010, true, false);
+    assert_eq!(system.cpu.x, 2);
+    assert_eq!(return_address(&mut system), 0x8002);
+}
+
+#[test]
+fn taken_branch_skips_last_poll_test() {
+    // BNE to the next instruction, taken without crossing a page, then INX, INX
+    let mut system = vector_system(&[0xd0, 0x00, 0xe8, 0xe8]);
+    step(&mut system, false, false);
+    step(&mut system, true, false);
+    step_to(&mut system, 0x8010, true, false);
+    assert_eq!(system.cpu.x, 1);
+    assert_eq!(return_address(&mut system), 0x8003);
+}
@@ -0,0 +1,199 @@
+mod common;
+
+use std::io::{Cursor, Write};
+
+use zip::write::{FileOptions, ZipWriter};
+
+use common::{pattern, press, INPUT_PROGRAM};
+use nes::{
+    input::Buttons,
+    movie::{Movie, MovieFrame, MovieMode},
+    system::ConsoleSystem,
+};
+
+fn run_frames(system: &mut ConsoleSystem, frames: usize) {
+    for _ in 0..frames {
+        system.run_frame();
+    }
+}
+
+fn record(system: &mut ConsoleSystem, frames: usize) -> Movie {
+    system.record_movie(false);
+    for frame in 0..frames {
+        press(system, pattern(frame));
+        system.run_frame();
+    }
+    system.stop_movie().unwrap().movie
+}
+
+fn play(system: &mut ConsoleSystem, movie: Movie) {
+    let frames = movie.frames.len();
+    system.play_movie(movie).unwrap();
+    for _ in 0..frames {
+        // The movie's input wins over whatever the frontend set
+        press(system, Buttons::START);
+        system.run_frame();
+    }
+}
+
+#[test]
+fn fm2_round_trip() {
+    let mut movie = Movie::from_state(vec![1, 2, 3, 4]);
+    movie.rerecords = 12;
+    movie.rom_filename = "game".to_string();
+    movie.comments.push("author someone".to_string());
+    movie.ram_hashes.insert(0, 0x0123_4567_89ab_cdef);
+    movie.frames.push(MovieFrame { ports: [Buttons::A | Buttons::RIGHT, Buttons::START], ..MovieFrame::new() });
+    movie.frames.push(MovieFrame { reset: true, ..MovieFrame::new() });
+    movie.frames.push(MovieFrame { power: true, ..MovieFrame::new() });
+
+    assert_eq!(Movie::parse_fm2(&movie.to_fm2()).unwrap(), movie);
+}
+
+#[test]
+fn parses_fceux_fm2() {
+    let text = "version 3\nemuVersion 22020\nrerecordCount 5\npalFlag 0\nromFilename smb\n\
+                romChecksum base64:AAAAAAAAAAAAAAAAAAAAAA==\nguid 00000000-0000-0000-0000-000000000000\n\
+                fourscore 0\nmicrophone 0\nport0 1\nport1 1\nport2 0\nFDS 0\nNewPPU 0\nRAMInitOption 0\n\
+                |1|........|........||\n|0|R......A|........||\n|0|...UT...|..D...B.||\n";
+    let movie = Movie::parse_fm2(text).unwrap();
+
+    assert_eq!(movie.rerecords, 5);
+    assert_eq!(movie.rom_filename, "smb");
+    assert_eq!(movie.frames.len(), 3);
+    assert!(movie.frames[0].reset);
+    assert_eq!(movie.frames[1].ports, [Buttons::RIGHT | Buttons::A, Buttons::empty()]);
+    assert_eq!(movie.frames[2].ports, [Buttons::UP | Buttons::START, Buttons::DOWN | Buttons::B]);
+
+    assert!(Movie::parse_fm2("binary 1\n").is_err());
+}
+
+#[test]
+fn reads_bk2() {
+    let log = "[Input]\nLogKey:#Reset|Power|#P1 Up|P1 Down|P1 Left|P1 Right|P1 Start|P1 Select|P1 B|P1 A|\n\
+               |..|........|\n|r.|........|\n|..|U......A|\n|..|...R..B.|\n[/Input]\n";
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    zip.start_file("Header.txt", FileOptions::default()).unwrap();
+    zip.write_all(b"MovieVersion BizHawk v2.0\nrerecordCount 7\nGameName Some Game\n").unwrap();
+    zip.start_file("Input Log.txt", FileOptions::default()).unwrap();
+    zip.write_all(log.as_bytes()).unwrap();
+    let data = zip.finish().unwrap().into_inner();
+
+    let movie = Movie::read_bk2(Cursor::new(data)).unwrap();
+    assert_eq!(movie.rerecords, 7);
+    assert_eq!(movie.rom_filename, "Some Game");
+    assert_eq!(movie.frames.len(), 4);
+    assert!(movie.frames[1].reset);
+    assert_eq!(movie.frames[2].ports[0], Buttons::UP | Buttons::A);
+    assert_eq!(movie.frames[3].ports[0], Buttons::RIGHT | Buttons::B);
+}
+
+#[test]
+fn playback_matches_recording() {
+    let mut recorder = common::program_system(INPUT_PROGRAM);
+    let movie = record(&mut recorder, 120);
+    assert_eq!(movie.frames.len(), 120);
+    assert!(movie.ram_hashes.contains_key(&60));
+
+    let mut player = common::program_system(INPUT_PROGRAM);
+    play(&mut player, Movie::parse_fm2(&movie.to_fm2()).unwrap());
+    let session = player.movie().unwrap();
+    assert!(session.desyncs.is_empty());
+    assert_eq!(player.save_state(), recorder.save_state());
+
+    // And again from a save state part way in
+    let mut recorder = common::program_system(INPUT_PROGRAM);
+    run_frames(&mut recorder, 10);
+    recorder.record_movie(true);
+    for frame in 0..30 {
+        press(&mut recorder, pattern(frame));
+        recorder.run_frame();
+    }
+    let movie = recorder.stop_movie().unwrap().movie;
+
+    let mut player = common::program_system(INPUT_PROGRAM);
+    play(&mut player, movie);
+    assert_eq!(player.save_state(), recorder.save_state());
+}
+
+#[test]
+fn detects_desync() {
+    let mut system = common::program_system(INPUT_PROGRAM);
+    let mut movie = record(&mut system, 90);
+    *movie.ram_hashes.get_mut(&60).unwrap() ^= 1;
+
+    let mut system = common::program_system(INPUT_PROGRAM);
+    play(&mut system, movie);
+    let desyncs = &system.movie().unwrap().desyncs;
+    assert_eq!(desyncs.len(), 1);
+    assert_eq!(desyncs[0].frame, 60);
+}
+
+#[test]
+fn read_only_playback_follows_loaded_states() {
+    let mut system = common::program_system(INPUT_PROGRAM);
+    let movie = record(&mut system, 60);
+
+    let mut system = common::program_system(INPUT_PROGRAM);
+    system.play_movie(movie.clone()).unwrap();
+    run_frames(&mut system, 20);
+    let state = system.save_state();
+    run_frames(&mut system, 20);
+    system.load_state(&state).unwrap();
+    run_frames(&mut system, 50);
+
+    let session = system.movie().unwrap();
+    assert_eq!(session.mode, MovieMode::Finished);
+    assert_eq!(session.movie, movie);
+    assert!(session.desyncs.is_empty());
+}
+
+#[test]
+fn read_write_takes_over_recording() {
+    let mut system = common::program_system(INPUT_PROGRAM);
+    let movie = record(&mut system, 60);
+
+    let mut system = common::program_system(INPUT_PROGRAM);
+    system.play_movie(movie.clone()).unwrap();
+    system.movie_mut().unwrap().read_only = false;
+    run_frames(&mut system, 20);
+    let state = system.save_state();
+    run_frames(&mut system, 20);
+    system.load_state(&state).unwrap();
+    for _ in 0..10 {
+        press(&mut system, Buttons::B);
+        system.run_frame();
+    }
+
+    let session = system.stop_movie().unwrap();
+    assert_eq!(session.mode, MovieMode::Recording);
+    assert_eq!(session.movie.rerecords, 1);
+    assert_eq!(session.movie.frames.len(), 30);
+    assert_eq!(session.movie.frames[..20], movie.frames[..20]);
+    assert!(session.movie.frames[20..].iter().all(|frame| frame.ports[0] == Buttons::B));
+
+    // Recording straight on past the end of a read-write movie
+    let mut system = common::program_system(INPUT_PROGRAM);
+    system.play_movie(movie).unwrap();
+    system.movie_mut().unwrap().read_only = false;
+    run_frames(&mut system, 70);
+    let session = system.stop_movie().unwrap();
+    assert_eq!(session.mode, MovieMode::Recording);
+    assert_eq!(session.movie.frames.len(), 70);
+    assert_eq!(session.movie.rerecords, 0);
+}
+
+#[test]
+fn recorded_reset_plays_back() {
+    let mut recorder = common::program_system(INPUT_PROGRAM);
+    recorder.record_movie(false);
+    run_frames(&mut recorder, 10);
+    recorder.movie_mut().unwrap().request_reset();
+    run_frames(&mut recorder, 10);
+    let movie = recorder.stop_movie().unwrap().movie;
+    assert!(movie.frames[10].reset);
+
+    let mut player = common::program_system(INPUT_PROGRAM);
+    play(&mut player, movie);
+    assert_eq!(player.save_state(), recorder.save_state());
+}
@@ -0,0 +1,86 @@
+mod common;
+
+use nes::{
+    ntsc::{NtscFilter, NtscFilterSettings, NTSC_WIDTH},
+    palette::Palette,
+    ppu::{SCREEN_HEIGHT, SCREEN_WIDTH},
+};
+
+fn flat(pixel: u16) -> Vec<u16> {
+    vec![pixel; SCREEN_WIDTH * SCREEN_HEIGHT]
+}
+
+// Thin vertical stripes, the kind of detail that shows up as colour on a TV
+fn stripes(a: u16, b: u16) -> Vec<u16> {
+    (0..SCREEN_WIDTH * SCREEN_HEIGHT).map(|i| if i % 2 == 0 { a } else { b }).collect()
+}
+
+fn colourfulness(output: &[[u8; 3]]) -> u32 {
+    output.iter().map(|[r, g, b]| (*r.max(g).max(b) - *r.min(g).min(b)) as u32).sum()
+}
+
+fn close(a: [u8; 3], b: [u8; 3], tolerance: i32) -> bool {
+    a.iter().zip(b.iter()).all(|(a, b)| (*a as i32 - *b as i32).abs() <= tolerance)
+}
+
+#[test]
+fn output_size_test() {
+    let output = NtscFilter::default().apply(&flat(0x0f), 0);
+    assert_eq!(output.len(), NTSC_WIDTH * SCREEN_HEIGHT);
+    assert!(output.iter().all(|pixel| *pixel == [0, 0, 0]));
+}
+
+#[test]
+fn flat_colour_test() {
+    // Away from the edges a solid colour decodes to what the generated palette says it is. Sharpening lets the square
+    // wave's harmonics into luma, so that's left off.
+    let filter = NtscFilter::new(NtscFilterSettings { sharpness: 0.0, ..NtscFilterSettings::new() });
+    let palette = Palette::generate(&filter.settings.colour);
+    for pixel in [0x10, 0x30, 0x16, 0x2a, 0x12 | 0x40] {
+        let output = filter.apply(&flat(pixel), 0);
+        let middle = output[100 * NTSC_WIDTH + NTSC_WIDTH / 2];
+        assert!(close(middle, palette.rgb(pixel), 2), "{:#x}: {:?} vs {:?}", pixel, middle, palette.rgb(pixel));
+    }
+}
+
+#[test]
+fn dot_crawl_test() {
+    let filter = NtscFilter::default();
+    let image = stripes(0x30, 0x0f);
+    let frames: Vec<_> = (0..4).map(|frame| filter.apply(&image, frame)).collect();
+    assert_ne!(frames[0], frames[1]);
+    assert_ne!(frames[1], frames[2]);
+    assert_ne!(frames[0], frames[2]);
+    // Three frames round and the phase is back where it started
+    assert_eq!(frames[0], frames[3]);
+}
+
+#[test]
+fn artifacts_test() {
+    let image = stripes(0x30, 0x0f);
+    let rainbow = colourfulness(&NtscFilter::default().apply(&image, 0));
+
+    let settings = NtscFilterSettings { artifacts: 0.0, ..NtscFilterSettings::new() };
+    let clean = colourfulness(&NtscFilter::new(settings).apply(&image, 0));
+    assert!(rainbow > 0);
+    assert!(clean < rainbow / 2, "{} vs {}", clean, rainbow);
+}
+
+#[test]
+fn fringing_test() {
+    // Half red, half blue, the colour edge in the middle
+    let image: Vec<u16> = (0..SCREEN_WIDTH * SCREEN_HEIGHT).map(|i| if i % SCREEN_WIDTH < 128 { 0x16 } else { 0x12 }).collect();
+    let edge = |fringing| {
+        let settings = NtscFilterSettings { fringing, sharpness: 1.0, ..NtscFilterSettings::new() };
+        let output = NtscFilter::new(settings).apply(&image, 0);
+        output[100 * NTSC_WIDTH + NTSC_WIDTH / 2 - 8..100 * NTSC_WIDTH + NTSC_WIDTH / 2 + 8].to_vec()
+    };
+    assert_ne!(edge(0.0), edge(1.0));
+
+    // Solid colour has no edges to fringe
+    let flat = |fringing| {
+        let settings = NtscFilterSettings { fringing, sharpness: 1.0, ..NtscFilterSettings::new() };
+        NtscFilter::new(settings).apply(&flat(0x16), 0)[100 * NTSC_WIDTH + NTSC_WIDTH / 2]
+    };
+    assert!(close(flat(0.0), flat(1.0), 1));
+}
@@ -0,0 +1,105 @@
+mod common;
+
+use nes::{
+    palette::{NtscParameters, Palette, DEFAULT_PALETTE, PALETTE_ENTRIES},
+    system::{ConsoleSystem, Region},
+};
+
+#[test]
+fn default_palette_test() {
+    let palette = Palette::new();
+    assert_eq!(palette.colours().len(), PALETTE_ENTRIES);
+    assert_eq!(palette.rgb(0x30), [0xff, 0xff, 0xff]);
+
+    // Red emphasis keeps red and dims the rest, except on the blacks
+    let [r, g, b] = palette.rgb(0x30 | 0x40);
+    assert_eq!(r, 0xff);
+    assert!(g < 0xff && b < 0xff);
+    assert_eq!(palette.rgb(0x0f | 0x1c0), [0, 0, 0]);
+}
+
+#[test]
+fn pal_file_test() {
+    let small: Vec<u8> = DEFAULT_PALETTE.iter().flatten().copied().collect();
+    let palette = Palette::from_bytes(&small).unwrap();
+    assert_eq!(palette, Palette::new());
+
+    // Full files keep their emphasis colours as they are
+    let mut full = palette.to_bytes();
+    assert_eq!(full.len(), 1536);
+    full[0x40 * 3..0x40 * 3 + 3].copy_from_slice(&[1, 2, 3]);
+    assert_eq!(Palette::from_bytes(&full).unwrap().rgb(0x40), [1, 2, 3]);
+
+    assert!(Palette::from_bytes(&small[..190]).is_err());
+}
+
+#[test]
+fn generated_palette_test() {
+    let palette = Palette::generate(&NtscParameters::new());
+
+    // The grey column has no chroma
+    for grey in [0x00, 0x10, 0x20, 0x2d, 0x3d] {
+        let [r, g, b] = palette.rgb(grey);
+        assert!(r.abs_diff(g) <= 1 && g.abs_diff(b) <= 1, "${:02X} is {:?}", grey, [r, g, b]);
+    }
+    assert_eq!(palette.rgb(0x0d), [0, 0, 0]);
+    assert_eq!(palette.rgb(0x30), [0xff, 0xff, 0xff]);
+
+    // Close enough to the built in palette, which came from the same model
+    for (index, expected) in DEFAULT_PALETTE.iter().enumerate() {
+        let actual = palette.rgb(index as u16);
+        assert!(actual.iter().zip(expected).all(|(a, e)| a.abs_diff(*e) <= 0x30), "${:02X} is {:?}", index, actual);
+    }
+
+    let dull = Palette::generate(&NtscParameters { saturation: 0.0, ..NtscParameters::new() });
+    let [r, g, b] = dull.rgb(0x16);
+    assert!(r.abs_diff(g) <= 1 && g.abs_diff(b) <= 1);
+
+    let dark = Palette::generate(&NtscParameters { brightness: -0.2, ..NtscParameters::new() });
+    assert!(dark.rgb(0x10)[0] < palette.rgb(0x10)[0]);
+}
+
+#[test]
+fn greyscale_test() {
+    let mut palette = Palette::new();
+    palette.greyscale = true;
+    assert_eq!(palette.rgb(0x16), palette.rgb(0x10));
+    assert_eq!(palette.rgb(0x2a | 0x40), palette.rgb(0x20 | 0x40));
+}
+
+#[test]
+fn rgb_ppu_test() {
+    let palette = Palette::rgb_ppu();
+    assert_eq!(palette.rgb(0x20), [0xff, 0xff, 0xff]);
+    assert_eq!(palette.rgb(0x16), [0xff, 0, 0]);
+    // Emphasis turns the channel all the way up
+    assert_eq!(palette.rgb(0x0f | 0x100), [0, 0, 0xff]);
+
+    let mut order: [u8; 64] = std::array::from_fn(|index| index as u8);
+    order.swap(0x00, 0x20);
+    assert_eq!(Palette::rgb_ppu_reordered(&order).rgb(0x00), [0xff, 0xff, 0xff]);
+}
+
+#[test]
+fn rp2c04_test() {
+    // 755, 572, 507 and 430 in 3-bit RGB
+    let expected = [(1, 0x00, [0xff, 0xb6, 0xb6]), (2, 0x03, [0xb6, 0xff, 0x48]), (3, 0x00, [0xb6, 0x00, 0xff]), (4, 0x00, [0x91, 0x6d, 0x00])];
+    for (revision, index, rgb) in expected {
+        assert_eq!(Palette::rp2c04(revision).unwrap().rgb(index), rgb, "RP2C04-000{}", revision);
+    }
+    // A grey the 2C03 doesn't have
+    assert_eq!(Palette::rp2c04(1).unwrap().rgb(0x06), [0x48, 0x48, 0x48]);
+    assert!(Palette::rp2c04(0).is_none() && Palette::rp2c04(5).is_none());
+}
+
+#[test]
+fn ppumask_output_test() {
+    let mut system = common::program_system(common::LOOP_PROGRAM);
+    system.cpu.mapper.write(0x2001, 0x21);
+    assert_eq!(system.cpu.mapper.get_ppu().output_pixel(0x16), 0x10 | 0x40);
+
+    // PAL swaps the red and green bits
+    let mut system = ConsoleSystem::with_region(common::program_image(common::LOOP_PROGRAM), Region::Pal);
+    system.cpu.mapper.write(0x2001, 0x20);
+    assert_eq!(system.cpu.mapper.get_ppu().output_pixel(0x16), 0x16 | 0x80);
+}
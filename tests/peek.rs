@@ -0,0 +1,68 @@
+mod common;
+
+use nes::input::{Buttons, Port, StandardController};
+
+#[test]
+fn peek_has_no_side_effects_test() {
+    let mut system = common::program_system(common::LOOP_PROGRAM);
+    let mapper = system.cpu.mapper.as_mut();
+
+    // Peeking $2002 between the two $2006 writes leaves the latch alone, reading it doesn't
+    mapper.write(0x2006, 0x21);
+    mapper.peek(0x2002);
+    mapper.write(0x2006, 0x08);
+    assert_eq!(mapper.get_ppu().vram_address, 0x2108);
+    mapper.write(0x2006, 0x21);
+    mapper.read(0x2002);
+    mapper.write(0x2006, 0x08);
+    assert_eq!(mapper.get_ppu().vram_address, 0x0808);
+
+    mapper.get_input().device_mut::<StandardController>(Port::One).unwrap().buttons = Buttons::A;
+    mapper.write(0x4016, 1);
+    mapper.write(0x4016, 0);
+    assert_eq!(mapper.peek(0x4016), 0x40);
+    assert_eq!(mapper.read(0x4016) & 1, 1);
+}
+
+#[test]
+fn vram_test() {
+    let mut system = common::program_system(common::LOOP_PROGRAM);
+    let mapper = system.cpu.mapper.as_mut();
+
+    for (address, data) in [(0x2001u16, 0x11u8), (0x3f10, 0x22)] {
+        mapper.write(0x2006, (address >> 8) as u8);
+        mapper.write(0x2006, address as u8);
+        mapper.write(0x2007, data);
+    }
+    // Horizontal mirroring, and $3F10 is $3F00
+    assert_eq!(mapper.peek_ppu(0x2401), 0x11);
+    assert_eq!(mapper.peek_ppu(0x2801), 0x00);
+    assert_eq!(mapper.peek_ppu(0x3f00), 0x22);
+
+    // $2007 reads come through the buffer, peeking shows what the next read returns without moving on
+    mapper.write(0x2006, 0x20);
+    mapper.write(0x2006, 0x01);
+    mapper.read(0x2007);
+    assert_eq!(mapper.peek(0x2007), 0x11);
+    assert_eq!(mapper.peek(0x2007), 0x11);
+    assert_eq!(mapper.read(0x2007), 0x11);
+    assert_eq!(mapper.get_ppu().vram_address, 0x2003);
+
+    mapper.poke_ppu(0x0010, 0x5a);
+    assert_eq!(mapper.peek_ppu(0x0010), 0x5a);
+}
+
+#[test]
+fn poke_test() {
+    let mut system = common::program_system(common::LOOP_PROGRAM);
+    let mapper = system.cpu.mapper.as_mut();
+
+    mapper.poke(0x0810, 0x99);
+    assert_eq!(mapper.peek(0x0010), 0x99);
+
+    // ROM can be patched, and NROM-128 shows it in both halves
+    mapper.poke(0x8001, 0x05);
+    assert_eq!((mapper.peek(0x8001), mapper.peek(0xc001)), (0x05, 0x05));
+    mapper.write(0x8001, 0x07);
+    assert_eq!(mapper.peek(0x8001), 0x05);
+}
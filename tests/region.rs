@@ -0,0 +1,104 @@
+mod common;
+
+use std::io::Cursor;
+
+use nes::{
+    apu::SAMPLE_RATE,
+    ppu::Status,
+    roms::{RomImage, TVSystem},
+    system::{ConsoleSystem, Region, NTSC, PAL},
+};
+
+fn region_system(region: Region) -> ConsoleSystem {
+    let mut system = ConsoleSystem::with_region(common::program_image(common::LOOP_PROGRAM), region);
+    system.reset();
+    system
+}
+
+// CPU cycles from the start of one frame to the start of the next
+fn frame_cycles(system: &mut ConsoleSystem) -> u64 {
+    let frame = system.cpu.mapper.get_ppu().frame;
+    while system.cpu.mapper.get_ppu().frame == frame {
+        system.cycle();
+    }
+
+    let start = system.clock.cycle;
+    let frame = frame + 1;
+    while system.cpu.mapper.get_ppu().frame == frame {
+        system.cycle();
+    }
+    (system.clock.cycle - start) / system.clock.cpu_divider
+}
+
+#[test]
+fn header_picks_region_test() {
+    assert_eq!(common::program_system(common::LOOP_PROGRAM).region, Region::Ntsc);
+
+    // NES 2.0 with the Dendy timing value in byte 12
+    let mut data = b"NES\x1a\x01\x01\x00\x08\x00\x00\x00\x00\x03\x00\x00\x00".to_vec();
+    data.extend(vec![0xea; 0x4000]);
+    data.extend(vec![0; 0x2000]);
+    let image = RomImage::from(&mut Cursor::new(data)).unwrap();
+    assert_eq!(image.header.tv_system, TVSystem::Dendy);
+    assert_eq!(ConsoleSystem::new(image).region, Region::Dendy);
+
+    // iNES 1.0 has no Dendy value in byte 9
+    let mut data = b"NES\x1a\x01\x01\x00\x00\x00\x03\x00\x00\x00\x00\x00\x00".to_vec();
+    data.extend(vec![0xea; 0x4000]);
+    data.extend(vec![0; 0x2000]);
+    assert!(RomImage::from(&mut Cursor::new(data)).is_err());
+}
+
+#[test]
+fn frame_length_test() {
+    // 262 * 341 / 3 and 312 * 341 / 3.2 and / 3, give or take the fraction
+    let expected = [(Region::Ntsc, 29_780..=29_781), (Region::Pal, 33_247..=33_248), (Region::Dendy, 35_464..=35_464)];
+    for (region, cycles) in expected {
+        let mut system = region_system(region);
+        let actual = frame_cycles(&mut system);
+        assert!(cycles.contains(&actual), "{:?} frame took {} cycles", region, actual);
+    }
+}
+
+#[test]
+fn vblank_scanlines_test() {
+    for (region, first, last) in [(Region::Ntsc, 241, 260), (Region::Pal, 241, 310), (Region::Dendy, 291, 310)] {
+        let profile = region.profile();
+        assert_eq!((profile.vblank_scanline(), profile.vblank_scanline() + profile.vblank_scanlines - 1), (first, last));
+        assert_eq!(profile.pre_render_scanline(), profile.scanlines_per_frame - 1);
+
+        let mut system = region_system(region);
+        let ppu = system.cpu.mapper.get_ppu();
+        ppu.status = Status::empty();
+        while (ppu.scanline, ppu.dot) != (first, 1) {
+            assert!(!ppu.status.contains(Status::VBLANK), "{:?} vblank early at {}", region, ppu.scanline);
+            ppu.cycle();
+        }
+        while (ppu.scanline, ppu.dot) != (last + 1, 1) {
+            assert!(ppu.status.contains(Status::VBLANK), "{:?} vblank ended at {}", region, ppu.scanline);
+            ppu.cycle();
+        }
+        assert!(!ppu.status.contains(Status::VBLANK));
+    }
+}
+
+#[test]
+fn apu_tables_test() {
+    assert_eq!(Region::Dendy.profile().noise_periods, NTSC.noise_periods);
+    assert_eq!(NTSC.noise_periods[15], 4068);
+    assert_eq!(PAL.dmc_periods[0], 398);
+    assert_eq!(PAL.frame_counter_steps[3], 33253);
+}
+
+#[test]
+fn pal_sample_rate_test() {
+    let mut system = region_system(Region::Pal);
+
+    // A tenth of a second of PAL CPU time
+    for _ in 0..166_260 {
+        system.cycle();
+    }
+
+    let samples = system.cpu.mapper.get_alu().samples.len() as u32;
+    assert!(samples.abs_diff(SAMPLE_RATE / 10) <= 1, "got {} samples", samples);
+}
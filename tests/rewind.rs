@@ -0,0 +1,118 @@
+mod common;
+
+use common::{pattern, press, INPUT_PROGRAM};
+use nes::{
+    input::Buttons,
+    system::{ConsoleSystem, RewindSettings},
+};
+
+// Runs `frames` frames with `buttons`, returning the state each one started from
+fn run_frames(system: &mut ConsoleSystem, frames: usize, buttons: impl Fn(usize) -> Buttons) -> Vec<Vec<u8>> {
+    (0..frames)
+        .map(|frame| {
+            press(system, buttons(frame));
+            let state = system.save_state();
+            system.run_frame();
+            state
+        })
+        .collect()
+}
+
+#[test]
+fn rewind_test() {
+    let mut system = common::program_system(INPUT_PROGRAM);
+    system.enable_rewind(RewindSettings { interval: 4, ..RewindSettings::new() });
+    let states = run_frames(&mut system, 40, pattern);
+    assert_eq!(system.rewind_buffer().unwrap().snapshots(), 10);
+
+    // Every frame back matches what was there the first time, snapshot or not
+    for state in states.iter().rev() {
+        assert!(system.rewind_frame());
+        assert!(system.save_state() == *state);
+    }
+    assert_eq!(system.cpu.mapper.get_ppu().frame, 0);
+    assert!(!system.rewind_frame());
+}
+
+#[test]
+fn rewind_and_replay_test() {
+    let mut system = common::program_system(INPUT_PROGRAM);
+    system.enable_rewind(RewindSettings { interval: 4, ..RewindSettings::new() });
+    run_frames(&mut system, 30, pattern);
+    for _ in 0..10 {
+        assert!(system.rewind_frame());
+    }
+    assert_eq!(system.cpu.mapper.get_ppu().frame, 20);
+
+    // Playing on with different input records a new future, and rewinding goes back through that one
+    let states = run_frames(&mut system, 6, |_| Buttons::A);
+    for state in states.iter().rev() {
+        assert!(system.rewind_frame());
+        assert!(system.save_state() == *state);
+    }
+    assert!(system.rewind_frame());
+    assert_eq!(system.cpu.mapper.get_ppu().frame, 19);
+}
+
+#[test]
+fn load_state_starts_over_test() {
+    let mut system = common::program_system(INPUT_PROGRAM);
+    system.enable_rewind(RewindSettings { interval: 4, ..RewindSettings::new() });
+    let states = run_frames(&mut system, 30, pattern);
+
+    // Back to frame 10 with a loaded state, then on with different input
+    system.load_state(&states[10]).unwrap();
+    assert_eq!(system.rewind_buffer().unwrap().snapshots(), 0);
+    let new_states = run_frames(&mut system, 10, |_| Buttons::A);
+    assert_eq!(system.rewind_buffer().unwrap().oldest_frame(), Some(12));
+
+    // Rewinding goes back through the new frames only, never to the abandoned ones
+    for state in new_states[2..].iter().rev() {
+        assert!(system.rewind_frame());
+        assert!(system.save_state() == *state);
+    }
+    assert!(!system.rewind_frame());
+
+    system.reset();
+    assert_eq!(system.rewind_buffer().unwrap().snapshots(), 0);
+}
+
+#[test]
+fn compression_test() {
+    let mut system = common::program_system(common::LOOP_PROGRAM);
+    let state_size = system.save_state().len();
+    system.enable_rewind(RewindSettings { interval: 1, ..RewindSettings::new() });
+    run_frames(&mut system, 100, |_| Buttons::empty());
+
+    // Nothing much changes frame to frame, so the older snapshots are far smaller than whole states
+    let buffer = system.rewind_buffer().unwrap();
+    assert_eq!(buffer.snapshots(), 100);
+    assert!(buffer.memory_used() < state_size * 2, "{} bytes for {} byte states", buffer.memory_used(), state_size);
+}
+
+#[test]
+fn budget_test() {
+    let mut system = common::program_system(INPUT_PROGRAM);
+    let budget = system.save_state().len() + 4096;
+    system.enable_rewind(RewindSettings { interval: 2, budget });
+    run_frames(&mut system, 200, pattern);
+
+    let buffer = system.rewind_buffer().unwrap();
+    assert!(buffer.memory_used() <= budget);
+    let oldest = buffer.oldest_frame().unwrap();
+    assert!(oldest > 0 && oldest < 200);
+
+    // As far back as the oldest snapshot and no further
+    while system.cpu.mapper.get_ppu().frame > oldest {
+        assert!(system.rewind_frame());
+    }
+    assert!(!system.rewind_frame());
+}
+
+#[test]
+fn disabled_test() {
+    let mut system = common::program_system(common::LOOP_PROGRAM);
+    system.run_frame();
+    assert!(system.rewind_buffer().is_none());
+    assert!(!system.rewind_frame());
+}
@@ -0,0 +1,86 @@
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use nes::testing::{Manifest, Outcome, PassCriteria};
+
+#[test]
+fn manifest_parse_test() {
+    let manifest = Manifest::parse(r#"
+        [[test]]
+        rom = "a.nes"
+        pass = "blargg"
+
+        [[test]]
+        rom = "b.nes"
+        subsystem = "ppu"
+        frames = 30
+        pass = { framebuffer_hash = "0123456789abcdef" }
+        expected_failure = true
+
+        [[test]]
+        rom = "c.nes"
+        pass = { ram = { address = 0x00f0, value = 0x01 } }
+    "#).unwrap();
+
+    assert_eq!(manifest.tests.len(), 3);
+    assert_eq!(manifest.tests[0].pass, PassCriteria::Blargg);
+    assert_eq!(manifest.tests[0].subsystem, "misc");
+    assert_eq!(manifest.tests[1].frames, 30);
+    assert!(manifest.tests[1].expected_failure);
+    assert_eq!(manifest.tests[2].pass, PassCriteria::Ram { address: 0xf0, value: 1 });
+
+    let report = manifest.run();
+    assert!(report.results.iter().all(|result| result.outcome == Outcome::Missing));
+    assert_eq!(report.unexpected().count(), 0);
+}
+
+#[test]
+fn unsupported_mapper_fails_entry() {
+    let directory = env::temp_dir().join(format!("rom_suite_mapper_{}", std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    // MMC1 header, then 16K PRG and 8K CHR
+    let mut rom = b"NES\x1a\x01\x01\x10\x00\x00\x00\x00\x00\x00\x00\x00\x00".to_vec();
+    rom.resize(16 + 0x6000, 0);
+    fs::write(directory.join("mmc1.nes"), rom).unwrap();
+
+    let mut manifest = Manifest::parse("[[test]]\nrom = \"mmc1.nes\"\npass = \"blargg\"\n").unwrap();
+    manifest.root = directory.clone();
+    let report = manifest.run();
+    fs::remove_dir_all(&directory).unwrap();
+
+    assert_eq!(report.results[0].outcome, Outcome::Failed("Unsupported mapper".to_string()));
+}
+
+#[test]
+fn crashing_rom_fails_entry() {
+    let directory = env::temp_dir().join(format!("rom_suite_crash_{}", std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    // NROM whose reset vector lands on $0B, an opcode the CPU doesn't do
+    let mut rom = b"NES\x1a\x01\x01\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00".to_vec();
+    rom.resize(16 + 0x4000, 0x0b);
+    rom[16 + 0x3ffc] = 0x00;
+    rom[16 + 0x3ffd] = 0x80;
+    rom.resize(16 + 0x6000, 0);
+    fs::write(directory.join("crash.nes"), rom).unwrap();
+
+    let text = "[[test]]\nrom = \"crash.nes\"\npass = \"blargg\"\n[[test]]\nrom = \"missing.nes\"\npass = \"blargg\"\n";
+    let mut manifest = Manifest::parse(text).unwrap();
+    manifest.root = directory.clone();
+    let report = manifest.run();
+    fs::remove_dir_all(&directory).unwrap();
+
+    assert!(matches!(&report.results[0].outcome, Outcome::Failed(reason) if reason.contains("Unsupported opcode 0b")));
+    // And the suite carries on past it
+    assert_eq!(report.results[1].outcome, Outcome::Missing);
+}
+
+#[test]
+fn rom_suite() {
+    let manifest = Manifest::load(Path::new("tests/roms.toml")).expect("Manifest load error");
+    let report = manifest.run();
+    println!("{}", report.matrix());
+
+    let unexpected: Vec<_> = report.unexpected().map(|result| result.name.as_str()).collect();
+    assert!(unexpected.is_empty(), "Unexpected results: {:?}", unexpected);
+}
@@ -1,100 +1,29 @@
-use std::{fs::File};
+use std::fs::{self, File};
 
-use nes::{roms::RomImage, system::{ConsoleSystem}};
+use nes::{roms::RomImage, system::{ConsoleSystem}, testing::{BlarggHarness, TraceComparison}};
 
 #[test]
 fn nes_test() {
     let mut rom_file = File::open("./nes-test-roms/other/nestest.nes").expect("Test rom open error");
     let image = RomImage::from(&mut rom_file).expect("Test rom load error");
+    let log = fs::read_to_string("./nes-test-roms/other/nestest.log").expect("Test log open error");
     let mut system = ConsoleSystem::new(image);
-    // system.reset();
+    // Automated mode starts at $C000 instead of the reset vector
     system.cpu.pc = 0xc000;
 
-    // Run tests
-    while system.cpu.cycle < 26554 {
-        system.cycle();
+    let comparison = TraceComparison::new();
+    if let Err(divergence) = comparison.run(&mut system, &log).expect("Test log read error") {
+        panic!("{}", divergence);
     }
-
-    assert_eq!(system.cpu.mapper.read(0x6000), 0x00);
-    assert_eq!(system.cpu.mapper.read(0x6001), 0xde);
-    assert_eq!(system.cpu.mapper.read(0x6002), 0xb0);
-    assert_eq!(system.cpu.mapper.read(0x6003), 0x61);
-    
 }
 
 #[test]
 fn basics() {
-    
-    //let cpu = crate::nes::cpu::RP2A03::new();
-    // let cpu = <Mos6502 as RP2A03>::new();
-    // assert_eq!(cpu.p, 0x34);
-    // assert_eq!(cpu.a, 0x00);
-    // assert_eq!(cpu.x, 0x00);
-    // assert_eq!(cpu.y, 0x00);
-    // assert_eq!(cpu.s, 0xFD);
-    
     let mut rom_file = File::open("./nes-test-roms/instr_test-v5/rom_singles/01-basics.nes").expect("Test rom open error");
-    // let mut rom_file = File::open("./nes-test-roms/other/nestest.nes").expect("Test rom open error");
     let image = RomImage::from(&mut rom_file).expect("Test rom load error");
     let mut system = ConsoleSystem::new(image);
     system.reset();
 
-    // Test rom init cycles
-    // while system.cpu.mapper.read(0x6000) != 0x80 {
-    //     system.cycle();
-    // }
-
-    println!("INIT FINISHED");
-
-    // Run tests
-    // while system.cpu.mapper.read(0x6000) == 0x80 && system.cpu.cycle < 26554 {
-    //     system.cycle();
-    // }
-
-    assert_eq!(system.cpu.mapper.read(0x6000), 0x00);
-    assert_eq!(system.cpu.mapper.read(0x6001), 0xde);
-    assert_eq!(system.cpu.mapper.read(0x6002), 0xb0);
-    assert_eq!(system.cpu.mapper.read(0x6003), 0x61);
-    
-    // Assert.AreEqual(0xDE, platform.Read(new Address(0x6001)));
-    // Assert.AreEqual(0xB0, platform.Read(new Address(0x6002)));
-    // Assert.AreEqual(0x61, platform.Read(new Address(0x6003)));
-    // system.Step();
-    //let mut reader = BufReader::new(romFile);    
-    
-    //let mut image = Vec::<u8>::new();
-    // romFile.read_to_end(&mut image).expect("Test rom read error");
-    
-    // rom.stream_len();
-    // rom.read_to_end(buf: &mut Vec<u8>)
-
-
-    // var romPath = Path.Combine(FindTestRomDirectory(), "instr_test-v3/rom_singles/", path);
-    // using var reader = new BinaryReader(File.OpenRead(romPath));
-    // var mapper = (Mapper0)Mapper.FromImage(RomImage.From(reader));
-    // var platform = new NesBus(mapper);
-    // platform.Reset();
-    // int result;
-    // // Test warmup, waiting for $6000 to go non-zero (usually 0x80 to indicate running state
-    // while (platform.Read(new Address(0x6000)) == 0)
-    // {
-    //     platform.DoCycle();
-    // }
-
-    // while ((result = platform.Read(new Address(0x6000))) == 0x80)
-    // {
-    //     platform.DoCycle();
-    // }
-
-    // // Checking for DEBO61
-    // Assert.AreEqual(0xDE, platform.Read(new Address(0x6001)));
-    // Assert.AreEqual(0xB0, platform.Read(new Address(0x6002)));
-    // Assert.AreEqual(0x61, platform.Read(new Address(0x6003)));
-    
-    // using (var debugPin = mapper.Ram.Slice(4).Pin())
-    // {
-    //     var errorMessage = Marshal.PtrToStringAnsi((IntPtr)debugPin.Pointer);
-    //     Assert.AreEqual(0, result, errorMessage);
-    // }
+    let result = BlarggHarness::new().run(&mut system).expect("Test rom didn't finish");
+    assert_eq!(result.code, 0, "{}", result.message);
 }
-
@@ -1,5 +1,5 @@
-use std::{fs::File, path::Path};
-use nes::roms::RomImage;
+use std::{fs::File, io::Cursor, path::Path};
+use nes::{input::{InputLayout, PortDeviceKind}, roms::RomImage, system::ConsoleSystem};
 
 #[test]
 fn basic_load_test() {
@@ -7,4 +7,32 @@ fn basic_load_test() {
     let mut rom_file = File::open(path).expect("Test rom open error");
     let image = RomImage::from(&mut rom_file).unwrap();
     assert_eq!(image.header.program_rom_size, 2);
-}
\ No newline at end of file
+}
+
+#[test]
+fn nes2_header_load_test() {
+    let mut data = b"NES\x1a\x01\x01\x00\x08\x00\x00\x07\x00\x00\x00\x00\x02".to_vec();
+    data.resize(16 + 0x4000 + 0x2000, 0);
+    let image = RomImage::from(&mut Cursor::new(data)).unwrap();
+    assert_eq!(image.header.program_ram_size, 1);
+    assert_eq!(image.header.default_expansion_device, Some(0x02));
+    assert_eq!(InputLayout::from_header(&image.header).port2, PortDeviceKind::FourScore);
+
+    // 8K of battery backed PRG-NVRAM and no volatile PRG-RAM
+    let mut data = b"NES\x1a\x01\x01\x02\x08\x00\x00\x70\x00\x00\x00\x00\x00".to_vec();
+    data.resize(16 + 0x4000 + 0x2000, 0);
+    let image = RomImage::from(&mut Cursor::new(data)).unwrap();
+    assert_eq!(image.header.program_ram_size, 1);
+
+    // 16K of PRG-RAM and 64K of PRG-NVRAM, more than NROM can map, still run
+    for (byte10, banks) in [(0x08, 2), (0xa0, 8)] {
+        let mut data = b"NES\x1a\x01\x01\x00\x08\x00\x00\x00\x00\x00\x00\x00\x00".to_vec();
+        data[10] = byte10;
+        data.resize(16 + 0x4000 + 0x2000, 0);
+        let image = RomImage::from(&mut Cursor::new(data)).unwrap();
+        assert_eq!(image.header.program_ram_size, banks);
+        let mut system = ConsoleSystem::new(image);
+        system.cpu.mapper.write(0x7fff, 0x42);
+        assert_eq!(system.cpu.mapper.read(0x7fff), 0x42);
+    }
+}
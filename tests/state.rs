@@ -0,0 +1,72 @@
+mod common;
+
+use common::{program_system, LOOP_PROGRAM};
+use nes::input::{Buttons, InputDevice, Port, StandardController};
+use nes::ppu::PPU;
+
+#[test]
+fn save_state_round_trips_mid_instruction() {
+    let mut original = program_system(LOOP_PROGRAM);
+
+    for cut in 0..120 {
+        let snapshot = original.save_state();
+        let mut restored = program_system(LOOP_PROGRAM);
+        restored.load_state(&snapshot).expect("State load error");
+        assert_eq!(restored.save_state(), snapshot, "reload differs at cycle {}", cut);
+
+        for _ in 0..300 {
+            original.cycle();
+            restored.cycle();
+        }
+        assert_eq!(restored.save_state(), original.save_state(), "diverged after restoring cycle {}", cut);
+
+        // Undo the look-ahead so the next snapshot lands one cycle later
+        original.load_state(&snapshot).unwrap();
+        original.cycle();
+    }
+}
+
+#[test]
+fn load_state_rejects_garbage() {
+    let mut system = program_system(LOOP_PROGRAM);
+    assert!(system.load_state(b"NOPE").is_err());
+
+    let mut state = system.save_state();
+    state[4] = 0xff;
+    assert!(system.load_state(&state).is_err());
+}
+
+#[test]
+fn failed_load_leaves_system_alone() {
+    let mut system = program_system(LOOP_PROGRAM);
+    for _ in 0..100 {
+        system.cycle();
+    }
+    let truncated = system.save_state();
+    for _ in 0..1000 {
+        system.cycle();
+    }
+    let before = system.save_state();
+
+    // Cut off in the middle of the mapper's part, after the clock and CPU have loaded
+    assert!(system.load_state(&truncated[..truncated.len() - 100]).is_err());
+    assert_eq!(system.save_state(), before);
+}
+
+#[test]
+fn save_state_keeps_controller_shift_registers() {
+    let mut system = program_system(LOOP_PROGRAM);
+    let controller = system.input().device_mut::<StandardController>(Port::One).unwrap();
+    controller.buttons = Buttons::A | Buttons::START;
+    controller.strobe(1);
+    controller.strobe(0);
+    let snapshot = system.save_state();
+
+    // Halfway through a read when it's restored, whatever's held now
+    let mut restored = program_system(LOOP_PROGRAM);
+    restored.load_state(&snapshot).unwrap();
+    let ppu = PPU::new();
+    let controller = restored.input().device_mut::<StandardController>(Port::One).unwrap();
+    let bits: Vec<u8> = (0..4).map(|_| controller.read(&ppu)).collect();
+    assert_eq!(bits, [1, 0, 0, 1]);
+}
@@ -0,0 +1,119 @@
+mod common;
+
+use nes::{
+    cpu::trace::{RingBufferSink, TraceFilter, TraceFormat, Tracer, WriterSink},
+    system::ConsoleSystem,
+    testing::{TraceComparison, TraceLine},
+};
+
+const LOOP_TRACE: &str = "\
+8000  A2 00     LDX #$00                        A:00 X:00 Y:00 P:24 SP:FD PPU:  0,  0 CYC:0
+8002  A9 01     LDA #$01                        A:00 X:00 Y:00 P:26 SP:FD PPU:  0,  6 CYC:2
+8004  85 10     STA $10 = 00                    A:01 X:00 Y:00 P:24 SP:FD PPU:  0, 12 CYC:4
+8006  E6 10     INC $10 = 01                    A:01 X:00 Y:00 P:24 SP:FD PPU:  0, 21 CYC:7
+8008  0A        ASL A                           A:01 X:00 Y:00 P:24 SP:FD PPU:  0, 36 CYC:12
+8009  8D 00 02  STA $0200 = 00                  A:02 X:00 Y:00 P:24 SP:FD PPU:  0, 42 CYC:14
+";
+
+fn loop_system() -> ConsoleSystem {
+    let mut system = ConsoleSystem::new(common::program_image(common::LOOP_PROGRAM));
+    system.cpu.pc = 0x8000;
+    system
+}
+
+#[test]
+fn parse_test() {
+    let line = TraceLine::parse("C000  4C F5 C5  JMP $C5F5                       A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 21 CYC:7").unwrap();
+    assert_eq!(line.pc, 0xc000);
+    assert_eq!(line.bytes, vec![0x4c, 0xf5, 0xc5]);
+    assert_eq!((line.a, line.p, line.s), (0x00, 0x24, 0xfd));
+    assert_eq!(line.ppu, Some((0, 21)));
+    assert_eq!(line.cycle, Some(7));
+
+    // The older layout has the PPU dot in CYC and no CPU cycles
+    let line = TraceLine::parse("C000  4C F5 C5  JMP $C5F5                       A:00 X:00 Y:00 P:24 SP:FD CYC:  0 SL:241").unwrap();
+    assert_eq!((line.ppu, line.cycle), (Some((241, 0)), None));
+    let line = TraceLine::parse("C5F5  A2 00     LDX #$00                        A:00 X:00 Y:00 P:24 SP:FD CYC:  9 SL:-1").unwrap();
+    assert_eq!(line.ppu, Some((261, 9)));
+}
+
+#[test]
+fn matching_trace_test() {
+    let comparison = TraceComparison::new();
+    assert_eq!(comparison.run(&mut loop_system(), LOOP_TRACE).unwrap().unwrap(), 6);
+}
+
+#[test]
+fn scanline_layout_trace_test() {
+    // LOOP_TRACE the way older nestest logs write it, starting near the end of the pre-render line so the dots wrap
+    let log = "\
+8000  A2 00     LDX #$00                        A:00 X:00 Y:00 P:24 SP:FD CYC:330 SL:-1
+8002  A9 01     LDA #$01                        A:00 X:00 Y:00 P:26 SP:FD CYC:336 SL:-1
+8004  85 10     STA $10 = 00                    A:01 X:00 Y:00 P:24 SP:FD CYC:  1 SL:0
+8006  E6 10     INC $10 = 01                    A:01 X:00 Y:00 P:24 SP:FD CYC: 10 SL:0
+8008  0A        ASL A                           A:01 X:00 Y:00 P:24 SP:FD CYC: 25 SL:0
+";
+    let comparison = TraceComparison::new();
+    assert_eq!(comparison.run(&mut loop_system(), log).unwrap().unwrap(), 5);
+
+    let log = log.replace("CYC: 25", "CYC: 26");
+    let divergence = comparison.run(&mut loop_system(), &log).unwrap().unwrap_err();
+    assert_eq!((divergence.line, divergence.field), (5, "PPU dot"));
+}
+
+#[test]
+fn divergence_test() {
+    let log = format!("{}800C  6D 00 02  ADC $0200 = 02                  A:03 X:00 Y:00 P:24 SP:FD PPU:  0, 54 CYC:18\n", LOOP_TRACE);
+    let comparison = TraceComparison { context: 2, ..TraceComparison::new() };
+
+    let divergence = comparison.run(&mut loop_system(), &log).unwrap().unwrap_err();
+    assert_eq!(divergence.line, 7);
+    assert_eq!(divergence.field, "A");
+    assert_eq!(divergence.actual.a, 0x02);
+    assert_eq!(divergence.context.len(), 2);
+    assert!(divergence.context[1].starts_with("8009"));
+}
+
+#[test]
+fn trace_sink_test() {
+    let mut system = loop_system();
+    let sink = WriterSink::new(Vec::new(), TraceFormat::Nestest);
+    let filter = TraceFilter { pc: Some(0x8004..=0x8008), ..TraceFilter::default() };
+    system.cpu.tracer = Some(Tracer::with_filter(Box::new(sink), filter));
+
+    for _ in 0..14 {
+        system.cycle();
+    }
+
+    let sink = system.cpu.tracer.as_mut().unwrap().sink_mut::<WriterSink<Vec<u8>>>().unwrap();
+    let text = String::from_utf8(sink.get_ref().clone()).unwrap();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].starts_with("8004  85 10     STA $10     "));
+    assert!(lines[2].ends_with("CYC:12"));
+    assert_eq!(TraceLine::parse(lines[1]).unwrap().a, 0x01);
+}
+
+#[test]
+fn ring_buffer_jam_test() {
+    let program = [
+        0xa9, 0x01,             // 8000 LDA #$01
+        0xe8,                   // 8002 INX
+        0xc8,                   // 8003 INY
+        0x02,                   // 8004 KIL
+    ];
+    let mut system = common::program_system(&program);
+    system.cpu.tracer = Some(Tracer::new(Box::new(RingBufferSink::new(2, TraceFormat::Fceux))));
+
+    for _ in 0..40 {
+        system.cycle();
+    }
+
+    assert!(system.cpu.jammed);
+    assert_eq!(system.cpu.pc, 0x8004);
+
+    let sink = system.cpu.tracer.as_mut().unwrap().sink_mut::<RingBufferSink>().unwrap();
+    let pcs: Vec<u16> = sink.events().map(|event| event.pc).collect();
+    assert_eq!(pcs, vec![0x8003, 0x8004]);
+    assert!(sink.lines()[0].contains("$8003:C8        INY"));
+}